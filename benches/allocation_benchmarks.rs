@@ -0,0 +1,168 @@
+//! Benchmarks measuring the bytes allocated by reads instead of their duration.
+//!
+//! A counting global allocator records every allocation, so each benchmark reports
+//! how many bytes one iteration allocates. Run with `cargo bench --bench
+//! allocation_benchmarks`.
+
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tempfile::TempDir;
+use zenoh::bytes::{Encoding, ZBytes};
+use zenoh::time::{NTP64, Timestamp, TimestampId};
+use zenoh_backend_redb::{RedbBackend, RedbBackendConfig, StoredValue};
+
+/// Total number of bytes allocated by the process.
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+/// System allocator counting allocated bytes.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        // SAFETY: forwarded as is to the system allocator
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: forwarded as is to the system allocator
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size as u64, Ordering::Relaxed);
+        // SAFETY: forwarded as is to the system allocator
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Criterion measurement of the bytes allocated while a benchmark runs.
+struct AllocatedBytes;
+
+impl Measurement for AllocatedBytes {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        ALLOCATED.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: Self::Intermediate) -> Self::Value {
+        ALLOCATED.load(Ordering::Relaxed) - start
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &BytesFormatter
+    }
+}
+
+/// Formats allocated bytes with binary prefixes.
+struct BytesFormatter;
+
+impl BytesFormatter {
+    fn scale(typical_value: f64, values: &mut [f64]) -> &'static str {
+        let (factor, unit) = if typical_value < 1024.0 {
+            (1.0, "B")
+        } else if typical_value < 1024.0 * 1024.0 {
+            (1024.0, "KiB")
+        } else {
+            (1024.0 * 1024.0, "MiB")
+        };
+        for value in values {
+            *value /= factor;
+        }
+        unit
+    }
+}
+
+impl ValueFormatter for BytesFormatter {
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        Self::scale(typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let elements = match throughput {
+            Throughput::Bytes(n) | Throughput::BytesDecimal(n) | Throughput::Elements(n) => *n,
+        };
+        for value in values.iter_mut() {
+            *value /= elements as f64;
+        }
+        match Self::scale(typical_value / elements as f64, values) {
+            "B" => "B/elem",
+            "KiB" => "KiB/elem",
+            _ => "MiB/elem",
+        }
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "B"
+    }
+}
+
+/// Benchmark the bytes allocated to read a value into `ZBytes`, by copying its
+/// payload or sharing it
+fn bench_read_allocations(c: &mut Criterion<AllocatedBytes>) {
+    let mut group = c.benchmark_group("read_allocations");
+
+    for size in [1_000, 100_000, 1_000_000].iter() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = RedbBackendConfig::new()
+            .with_base_dir(temp_dir.path().to_path_buf())
+            .with_create_dir(true);
+        let backend = RedbBackend::new(config).unwrap();
+        let storage = backend
+            .create_storage("bench_storage".to_string(), None)
+            .unwrap();
+        let value = StoredValue::new(
+            vec![0u8; *size],
+            Timestamp::new(NTP64(1), TimestampId::rand()),
+            Encoding::ZENOH_BYTES,
+        );
+        storage.put("test/key", value).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("get", size), size, |b, _| {
+            b.iter(|| {
+                let value = storage.get("test/key").unwrap().unwrap();
+                black_box(ZBytes::from(value.payload))
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("get_view", size), size, |b, _| {
+            b.iter(|| {
+                let view = storage.get_view("test/key").unwrap().unwrap();
+                black_box(view.into_zbytes())
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = allocation_benches;
+    // Allocations do not vary between samples, which the plots cannot render
+    config = Criterion::default().with_measurement(AllocatedBytes).without_plots();
+    targets = bench_read_allocations
+}
+
+criterion_main!(allocation_benches);
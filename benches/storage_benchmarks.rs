@@ -1,8 +1,10 @@
 use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
 use tempfile::TempDir;
-use zenoh::bytes::Encoding;
+use zenoh::bytes::{Encoding, ZBytes};
 use zenoh::time::{NTP64, Timestamp, TimestampId};
-use zenoh_backend_redb::{RedbBackend, RedbBackendConfig, RedbStorageConfig, StoredValue};
+use zenoh_backend_redb::{
+    RedbBackend, RedbBackendConfig, RedbStorageConfig, StorageLayout, StoredValue, WriteOp,
+};
 
 /// Helper to create a temporary backend for benchmarks
 fn create_test_backend() -> (RedbBackend, TempDir) {
@@ -96,13 +98,42 @@ fn bench_delete_operations(c: &mut Criterion) {
             },
             |key| {
                 // Benchmark: delete the key
-                storage.delete(black_box(&key)).unwrap();
+                let timestamp = Timestamp::new(NTP64(1), TimestampId::rand());
+                storage.delete(black_box(&key), timestamp).unwrap();
             },
             criterion::BatchSize::SmallInput,
         );
     });
 }
 
+/// Benchmark GET operations copying the payload against views sharing it
+fn bench_zero_copy_reads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("zero_copy_reads");
+
+    for size in [1_000, 100_000, 1_000_000].iter() {
+        let (backend, _temp_dir) = create_test_backend();
+        let storage = backend
+            .create_storage("bench_storage".to_string(), None)
+            .unwrap();
+        storage.put("test/key", create_value(*size, 1)).unwrap();
+
+        group.throughput(Throughput::Bytes(*size as u64));
+        group.bench_with_input(BenchmarkId::new("get", size), size, |b, _| {
+            b.iter(|| {
+                let value = storage.get("test/key").unwrap().unwrap();
+                black_box(ZBytes::from(value.payload))
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("get_view", size), size, |b, _| {
+            b.iter(|| {
+                let view = storage.get_view("test/key").unwrap().unwrap();
+                black_box(view.into_zbytes())
+            });
+        });
+    }
+    group.finish();
+}
+
 /// Benchmark batch PUT operations
 fn bench_batch_put(c: &mut Criterion) {
     let mut group = c.benchmark_group("batch_put");
@@ -131,6 +162,38 @@ fn bench_batch_put(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark batch PUT operations applied in a single transaction
+fn bench_write_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("write_batch");
+
+    for batch_size in [10, 100, 1_000, 10_000].iter() {
+        group.throughput(Throughput::Elements(*batch_size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(batch_size),
+            batch_size,
+            |b, &batch_size| {
+                let (backend, _temp_dir) = create_test_backend();
+                let storage = backend
+                    .create_storage("bench_storage".to_string(), None)
+                    .unwrap();
+                let mut counter = 0u64;
+
+                b.iter(|| {
+                    // Each iteration replaces the keys with newer values
+                    let time = counter;
+                    counter += 1;
+                    let ops = (0..batch_size).map(move |i| WriteOp::Put {
+                        key: format!("test/batch/{}", i),
+                        value: create_value(1000, time),
+                    });
+                    storage.write_batch(black_box(ops)).unwrap();
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
 /// Benchmark prefix queries with varying result set sizes
 fn bench_prefix_queries(c: &mut Criterion) {
     let mut group = c.benchmark_group("prefix_queries");
@@ -236,6 +299,63 @@ fn bench_wildcard_multi_segment(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark selective queries on a large keyspace.
+///
+/// Compares the bounded range scans used by `get_by_prefix` and `get_by_wildcard`
+/// against filtering the result of a full table scan.
+fn bench_large_keyspace_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_keyspace_queries");
+    group.sample_size(10);
+
+    for total_entries in [10_000, 100_000].iter() {
+        let (backend, _temp_dir) = create_test_backend();
+        let config = RedbStorageConfig::new().with_fsync(false);
+        let storage = backend
+            .create_storage("bench_storage".to_string(), Some(config))
+            .unwrap();
+
+        // 100 rooms, so each room holds 1% of the keyspace
+        for i in 0..*total_entries {
+            let key = format!("sensor/room{}/device{}", i % 100, i);
+            let value = create_value(100, i as u64);
+            storage.put(&key, value).unwrap();
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("prefix_range_scan", total_entries),
+            total_entries,
+            |b, _| {
+                b.iter(|| black_box(storage.get_by_prefix("sensor/room42/").unwrap()));
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("wildcard_range_scan", total_entries),
+            total_entries,
+            |b, _| {
+                b.iter(|| black_box(storage.get_by_wildcard("sensor/room42/**").unwrap()));
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("full_scan_filter", total_entries),
+            total_entries,
+            |b, _| {
+                b.iter(|| {
+                    let results: Vec<_> = storage
+                        .get_all()
+                        .unwrap()
+                        .into_iter()
+                        .filter(|(key, _)| key.starts_with("sensor/room42/"))
+                        .collect();
+                    black_box(results)
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
 /// Benchmark get_all operations
 fn bench_get_all(c: &mut Criterion) {
     let mut group = c.benchmark_group("get_all");
@@ -267,6 +387,33 @@ fn bench_get_all(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark listing keys and timestamps against loading every entry, with large values
+fn bench_list_entries_metadata(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list_entries_metadata");
+
+    let (backend, _temp_dir) = create_test_backend();
+    let config = RedbStorageConfig::new().with_fsync(false);
+    let storage = backend
+        .create_storage("bench_storage".to_string(), Some(config))
+        .unwrap();
+
+    // 100 entries of 1 MB
+    let puts = (0..100).map(|i| WriteOp::Put {
+        key: format!("test/entry/{}", i),
+        value: create_value(1_000_000, i),
+    });
+    storage.write_batch(puts).unwrap();
+
+    group.throughput(Throughput::Elements(100));
+    group.bench_function("get_all", |b| {
+        b.iter(|| black_box(storage.get_all().unwrap()));
+    });
+    group.bench_function("list_entries_metadata", |b| {
+        b.iter(|| black_box(storage.list_entries_metadata().unwrap()));
+    });
+    group.finish();
+}
+
 /// Benchmark concurrent read operations
 fn bench_concurrent_reads(c: &mut Criterion) {
     use std::sync::Arc;
@@ -356,7 +503,7 @@ fn bench_prefix_stripping(c: &mut Criterion) {
         group.bench_function(label, |b| {
             let (backend, _temp_dir) = create_test_backend();
             let mut config =
-                RedbStorageConfig::new().with_key_expr("test/long/prefix/path/".to_string());
+                RedbStorageConfig::new().with_key_expr("test/long/prefix/path/**".to_string());
             if *strip_prefix {
                 config = config.with_strip_prefix(true);
             }
@@ -376,6 +523,64 @@ fn bench_prefix_stripping(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark puts, gets and scans with the two-table and single-table layouts
+fn bench_layout_comparison(c: &mut Criterion) {
+    let mut group = c.benchmark_group("layout_comparison");
+
+    for layout in [StorageLayout::TwoTables, StorageLayout::SingleTable] {
+        let label = match layout {
+            StorageLayout::TwoTables => "two_tables",
+            StorageLayout::SingleTable => "single_table",
+        };
+
+        group.bench_function(BenchmarkId::new("put", label), |b| {
+            let (backend, _temp_dir) = create_test_backend();
+            let config = RedbStorageConfig::new()
+                .with_fsync(false)
+                .with_layout(layout);
+            let storage = backend
+                .create_storage("bench_storage".to_string(), Some(config))
+                .unwrap();
+
+            let mut counter = 0u64;
+            b.iter(|| {
+                let key = format!("test/key/{}", counter);
+                let value = create_value(1000, counter);
+                counter += 1;
+                storage.put(&key, black_box(value)).unwrap();
+            });
+        });
+
+        let (backend, _temp_dir) = create_test_backend();
+        let config = RedbStorageConfig::new()
+            .with_fsync(false)
+            .with_layout(layout);
+        let storage = backend
+            .create_storage("bench_storage".to_string(), Some(config))
+            .unwrap();
+        for i in 0..1000 {
+            let key = format!("test/key/{}", i);
+            storage.put(&key, create_value(1000, i)).unwrap();
+        }
+
+        group.bench_function(BenchmarkId::new("get", label), |b| {
+            let mut counter = 0u64;
+            b.iter(|| {
+                let key = format!("test/key/{}", counter % 1000);
+                counter += 1;
+                black_box(storage.get(&key).unwrap());
+            });
+        });
+
+        group.bench_function(BenchmarkId::new("scan", label), |b| {
+            b.iter(|| {
+                black_box(storage.get_by_prefix("test/key/").unwrap());
+            });
+        });
+    }
+    group.finish();
+}
+
 /// Benchmark key encoding/decoding overhead
 fn bench_key_operations(c: &mut Criterion) {
     c.bench_function("key_encoding", |b| {
@@ -392,15 +597,20 @@ criterion_group!(
     storage_benches,
     bench_put_operations,
     bench_get_operations,
+    bench_zero_copy_reads,
     bench_delete_operations,
     bench_batch_put,
+    bench_write_batch,
     bench_prefix_queries,
     bench_wildcard_single_segment,
     bench_wildcard_multi_segment,
+    bench_large_keyspace_queries,
     bench_get_all,
+    bench_list_entries_metadata,
     bench_concurrent_reads,
     bench_fsync_impact,
     bench_prefix_stripping,
+    bench_layout_comparison,
     bench_key_operations,
 );
 
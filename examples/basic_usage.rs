@@ -7,6 +7,17 @@ use zenoh::bytes::Encoding;
 use zenoh::time::{NTP64, Timestamp, TimestampId};
 use zenoh_backend_redb::{RedbBackend, RedbBackendConfig, RedbStorageConfig, StoredValue};
 
+/// Build a timestamp for the current time.
+///
+/// Writes follow last-writer-wins semantics, so updates need increasing timestamps.
+fn now() -> Result<Timestamp, std::time::SystemTimeError> {
+    let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+    Ok(Timestamp::new(
+        NTP64::from(since_epoch),
+        TimestampId::rand(),
+    ))
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing for logging
     tracing_subscriber::fmt::init();
@@ -43,10 +54,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     ];
 
     for (key, value, encoding) in &entries {
-        let time_u64 = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs();
-        let timestamp = Timestamp::new(NTP64(time_u64), TimestampId::rand());
+        let timestamp = now()?;
         let enc = match *encoding {
             "application/json" => Encoding::APPLICATION_JSON,
             "text/plain" => Encoding::TEXT_PLAIN,
@@ -100,10 +108,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 8. Update a value
     // 8. Update an existing value
     println!("8. Updating value...");
-    let time_u64 = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)?
-        .as_secs();
-    let timestamp = Timestamp::new(NTP64(time_u64), TimestampId::rand());
+    let timestamp = now()?;
     let updated_value = StoredValue::new(
         "24.8".as_bytes().to_vec(),
         timestamp,
@@ -117,7 +122,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 9. Delete a value
     println!("9. Deleting value...");
-    storage.delete("demo/device/status")?;
+    storage.delete("demo/device/status", now()?)?;
     println!("   ✓ Deleted 'demo/device/status'\n");
 
     // 10. Count remaining entries
@@ -1,12 +1,36 @@
 //! Backend implementation for the zenoh-backend-redb storage backend.
 
-use crate::config::{RedbBackendConfig, RedbStorageConfig};
+use crate::config::{DEFAULT_TABLE_NAME, RedbBackendConfig, RedbStorageConfig, StorageLayout};
 use crate::error::{RedbBackendError, Result};
-use crate::storage::RedbStorage;
-use std::collections::HashMap;
+use crate::read_only;
+use crate::schema::{self, KeyScopeRecord};
+use crate::storage::{RedbStorage, TableNames};
+use redb::{Database, TableError, TableHandle};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use tracing::{debug, info, warn};
 
+/// A storage found in a database file of the base directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnDiskStorage {
+    /// Name the storage was last opened under. Storages written before names were
+    /// recorded are named after their table name, or their file for the default one.
+    pub name: String,
+    /// Canonical path of the database file
+    pub path: PathBuf,
+    /// Table name of the storage in the file
+    pub table_name: String,
+    /// Table layout of the entries
+    pub layout: StorageLayout,
+    /// Key expression the keys were stored with, if recorded
+    pub key_expr: Option<String>,
+    /// Whether the prefix of `key_expr` was stripped from the stored keys
+    pub strip_prefix: bool,
+    /// Whether the storage is registered in the backend
+    pub registered: bool,
+}
+
 /// The redb backend manages multiple storage instances.
 pub struct RedbBackend {
     /// Backend configuration
@@ -14,6 +38,14 @@ pub struct RedbBackend {
 
     /// Map of storage name to storage instance
     storages: Arc<RwLock<HashMap<String, Arc<RedbStorage>>>>,
+
+    /// Map of canonical database path to the database shared by the storages in
+    /// that file. A database is released with the last storage using it.
+    databases: Arc<RwLock<HashMap<PathBuf, Arc<Database>>>>,
+
+    /// Map of storage name to canonical database path of the clones being copied,
+    /// which no other storage may use until they are registered.
+    reserved: Arc<RwLock<HashMap<String, PathBuf>>>,
 }
 
 impl RedbBackend {
@@ -27,13 +59,27 @@ impl RedbBackend {
             debug!("Created base directory: {:?}", config.base_dir);
         }
 
-        Ok(Self {
+        let backend = Self {
             config,
             storages: Arc::new(RwLock::new(HashMap::new())),
-        })
+            databases: Arc::new(RwLock::new(HashMap::new())),
+            reserved: Arc::new(RwLock::new(HashMap::new())),
+        };
+        if backend.config.open_existing {
+            backend.discover()?;
+        }
+        Ok(backend)
     }
 
     /// Create a new storage instance.
+    ///
+    /// Storages whose configurations point to the same database file share one
+    /// database handle, each in its own tables named after its `table_name`.
+    ///
+    /// Creating a storage again with the same name returns the existing storage if
+    /// its configuration is unchanged, and fails with a
+    /// [`ConfigError`](RedbBackendError::ConfigError) otherwise: remove it first to
+    /// replace it.
     pub fn create_storage(
         &self,
         name: String,
@@ -44,30 +90,355 @@ impl RedbBackend {
         // Use provided config or default from backend config
         let storage_config = config.unwrap_or_else(|| self.config.default_storage_config.clone());
 
+        let mut storages = self
+            .storages
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
+        let mut databases = self
+            .databases
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
+
+        if let Some(existing) = storages.get(&name) {
+            let db_path = canonical_path(&storage_config.effective_db_path(&name, &self.config));
+            let same_file = databases
+                .get(&db_path)
+                .is_some_and(|db| Arc::ptr_eq(db, existing.database()));
+            if same_file && existing.config() == &storage_config {
+                debug!("Storage '{}' already exists, reusing it", name);
+                return Ok(existing.clone());
+            }
+            return Err(RedbBackendError::config(format!(
+                "Storage '{}' already exists with a different configuration",
+                name
+            )));
+        }
+
+        let storage_arc =
+            self.register(&mut storages, &mut databases, name.clone(), storage_config)?;
+
+        info!("Storage '{}' created successfully", name);
+        Ok(storage_arc)
+    }
+
+    /// Open a storage that isn't registered yet in the database of its file, opening
+    /// the file if needed, and register both.
+    fn register(
+        &self,
+        storages: &mut HashMap<String, Arc<RedbStorage>>,
+        databases: &mut HashMap<PathBuf, Arc<Database>>,
+        name: String,
+        storage_config: RedbStorageConfig,
+    ) -> Result<Arc<RedbStorage>> {
         // Determine the database path
-        let db_path = storage_config.effective_db_path(&name, &self.config);
+        let db_path = canonical_path(&storage_config.effective_db_path(&name, &self.config));
 
         debug!("Storage '{}' will use database at: {:?}", name, db_path);
 
-        // Create the storage
-        let storage = RedbStorage::new(db_path, storage_config, name.clone())?;
+        self.check_reserved(&name, &db_path)?;
+        let open_db = databases.get(&db_path).cloned();
+        if let Some(db) = &open_db
+            && let Some(other) = storages.values().find(|other| {
+                Arc::ptr_eq(other.database(), db)
+                    && other.config().table_name == storage_config.table_name
+            })
+        {
+            return Err(RedbBackendError::config(format!(
+                "Storage '{}' already uses table name '{}' in {:?}",
+                other.name(),
+                storage_config.table_name,
+                db_path
+            )));
+        }
+
+        // Create the storage in the database of its file, opening it if needed
+        let db = match open_db {
+            Some(db) => {
+                debug!("Sharing open database at: {:?}", db_path);
+                db
+            }
+            None => Arc::new(open_database(&db_path, &storage_config)?),
+        };
+        let storage = RedbStorage::from_database(db.clone(), storage_config, name.clone())?;
         let storage_arc = Arc::new(storage);
 
-        // Register the storage
+        // Register the storage and its database
+        databases.insert(db_path, db);
+        storages.insert(name, storage_arc.clone());
+        Ok(storage_arc)
+    }
+
+    /// Fail if `name` or `db_path` is reserved by a clone being copied.
+    fn check_reserved(&self, name: &str, db_path: &Path) -> Result<()> {
+        let reserved = self
+            .reserved
+            .read()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire read lock: {}", e)))?;
+
+        if reserved.contains_key(name) || reserved.values().any(|path| path == db_path) {
+            return Err(RedbBackendError::config(format!(
+                "Storage '{}' or database file {:?} is reserved by a clone in progress",
+                name, db_path
+            )));
+        }
+        Ok(())
+    }
+
+    /// Remove a storage and delete its data.
+    ///
+    /// The database file is deleted with the storage, unless it holds the tables of
+    /// other storages, registered or not, in which case only the tables of the
+    /// storage are. A storage still held
+    /// outside the backend, by a plugin or a caller, is only destroyed with `force`:
+    /// its handles then fail with [`StorageNotFound`](RedbBackendError::StorageNotFound).
+    /// The storage stays registered, and its handles usable, if its data cannot be
+    /// deleted.
+    pub fn destroy_storage(&self, name: &str, force: bool) -> Result<()> {
+        info!("Destroying storage: {}", name);
+
+        let mut storages = self
+            .storages
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
+        let storage = storages
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RedbBackendError::storage_not_found(name))?;
+        if in_use(&storage) && !force {
+            return Err(RedbBackendError::config(format!(
+                "Storage '{}' is still in use; destroy it with force to delete its data anyway",
+                name
+            )));
+        }
+
+        // The file may also hold storages that aren't registered
+        let shared = storage.shares_database()?;
+
+        // Handles held elsewhere fail from now on, instead of reaching deleted data
+        storage.set_closed(true);
+        let deleted = if shared {
+            storage.delete_tables()
+        } else {
+            let db_path = canonical_path(&storage.config().effective_db_path(name, &self.config));
+            std::fs::remove_file(&db_path)
+                .map(|()| debug!("Deleted database at: {:?}", db_path))
+                .map_err(Into::into)
+        };
+        if let Err(e) = deleted {
+            storage.set_closed(false);
+            return Err(e);
+        }
+
+        storages.remove(name);
+        drop(storage);
+        self.release_databases(&storages)?;
+
+        info!("Storage '{}' destroyed", name);
+        Ok(())
+    }
+
+    /// Register a storage under a new name.
+    ///
+    /// A database file named after the storage, without `db_file` or `db_path` in its
+    /// configuration, is renamed along with it, so the file must not hold the tables
+    /// of other storages, registered or not. The storage must not be held outside the
+    /// backend, and is reopened under its old name if it cannot be renamed.
+    pub fn rename_storage(&self, name: &str, new_name: String) -> Result<Arc<RedbStorage>> {
+        info!("Renaming storage '{}' to '{}'", name, new_name);
+
+        let mut storages = self
+            .storages
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
+        let mut databases = self
+            .databases
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
+
+        if storages.contains_key(&new_name) {
+            return Err(RedbBackendError::config(format!(
+                "Storage '{}' already exists",
+                new_name
+            )));
+        }
+        let storage = storages
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RedbBackendError::storage_not_found(name))?;
+        if in_use(&storage) {
+            return Err(RedbBackendError::config(format!(
+                "Storage '{}' is still in use and cannot be renamed",
+                name
+            )));
+        }
+
+        let config = storage.config().clone();
+        let db_path = canonical_path(&config.effective_db_path(name, &self.config));
+        let new_path = canonical_path(&config.effective_db_path(&new_name, &self.config));
+        self.check_reserved(&new_name, &new_path)?;
+        if db_path != new_path {
+            if storage.shares_database()? {
+                return Err(RedbBackendError::config(format!(
+                    "Storage '{}' shares its database file {:?} with other storages",
+                    name, db_path
+                )));
+            }
+            if new_path.exists() {
+                return Err(RedbBackendError::config(format!(
+                    "Database file {:?} already exists",
+                    new_path
+                )));
+            }
+        }
+
+        storages.remove(name);
+        if db_path != new_path {
+            // Close the file before moving it
+            databases.remove(&db_path);
+            drop(storage);
+            if let Err(e) = std::fs::rename(&db_path, &new_path) {
+                self.restore(&mut storages, &mut databases, name, config);
+                return Err(e.into());
+            }
+            debug!("Moved database from {:?} to {:?}", db_path, new_path);
+        }
+        let renamed = match self.register(&mut storages, &mut databases, new_name, config.clone()) {
+            Ok(renamed) => renamed,
+            Err(e) => {
+                // Move the file back, so that the storage opens under its old name
+                if db_path != new_path
+                    && let Err(move_error) = std::fs::rename(&new_path, &db_path)
+                {
+                    warn!(
+                        "Failed to move database back from {:?} to {:?}: {}",
+                        new_path, db_path, move_error
+                    );
+                } else {
+                    self.restore(&mut storages, &mut databases, name, config);
+                }
+                return Err(e);
+            }
+        };
+
+        info!("Storage '{}' renamed to '{}'", name, renamed.name());
+        Ok(renamed)
+    }
+
+    /// Register a storage again under its old name after a failed rename. Failures
+    /// are only logged, so that the error of the rename is the one returned.
+    fn restore(
+        &self,
+        storages: &mut HashMap<String, Arc<RedbStorage>>,
+        databases: &mut HashMap<PathBuf, Arc<Database>>,
+        name: &str,
+        config: RedbStorageConfig,
+    ) {
+        if let Err(e) = self.register(storages, databases, name.to_string(), config) {
+            warn!(
+                "Failed to reopen storage '{}' after a failed rename: {}",
+                name, e
+            );
+        }
+    }
+
+    /// Copy a storage into a new database file, registered under `clone_name`.
+    ///
+    /// The copy is taken from a single read snapshot, so it is consistent even while
+    /// the storage is written to. The clone has the configuration of the storage,
+    /// with its file named after `clone_name` in the base directory, which must not
+    /// exist yet.
+    pub fn clone_storage(&self, name: &str, clone_name: String) -> Result<Arc<RedbStorage>> {
+        info!("Cloning storage '{}' into '{}'", name, clone_name);
+
+        let storage = self.get_storage(name)?;
+        let mut config = storage.config().clone();
+        config.db_file = None;
+        config.db_path = None;
+        config.create_db = true;
+        let db_path = canonical_path(&config.effective_db_path(&clone_name, &self.config));
+
+        // Reserve the name and file of the clone, so that the backend isn't locked
+        // while the storage is copied
         {
-            let mut storages = self.storages.write().map_err(|e| {
-                RedbBackendError::other(format!("Failed to acquire write lock: {}", e))
+            let storages = self.storages.read().map_err(|e| {
+                RedbBackendError::other(format!("Failed to acquire read lock: {}", e))
             })?;
+            if storages.contains_key(&clone_name) {
+                return Err(RedbBackendError::config(format!(
+                    "Storage '{}' already exists",
+                    clone_name
+                )));
+            }
+            if db_path.exists() {
+                return Err(RedbBackendError::config(format!(
+                    "Database file {:?} already exists",
+                    db_path
+                )));
+            }
+            self.check_reserved(&clone_name, &db_path)?;
+            self.reserved
+                .write()
+                .map_err(|e| {
+                    RedbBackendError::other(format!("Failed to acquire write lock: {}", e))
+                })?
+                .insert(clone_name.clone(), db_path.clone());
+        }
+
+        let copied = copy_storage(&storage, &db_path, &config);
 
-            if storages.contains_key(&name) {
-                warn!("Storage '{}' already exists, replacing it", name);
+        let mut storages = self
+            .storages
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
+        let mut databases = self
+            .databases
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
+        self.reserved
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?
+            .remove(&clone_name);
+
+        let cloned =
+            copied.and_then(|()| self.register(&mut storages, &mut databases, clone_name, config));
+        let clone = match cloned {
+            Ok(clone) => clone,
+            Err(e) => {
+                if db_path.exists()
+                    && let Err(remove_error) = std::fs::remove_file(&db_path)
+                {
+                    warn!(
+                        "Failed to delete the database of a failed clone at {:?}: {}",
+                        db_path, remove_error
+                    );
+                }
+                return Err(e);
             }
+        };
 
-            storages.insert(name.clone(), storage_arc.clone());
-        }
+        info!("Storage '{}' cloned into '{}'", name, clone.name());
+        Ok(clone)
+    }
 
-        info!("Storage '{}' created successfully", name);
-        Ok(storage_arc)
+    /// Release the databases no longer used by any of `storages`.
+    ///
+    /// A database file is closed once every handle to its storages is dropped too.
+    fn release_databases(&self, storages: &HashMap<String, Arc<RedbStorage>>) -> Result<()> {
+        let mut databases = self
+            .databases
+            .write()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
+
+        databases.retain(|path, db| {
+            let used = storages
+                .values()
+                .any(|storage| Arc::ptr_eq(storage.database(), db));
+            if !used {
+                debug!("Releasing database at: {:?}", path);
+            }
+            used
+        });
+        Ok(())
     }
 
     /// Get an existing storage instance by name.
@@ -93,6 +464,7 @@ impl RedbBackend {
             .map_err(|e| RedbBackendError::other(format!("Failed to acquire write lock: {}", e)))?;
 
         if storages.remove(name).is_some() {
+            self.release_databases(&storages)?;
             info!("Storage '{}' removed successfully", name);
             Ok(())
         } else {
@@ -111,6 +483,104 @@ impl RedbBackend {
         Ok(storages.keys().cloned().collect())
     }
 
+    /// List the storages of the database files (`*.redb`) in the base directory,
+    /// including those no storage of the backend uses.
+    ///
+    /// Files that no storage uses are opened read-only: they are left unchanged,
+    /// even if they were not closed cleanly or have an older schema version. Files
+    /// that cannot be read, such as files open in another process or with a newer
+    /// schema version, are skipped with a warning.
+    pub fn list_on_disk(&self) -> Result<Vec<OnDiskStorage>> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(&self.config.base_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "redb") {
+                paths.push(canonical_path(&path));
+            }
+        }
+        paths.sort();
+
+        let storages = self
+            .storages
+            .read()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire read lock: {}", e)))?;
+        let databases = self
+            .databases
+            .read()
+            .map_err(|e| RedbBackendError::other(format!("Failed to acquire read lock: {}", e)))?;
+
+        let mut found = Vec::new();
+        for path in paths {
+            let db = match databases.get(&path) {
+                Some(db) => db.clone(),
+                None => {
+                    // Files no storage uses are only read, even if they need recovery
+                    match read_only::open_database(&path) {
+                        Ok(db) => Arc::new(db),
+                        Err(e) => {
+                            warn!("Skipping database {:?}: {}", path, e);
+                            continue;
+                        }
+                    }
+                }
+            };
+            match inspect_database(&path, &db, &storages) {
+                Ok(mut in_file) => found.append(&mut in_file),
+                Err(e) => warn!("Skipping database {:?}: {}", path, e),
+            }
+        }
+        Ok(found)
+    }
+
+    /// Reopen the storages of the database files in the base directory that aren't
+    /// registered yet, returning their names.
+    ///
+    /// Storages are registered under the name they were last opened under, with the
+    /// default storage configuration of the backend for their file, table name,
+    /// layout and key scope. Storages whose name is already registered, or that fail to open, are
+    /// skipped with a warning.
+    pub fn discover(&self) -> Result<Vec<String>> {
+        info!("Discovering storages in: {:?}", self.config.base_dir);
+
+        let mut opened = Vec::new();
+        for found in self.list_on_disk()? {
+            if found.registered {
+                continue;
+            }
+            if self.has_storage(&found.name)? {
+                warn!(
+                    "Skipping storage '{}' in {:?}: the name is already registered",
+                    found.name, found.path
+                );
+                continue;
+            }
+            let mut config = self
+                .config
+                .default_storage_config
+                .clone()
+                .with_table_name(found.table_name)
+                .with_layout(found.layout)
+                .with_strip_prefix(found.strip_prefix)
+                .with_create_db(false);
+            config.key_expr = found.key_expr;
+            // Name the file like those of created storages, so it follows renames
+            let db_file = found.path.file_stem().unwrap_or_default();
+            let db_file = db_file.to_string_lossy().into_owned();
+            config.db_path = None;
+            config.db_file = (db_file != found.name).then_some(db_file);
+            match self.create_storage(found.name.clone(), Some(config)) {
+                Ok(_) => opened.push(found.name),
+                Err(e) => warn!(
+                    "Skipping storage '{}' in {:?}: {}",
+                    found.name, found.path, e
+                ),
+            }
+        }
+
+        info!("Discovered {} storage(s)", opened.len());
+        Ok(opened)
+    }
+
     /// Get the backend configuration.
     pub fn config(&self) -> &RedbBackendConfig {
         &self.config
@@ -147,12 +617,124 @@ impl RedbBackend {
 
         let count = storages.len();
         storages.clear();
+        self.release_databases(&storages)?;
 
         info!("Closed {} storage(s)", count);
         Ok(())
     }
 }
 
+/// Open, or create if `create_db` is set, the database file of a storage.
+///
+/// Files still held by storages removed from the backend cannot be opened again
+/// until these are dropped.
+fn open_database(path: &Path, config: &RedbStorageConfig) -> Result<Database> {
+    match crate::storage::open_database(path, config) {
+        Err(RedbBackendError::DatabaseError(e))
+            if matches!(*e, redb::DatabaseError::DatabaseAlreadyOpen) =>
+        {
+            Err(RedbBackendError::config(format!(
+                "Database {:?} is still open by a storage removed from the backend",
+                path
+            )))
+        }
+        result => result,
+    }
+}
+
+/// Copy the tables of `storage` into a new database file at `path`.
+fn copy_storage(storage: &RedbStorage, path: &Path, config: &RedbStorageConfig) -> Result<()> {
+    let db = open_database(path, config)?;
+    let write_txn = db.begin_write()?;
+    storage.copy_tables(&write_txn)?;
+    write_txn.commit()?;
+    Ok(())
+}
+
+/// Whether a registered storage is held outside the backend, besides the handle of
+/// the caller.
+fn in_use(storage: &Arc<RedbStorage>) -> bool {
+    Arc::strong_count(storage) > 2
+}
+
+/// The storages recorded in the database file at `path`.
+fn inspect_database(
+    path: &Path,
+    db: &Arc<Database>,
+    storages: &HashMap<String, Arc<RedbStorage>>,
+) -> Result<Vec<OnDiskStorage>> {
+    let read_txn = db.begin_read()?;
+    let table_names: BTreeSet<String> = read_txn
+        .list_tables()?
+        .map(|table| TableNames::table_name_of(table.name()).to_string())
+        .collect();
+
+    let mut found = Vec::new();
+    for table_name in table_names {
+        let tables = TableNames::new(&table_name)?;
+        let metadata_table = match read_txn.open_table(tables.metadata()) {
+            Ok(table) => Some(table),
+            Err(TableError::TableDoesNotExist(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let layout = schema::inspect(metadata_table.as_ref())?;
+        let (recorded, key_scope) = match &metadata_table {
+            Some(table) => (
+                schema::read_storage_name(table)?,
+                schema::read_key_scope(table)?,
+            ),
+            None => (None, None),
+        };
+        let key_scope = key_scope.unwrap_or(KeyScopeRecord {
+            key_expr: None,
+            strip_prefix: false,
+        });
+        let name = recorded.unwrap_or_else(|| {
+            if table_name == DEFAULT_TABLE_NAME {
+                let stem = path.file_stem().unwrap_or_default();
+                stem.to_string_lossy().into_owned()
+            } else {
+                table_name.clone()
+            }
+        });
+        let registered = storages.values().any(|storage| {
+            Arc::ptr_eq(storage.database(), db) && storage.config().table_name == table_name
+        });
+        found.push(OnDiskStorage {
+            name,
+            path: path.to_path_buf(),
+            table_name,
+            layout,
+            key_expr: key_scope.key_expr,
+            strip_prefix: key_scope.strip_prefix,
+            registered,
+        });
+    }
+    Ok(found)
+}
+
+/// Canonical form of a database path, so that every path to a file maps to its
+/// open database. Files that don't exist yet are resolved from their directory.
+fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            parent
+                .canonicalize()
+                .map(|parent| parent.join(file_name))
+                .unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
 impl Drop for RedbBackend {
     fn drop(&mut self) {
         if let Err(e) = self.close() {
@@ -164,7 +746,10 @@ impl Drop for RedbBackend {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::StoredValue;
     use tempfile::TempDir;
+    use zenoh::bytes::Encoding;
+    use zenoh::time::{NTP64, Timestamp, TimestampId};
 
     fn create_test_backend() -> (RedbBackend, TempDir) {
         let temp_dir = TempDir::new().unwrap();
@@ -313,4 +898,376 @@ mod tests {
         backend.close().unwrap();
         assert_eq!(backend.storage_count().unwrap(), 0);
     }
+
+    #[test]
+    fn test_storages_share_database_file() {
+        let (backend, temp) = create_test_backend();
+        let shared = |table_name: &str| {
+            RedbStorageConfig::new()
+                .with_db_file("shared".to_string())
+                .with_table_name(table_name.to_string())
+        };
+
+        let first = backend
+            .create_storage("first".to_string(), Some(shared("first")))
+            .unwrap();
+        let second = backend
+            .create_storage("second".to_string(), Some(shared("second")))
+            .unwrap();
+        assert!(Arc::ptr_eq(first.database(), second.database()));
+
+        let timestamp = Timestamp::new(NTP64(1), TimestampId::rand());
+        let value = StoredValue::new(b"one".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        first.put("key", value).unwrap();
+        assert!(first.get("key").unwrap().is_some());
+        assert!(second.get("key").unwrap().is_none());
+        assert_eq!(second.usage().unwrap().entries, 0);
+
+        // Table names cannot be used twice in a file
+        let result = backend.create_storage("third".to_string(), Some(shared("first")));
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+
+        // The file is closed with its last storage, and reopened on demand
+        backend.close().unwrap();
+        drop((first, second));
+        assert!(redb::Database::open(temp.path().join("shared.redb")).is_ok());
+        let first = backend
+            .create_storage("first".to_string(), Some(shared("first")))
+            .unwrap();
+        assert!(first.get("key").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_create_storage_twice() {
+        let (backend, temp) = create_test_backend();
+
+        let first = backend.create_storage("storage".to_string(), None).unwrap();
+        let again = backend.create_storage("storage".to_string(), None).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        // Another path to the same file shares its database
+        let config = RedbStorageConfig::new()
+            .with_db_path(temp.path().join(".").join("storage.redb"))
+            .with_table_name("other".to_string());
+        let other = backend
+            .create_storage("other".to_string(), Some(config))
+            .unwrap();
+        assert!(Arc::ptr_eq(first.database(), other.database()));
+
+        // A different configuration under the same name is a conflict
+        let config = RedbStorageConfig::new().with_fsync(false);
+        let result = backend.create_storage("storage".to_string(), Some(config.clone()));
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+
+        // Until the storage is removed
+        backend.remove_storage("storage").unwrap();
+        drop((first, again));
+        let replaced = backend
+            .create_storage("storage".to_string(), Some(config))
+            .unwrap();
+        assert!(!replaced.config().fsync);
+    }
+
+    #[test]
+    fn test_removed_storage_still_open() {
+        let (backend, _temp) = create_test_backend();
+
+        let storage = backend.create_storage("storage".to_string(), None).unwrap();
+        backend.remove_storage("storage").unwrap();
+
+        // The file is still held by the removed storage
+        let result = backend.create_storage("storage".to_string(), None);
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+
+        drop(storage);
+        assert!(backend.create_storage("storage".to_string(), None).is_ok());
+    }
+
+    #[test]
+    fn test_discover_storages() {
+        let (backend, temp) = create_test_backend();
+        let timestamp = Timestamp::new(NTP64(1), TimestampId::rand());
+        let value = StoredValue::new(b"one".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+
+        let single = RedbStorageConfig::new()
+            .with_db_file("legacy".to_string())
+            .with_layout(StorageLayout::SingleTable)
+            .with_key_expr("sensors/**".to_string())
+            .with_strip_prefix(true);
+        backend
+            .create_storage("sensors".to_string(), Some(single))
+            .unwrap()
+            .put("sensors/key", value.clone())
+            .unwrap();
+        let shared = RedbStorageConfig::new()
+            .with_db_file("legacy".to_string())
+            .with_table_name("other".to_string());
+        backend
+            .create_storage("other".to_string(), Some(shared))
+            .unwrap();
+        backend.remove_storage("other").unwrap();
+
+        let on_disk = backend.list_on_disk().unwrap();
+        assert_eq!(on_disk.len(), 2);
+        let sensors = on_disk.iter().find(|s| s.name == "sensors").unwrap();
+        assert!(sensors.registered);
+        assert_eq!(sensors.table_name, DEFAULT_TABLE_NAME);
+        assert_eq!(sensors.layout, StorageLayout::SingleTable);
+        assert_eq!(sensors.key_expr.as_deref(), Some("sensors/**"));
+        assert!(sensors.strip_prefix);
+        let other = on_disk.iter().find(|s| s.name == "other").unwrap();
+        assert!(!other.registered);
+        assert_eq!(other.path, sensors.path);
+
+        // Orphaned storages are reopened by discovery
+        assert_eq!(backend.discover().unwrap(), vec!["other".to_string()]);
+        assert!(backend.list_on_disk().unwrap().iter().all(|s| s.registered));
+        backend.close().unwrap();
+
+        // And a new backend reopens all of them at creation
+        let config = RedbBackendConfig::new()
+            .with_base_dir(temp.path().to_path_buf())
+            .with_open_existing(true);
+        let reopened = RedbBackend::new(config).unwrap();
+        let mut names = reopened.list_storages().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["other".to_string(), "sensors".to_string()]);
+        let sensors = reopened.get_storage("sensors").unwrap();
+        assert_eq!(sensors.config().layout, StorageLayout::SingleTable);
+        assert!(sensors.get("sensors/key").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_discover_skips_unreadable_files() {
+        let (backend, temp) = create_test_backend();
+        std::fs::write(temp.path().join("garbage.redb"), b"not a database").unwrap();
+        std::fs::write(temp.path().join("notes.txt"), b"ignored").unwrap();
+
+        // Files written before storage names were recorded are named after the file
+        let tables = TableNames::new(DEFAULT_TABLE_NAME).unwrap();
+        let db = Database::create(temp.path().join("unnamed.redb")).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_txn.open_table(tables.payloads()).unwrap();
+        write_txn.open_table(tables.data_info()).unwrap();
+        write_txn.commit().unwrap();
+        drop(db);
+
+        // Listing only reads the file, which is migrated when discovery opens it
+        let before = std::fs::read(temp.path().join("unnamed.redb")).unwrap();
+        let on_disk = backend.list_on_disk().unwrap();
+        assert_eq!(
+            std::fs::read(temp.path().join("unnamed.redb")).unwrap(),
+            before
+        );
+        assert_eq!(on_disk.len(), 1);
+        assert_eq!(on_disk[0].name, "unnamed");
+        assert_eq!(on_disk[0].layout, StorageLayout::TwoTables);
+
+        assert_eq!(backend.discover().unwrap(), vec!["unnamed".to_string()]);
+    }
+
+    fn put_value(storage: &RedbStorage, key: &str) {
+        let timestamp = Timestamp::new(NTP64(1), TimestampId::rand());
+        let value = StoredValue::new(b"one".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        storage.put(key, value).unwrap();
+    }
+
+    #[test]
+    fn test_destroy_storage() {
+        let (backend, temp) = create_test_backend();
+
+        let storage = backend.create_storage("storage".to_string(), None).unwrap();
+        put_value(&storage, "key");
+        assert!(temp.path().join("storage.redb").exists());
+
+        // Handles outside the backend need force
+        let result = backend.destroy_storage("storage", false);
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+        assert!(backend.has_storage("storage").unwrap());
+        drop(storage);
+
+        backend.destroy_storage("storage", false).unwrap();
+        assert!(!backend.has_storage("storage").unwrap());
+        assert!(!temp.path().join("storage.redb").exists());
+        let result = backend.destroy_storage("storage", false);
+        assert!(matches!(result, Err(RedbBackendError::StorageNotFound(_))));
+
+        // A storage whose file cannot be deleted stays registered and usable
+        let storage = backend.create_storage("kept".to_string(), None).unwrap();
+        std::fs::remove_file(temp.path().join("kept.redb")).unwrap();
+        assert!(matches!(
+            backend.destroy_storage("kept", true),
+            Err(RedbBackendError::IoError(_))
+        ));
+        assert!(backend.has_storage("kept").unwrap());
+        put_value(&storage, "key");
+        drop(storage);
+
+        // A shared file only loses the tables of the storage
+        let shared = |table_name: &str| {
+            RedbStorageConfig::new()
+                .with_db_file("shared".to_string())
+                .with_table_name(table_name.to_string())
+        };
+        let first = backend
+            .create_storage("first".to_string(), Some(shared("first")))
+            .unwrap();
+        put_value(&first, "key");
+        let second = backend
+            .create_storage("second".to_string(), Some(shared("second")))
+            .unwrap();
+        put_value(&second, "key");
+        backend.destroy_storage("second", true).unwrap();
+        assert!(temp.path().join("shared.redb").exists());
+        assert!(first.get("key").unwrap().is_some());
+
+        // Forcibly destroyed storages fail instead of reaching deleted data
+        assert!(matches!(
+            second.get("key"),
+            Err(RedbBackendError::StorageNotFound(_))
+        ));
+        let timestamp = Timestamp::new(NTP64(2), TimestampId::rand());
+        let value = StoredValue::new(b"two".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        assert!(matches!(
+            second.put("key", value),
+            Err(RedbBackendError::StorageNotFound(_))
+        ));
+        let on_disk = backend.list_on_disk().unwrap();
+        assert_eq!(on_disk.len(), 1);
+        assert_eq!(on_disk[0].name, "first");
+    }
+
+    #[test]
+    fn test_destroy_storage_keeps_unregistered_storages() {
+        let (backend, temp) = create_test_backend();
+        let shared = |table_name: &str| {
+            RedbStorageConfig::new()
+                .with_db_file("shared".to_string())
+                .with_table_name(table_name.to_string())
+        };
+
+        let removed = backend
+            .create_storage("removed".to_string(), Some(shared("removed")))
+            .unwrap();
+        put_value(&removed, "key");
+        drop(removed);
+        let destroyed = backend
+            .create_storage("destroyed".to_string(), Some(shared("destroyed")))
+            .unwrap();
+        put_value(&destroyed, "key");
+        drop(destroyed);
+        backend.remove_storage("removed").unwrap();
+
+        backend.destroy_storage("destroyed", false).unwrap();
+        assert!(temp.path().join("shared.redb").exists());
+        let removed = backend
+            .create_storage("removed".to_string(), Some(shared("removed")))
+            .unwrap();
+        assert!(removed.get("key").unwrap().is_some());
+        let on_disk = backend.list_on_disk().unwrap();
+        assert_eq!(on_disk.len(), 1);
+        assert_eq!(on_disk[0].name, "removed");
+    }
+
+    #[test]
+    fn test_rename_storage() {
+        let (backend, temp) = create_test_backend();
+
+        let storage = backend.create_storage("before".to_string(), None).unwrap();
+        put_value(&storage, "key");
+        backend.create_storage("taken".to_string(), None).unwrap();
+
+        let result = backend.rename_storage("before", "after".to_string());
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+        drop(storage);
+        let result = backend.rename_storage("before", "taken".to_string());
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+
+        let renamed = backend
+            .rename_storage("before", "after".to_string())
+            .unwrap();
+        assert_eq!(renamed.name(), "after");
+        assert!(renamed.get("key").unwrap().is_some());
+        assert!(!backend.has_storage("before").unwrap());
+        assert!(!temp.path().join("before.redb").exists());
+        assert!(temp.path().join("after.redb").exists());
+        drop(renamed);
+
+        // Storages with an explicit file keep it, and record their new name
+        let config = RedbStorageConfig::new().with_db_file("fixed".to_string());
+        backend
+            .create_storage("fixed".to_string(), Some(config))
+            .unwrap();
+        backend
+            .rename_storage("fixed", "moved".to_string())
+            .unwrap();
+        assert!(temp.path().join("fixed.redb").exists());
+        let on_disk = backend.list_on_disk().unwrap();
+        assert!(on_disk.iter().any(|s| s.name == "moved" && s.registered));
+
+        // A file holding the tables of an unregistered storage stays in place
+        let config = RedbStorageConfig::new()
+            .with_db_file("after".to_string())
+            .with_table_name("removed".to_string());
+        backend
+            .create_storage("removed".to_string(), Some(config))
+            .unwrap();
+        backend.remove_storage("removed").unwrap();
+        let result = backend.rename_storage("after", "again".to_string());
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+        assert!(temp.path().join("after.redb").exists());
+        assert!(
+            backend
+                .get_storage("after")
+                .unwrap()
+                .get("key")
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_clone_storage() {
+        let (backend, temp) = create_test_backend();
+        let config = RedbStorageConfig::new()
+            .with_db_file("shared".to_string())
+            .with_table_name("source".to_string())
+            .with_keep_history(true);
+        let source = backend
+            .create_storage("source".to_string(), Some(config))
+            .unwrap();
+        put_value(&source, "key");
+
+        let clone = backend
+            .clone_storage("source", "clone".to_string())
+            .unwrap();
+        assert!(temp.path().join("clone.redb").exists());
+        assert!(!Arc::ptr_eq(source.database(), clone.database()));
+        assert!(clone.get("key").unwrap().is_some());
+        assert_eq!(clone.usage().unwrap(), source.usage().unwrap());
+        assert!(clone.verify().unwrap().is_ok());
+
+        // The clone is independent of its source
+        put_value(&clone, "other");
+        assert!(source.get("other").unwrap().is_none());
+
+        let result = backend.clone_storage("source", "clone".to_string());
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+        let result = backend.clone_storage("missing", "copy".to_string());
+        assert!(matches!(result, Err(RedbBackendError::StorageNotFound(_))));
+
+        // Names and files of clones being copied cannot be taken meanwhile
+        let reserved = canonical_path(&temp.path().join("copy.redb"));
+        backend
+            .reserved
+            .write()
+            .unwrap()
+            .insert("copy".to_string(), reserved);
+        let result = backend.create_storage("copy".to_string(), None);
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+        let config = RedbStorageConfig::new().with_db_file("copy".to_string());
+        let result = backend.create_storage("other".to_string(), Some(config));
+        assert!(matches!(result, Err(RedbBackendError::ConfigError(_))));
+        assert!(!temp.path().join("copy.redb").exists());
+    }
 }
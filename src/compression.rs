@@ -0,0 +1,165 @@
+//! Payload compression for the zenoh-backend-redb storage backend.
+//!
+//! The codec used for each entry is recorded in its DataInfo, so a storage can hold
+//! entries written with different compression settings.
+
+use crate::config::Compression;
+use crate::error::{RedbBackendError, Result};
+use std::borrow::Cow;
+
+/// Codec identifier of uncompressed payloads.
+pub(crate) const CODEC_NONE: u8 = 0;
+
+/// Codec identifier of LZ4-compressed payloads.
+pub(crate) const CODEC_LZ4: u8 = 1;
+
+/// Codec identifier of zstd-compressed payloads.
+pub(crate) const CODEC_ZSTD: u8 = 2;
+
+/// Check that a compression setting can be used by this build.
+pub(crate) fn validate(compression: Compression) -> Result<()> {
+    match compression {
+        Compression::None | Compression::Lz4 => Ok(()),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { level } => {
+            if zstd::compression_level_range().contains(&level) {
+                Ok(())
+            } else {
+                Err(RedbBackendError::config(format!(
+                    "Invalid zstd compression level: {} (supported: {:?})",
+                    level,
+                    zstd::compression_level_range()
+                )))
+            }
+        }
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd { .. } => Err(zstd_unavailable()),
+    }
+}
+
+/// Compress a payload according to `compression`.
+///
+/// Payloads shorter than `min_size`, or that do not get smaller, are kept as is.
+/// Returns the codec to record with the entry and the bytes to store.
+pub(crate) fn compress(
+    payload: &[u8],
+    compression: Compression,
+    min_size: usize,
+) -> Result<(u8, Cow<'_, [u8]>)> {
+    if payload.len() < min_size {
+        return Ok((CODEC_NONE, Cow::Borrowed(payload)));
+    }
+
+    let (codec, compressed) = match compression {
+        Compression::None => return Ok((CODEC_NONE, Cow::Borrowed(payload))),
+        Compression::Lz4 => (CODEC_LZ4, lz4_flex::compress_prepend_size(payload)),
+        Compression::Zstd { level } => (CODEC_ZSTD, zstd_compress(payload, level)?),
+    };
+
+    if compressed.len() < payload.len() {
+        Ok((codec, Cow::Owned(compressed)))
+    } else {
+        Ok((CODEC_NONE, Cow::Borrowed(payload)))
+    }
+}
+
+/// Decompress a stored payload written with `codec`.
+pub(crate) fn decompress(codec: u8, stored: &[u8]) -> Result<Cow<'_, [u8]>> {
+    match codec {
+        CODEC_NONE => Ok(Cow::Borrowed(stored)),
+        CODEC_LZ4 => lz4_flex::decompress_size_prepended(stored)
+            .map(Cow::Owned)
+            .map_err(|e| {
+                RedbBackendError::value_encoding(format!("LZ4 decompression failed: {}", e))
+            }),
+        CODEC_ZSTD => zstd_decompress(stored).map(Cow::Owned),
+        other => Err(RedbBackendError::value_encoding(format!(
+            "Unknown compression codec: {}",
+            other
+        ))),
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(payload: &[u8], level: i32) -> Result<Vec<u8>> {
+    zstd::bulk::compress(payload, level)
+        .map_err(|e| RedbBackendError::value_encoding(format!("zstd compression failed: {}", e)))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(stored: &[u8]) -> Result<Vec<u8>> {
+    zstd::decode_all(stored)
+        .map_err(|e| RedbBackendError::value_encoding(format!("zstd decompression failed: {}", e)))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_compress(_payload: &[u8], _level: i32) -> Result<Vec<u8>> {
+    Err(zstd_unavailable())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_stored: &[u8]) -> Result<Vec<u8>> {
+    Err(zstd_unavailable())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_unavailable() -> RedbBackendError {
+    RedbBackendError::config("zstd compression requires the `zstd` feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compressible() -> Vec<u8> {
+        br#"{"sensor":"temperature","value":23.5,"unit":"celsius"}"#.repeat(20)
+    }
+
+    #[test]
+    fn test_lz4_roundtrip() {
+        let payload = compressible();
+        let (codec, stored) = compress(&payload, Compression::Lz4, 0).unwrap();
+        assert_eq!(codec, CODEC_LZ4);
+        assert!(stored.len() < payload.len());
+        assert_eq!(
+            decompress(codec, &stored).unwrap().as_ref(),
+            payload.as_slice()
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_roundtrip() {
+        let payload = compressible();
+        let (codec, stored) = compress(&payload, Compression::Zstd { level: 3 }, 0).unwrap();
+        assert_eq!(codec, CODEC_ZSTD);
+        assert!(stored.len() < payload.len());
+        assert_eq!(
+            decompress(codec, &stored).unwrap().as_ref(),
+            payload.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_small_and_incompressible_payloads_kept_as_is() {
+        let payload = compressible();
+        let (codec, stored) = compress(&payload, Compression::Lz4, payload.len() + 1).unwrap();
+        assert_eq!(codec, CODEC_NONE);
+        assert_eq!(stored.as_ref(), payload.as_slice());
+
+        let (codec, _) = compress(b"abc", Compression::Lz4, 0).unwrap();
+        assert_eq!(codec, CODEC_NONE);
+    }
+
+    #[test]
+    fn test_unknown_codec() {
+        assert!(decompress(42, b"data").is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_validate_zstd_level() {
+        assert!(validate(Compression::Zstd { level: 3 }).is_ok());
+        assert!(validate(Compression::Zstd { level: 1000 }).is_err());
+    }
+}
@@ -1,7 +1,18 @@
 //! Configuration structures for the zenoh-backend-redb storage backend.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default table name of a storage.
+///
+/// Storages with this table name use the table names of files written before
+/// storages could share a database file.
+pub const DEFAULT_TABLE_NAME: &str = "zenoh_kv";
+
+/// Default number of versions kept per key when `keep_history` is enabled.
+pub const DEFAULT_MAX_VERSIONS: usize = 1000;
 
 /// Configuration for the redb backend.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,13 +26,18 @@ pub struct RedbBackendConfig {
     #[serde(default = "default_true")]
     pub create_dir: bool,
 
+    /// Whether to reopen the storages of the database files found in base_dir when
+    /// the backend is created. See [`RedbBackend::discover`](crate::RedbBackend::discover).
+    #[serde(default)]
+    pub open_existing: bool,
+
     /// Default configuration for storages (can be overridden per storage).
     #[serde(default)]
     pub default_storage_config: RedbStorageConfig,
 }
 
 /// Configuration for a single redb storage instance.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RedbStorageConfig {
     /// Database file name. If not specified, uses the storage name.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -41,17 +57,25 @@ pub struct RedbStorageConfig {
     #[serde(default = "default_true")]
     pub fsync: bool,
 
-    /// Key expression prefix for this storage.
-    /// Only keys matching this prefix will be stored.
+    /// Key expression of this storage.
+    /// Keys not included in it are rejected.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub key_expr: Option<String>,
 
-    /// Whether to strip the key_expr prefix from stored keys.
+    /// Whether to strip the key_expr prefix (its leading chunks without
+    /// wildcards) from stored keys.
     /// Default is false (store full key).
     #[serde(default)]
     pub strip_prefix: bool,
 
+    /// Whether to rewrite the stored keys when the storage is opened with a
+    /// `key_expr` or `strip_prefix` different from the ones it was written with.
+    /// Default is false (fail with a configuration mismatch error).
+    #[serde(default)]
+    pub migrate_keys: bool,
+
     /// Table name within the database.
+    /// Storages sharing a database file must have different table names.
     /// Default is "zenoh_kv"
     #[serde(default = "default_table_name")]
     pub table_name: String,
@@ -63,6 +87,211 @@ pub struct RedbStorageConfig {
     /// Read-only mode. If true, the storage will not accept writes.
     #[serde(default)]
     pub read_only: bool,
+
+    /// Whether to keep every version of each key instead of only the latest one.
+    /// Default is false.
+    #[serde(default)]
+    pub keep_history: bool,
+
+    /// Maximum number of versions kept per key when `keep_history` is enabled.
+    /// The oldest versions are dropped first. Default is [`DEFAULT_MAX_VERSIONS`];
+    /// `None` (`null`) keeps every version, so history grows without bound.
+    #[serde(default = "default_max_versions")]
+    pub max_versions: Option<usize>,
+
+    /// Time-to-live of entries in seconds, counted from their timestamp.
+    /// Expired entries are hidden from queries and removed by the expiry sweeper.
+    /// If not specified (or 0), entries never expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+
+    /// Per-key-prefix overrides of `ttl_secs`, in seconds.
+    /// The longest matching prefix wins; a value of 0 disables expiry for that prefix.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ttl_overrides: BTreeMap<String, u64>,
+
+    /// Maximum number of live entries. Unlimited if not specified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<u64>,
+
+    /// Maximum total size in bytes of the keys and payloads of live entries.
+    /// Unlimited if not specified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+
+    /// What to do when a write would exceed `max_entries` or `max_bytes`.
+    /// Default is to reject the write.
+    #[serde(default)]
+    pub quota_policy: QuotaPolicy,
+
+    /// Compression applied to new payloads. Default is no compression.
+    /// Existing entries keep the codec they were written with.
+    #[serde(default)]
+    pub compression: Compression,
+
+    /// Payloads smaller than this many bytes are stored uncompressed.
+    #[serde(default = "default_compression_min_size")]
+    pub compression_min_size: usize,
+
+    /// How entries are laid out in tables. Existing files are converted when opened
+    /// with a different layout. Default is two tables.
+    #[serde(default)]
+    pub layout: StorageLayout,
+
+    /// Encryption of payloads and encodings at rest. Disabled if not specified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<EncryptionConfig>,
+
+    /// Group commit of the writes received by the Zenoh plugin.
+    /// Disabled if not specified: each write is committed on its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_commit: Option<GroupCommitConfig>,
+}
+
+/// Configuration of group commit.
+///
+/// Puts and deletes are queued and committed together by a single writer, which
+/// waits up to `max_delay_ms` after the first queued write for others to join its
+/// batch. This trades a little latency for fewer fsyncs under bursts of writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupCommitConfig {
+    /// Longest time in milliseconds a write waits for others before being committed.
+    #[serde(default = "default_group_commit_max_delay_ms")]
+    pub max_delay_ms: u64,
+
+    /// Largest number of writes committed in one transaction.
+    #[serde(default = "default_group_commit_max_batch_size")]
+    pub max_batch_size: usize,
+}
+
+impl Default for GroupCommitConfig {
+    fn default() -> Self {
+        Self {
+            max_delay_ms: default_group_commit_max_delay_ms(),
+            max_batch_size: default_group_commit_max_batch_size(),
+        }
+    }
+}
+
+impl GroupCommitConfig {
+    /// Set the longest time a write waits for others before being committed.
+    pub fn with_max_delay_ms(mut self, max_delay_ms: u64) -> Self {
+        self.max_delay_ms = max_delay_ms;
+        self
+    }
+
+    /// Set the largest number of writes committed in one transaction.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+}
+
+/// Configuration of encryption at rest.
+///
+/// New entries are encrypted with the active key, and each entry records the
+/// identifier of its key. Keys can be rotated by adding a new key, making it
+/// active, and keeping the previous ones as long as entries still use them.
+///
+/// Only payloads and encodings are encrypted: keys, timestamps, tombstone flags
+/// and the other metadata of each entry are stored in plaintext.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Identifier of the key used for new writes.
+    pub active_key: u32,
+
+    /// Available keys. Identifiers must be non-zero and unique.
+    pub keys: Vec<EncryptionKeyConfig>,
+}
+
+/// A 256-bit encryption key and where to load it from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionKeyConfig {
+    /// Key identifier recorded with each entry encrypted with this key.
+    pub id: u32,
+
+    /// Source of the key material.
+    #[serde(flatten)]
+    pub source: KeySource,
+}
+
+/// Source of encryption key material.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// File holding the key, as 32 raw bytes or 64 hexadecimal characters.
+    File(PathBuf),
+    /// Environment variable holding the key as 64 hexadecimal characters.
+    Env(String),
+}
+
+impl EncryptionConfig {
+    /// Create an encryption configuration using `active_key` for new writes.
+    pub fn new(active_key: u32) -> Self {
+        Self {
+            active_key,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Add a key loaded from a file.
+    pub fn with_key_file(mut self, id: u32, path: PathBuf) -> Self {
+        self.keys.push(EncryptionKeyConfig {
+            id,
+            source: KeySource::File(path),
+        });
+        self
+    }
+
+    /// Add a key loaded from an environment variable.
+    pub fn with_key_env(mut self, id: u32, var: String) -> Self {
+        self.keys.push(EncryptionKeyConfig {
+            id,
+            source: KeySource::Env(var),
+        });
+        self
+    }
+}
+
+/// Compression codec applied to stored payloads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "codec", rename_all = "snake_case")]
+pub enum Compression {
+    /// Store payloads as is.
+    #[default]
+    None,
+    /// LZ4 block compression: fast, moderate ratio.
+    Lz4,
+    /// zstd compression at the given level (requires the `zstd` feature).
+    Zstd {
+        /// Compression level, higher is smaller but slower.
+        #[serde(default = "default_zstd_level")]
+        level: i32,
+    },
+}
+
+/// Table layout of a storage's entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageLayout {
+    /// Payloads and metadata in separate tables.
+    #[default]
+    TwoTables,
+    /// Metadata and payload together in one record, so each operation on an entry
+    /// touches a single table.
+    SingleTable,
+}
+
+/// Policy applied when a write would exceed a storage's size quota.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaPolicy {
+    /// Reject the write with [`RedbBackendError::QuotaExceeded`](crate::RedbBackendError::QuotaExceeded).
+    #[default]
+    Reject,
+    /// Evict the entries with the oldest timestamps until the write fits.
+    /// Evicted entries leave a tombstone, as if they were deleted.
+    EvictOldest,
 }
 
 impl Default for RedbBackendConfig {
@@ -70,11 +299,41 @@ impl Default for RedbBackendConfig {
         Self {
             base_dir: default_base_dir(),
             create_dir: true,
+            open_existing: false,
             default_storage_config: RedbStorageConfig::default(),
         }
     }
 }
 
+impl Default for RedbStorageConfig {
+    fn default() -> Self {
+        Self {
+            db_file: None,
+            db_path: None,
+            cache_size: None,
+            fsync: true,
+            key_expr: None,
+            strip_prefix: false,
+            migrate_keys: false,
+            table_name: default_table_name(),
+            create_db: true,
+            read_only: false,
+            keep_history: false,
+            max_versions: default_max_versions(),
+            ttl_secs: None,
+            ttl_overrides: BTreeMap::new(),
+            max_entries: None,
+            max_bytes: None,
+            quota_policy: QuotaPolicy::default(),
+            compression: Compression::default(),
+            compression_min_size: default_compression_min_size(),
+            layout: StorageLayout::default(),
+            encryption: None,
+            group_commit: None,
+        }
+    }
+}
+
 impl RedbBackendConfig {
     /// Create a new configuration with default values.
     pub fn new() -> Self {
@@ -93,6 +352,12 @@ impl RedbBackendConfig {
         self
     }
 
+    /// Set whether to reopen the storages found in base_dir at creation.
+    pub fn with_open_existing(mut self, open_existing: bool) -> Self {
+        self.open_existing = open_existing;
+        self
+    }
+
     /// Set the default storage configuration.
     pub fn with_default_storage_config(mut self, config: RedbStorageConfig) -> Self {
         self.default_storage_config = config;
@@ -142,6 +407,12 @@ impl RedbStorageConfig {
         self
     }
 
+    /// Set whether to rewrite the stored keys on a key scope change.
+    pub fn with_migrate_keys(mut self, migrate_keys: bool) -> Self {
+        self.migrate_keys = migrate_keys;
+        self
+    }
+
     /// Set the table name.
     pub fn with_table_name(mut self, table_name: String) -> Self {
         self.table_name = table_name;
@@ -160,6 +431,108 @@ impl RedbStorageConfig {
         self
     }
 
+    /// Set whether to keep every version of each key.
+    pub fn with_keep_history(mut self, keep_history: bool) -> Self {
+        self.keep_history = keep_history;
+        self
+    }
+
+    /// Set the maximum number of versions kept per key.
+    pub fn with_max_versions(mut self, max_versions: usize) -> Self {
+        self.max_versions = Some(max_versions);
+        self
+    }
+
+    /// Keep every version of each key, without a maximum number of versions.
+    pub fn without_max_versions(mut self) -> Self {
+        self.max_versions = None;
+        self
+    }
+
+    /// Set the time-to-live of entries in seconds.
+    pub fn with_ttl_secs(mut self, ttl_secs: u64) -> Self {
+        self.ttl_secs = Some(ttl_secs);
+        self
+    }
+
+    /// Override the time-to-live for keys starting with `prefix`.
+    pub fn with_ttl_override(mut self, prefix: String, ttl_secs: u64) -> Self {
+        self.ttl_overrides.insert(prefix, ttl_secs);
+        self
+    }
+
+    /// Set the maximum number of live entries.
+    pub fn with_max_entries(mut self, max_entries: u64) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Set the maximum total size in bytes of live entries.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Set the policy applied when a quota is exceeded.
+    pub fn with_quota_policy(mut self, quota_policy: QuotaPolicy) -> Self {
+        self.quota_policy = quota_policy;
+        self
+    }
+
+    /// Set the table layout of entries.
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set the compression applied to new payloads.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the minimum payload size for compression.
+    pub fn with_compression_min_size(mut self, min_size: usize) -> Self {
+        self.compression_min_size = min_size;
+        self
+    }
+
+    /// Enable encryption at rest.
+    pub fn with_encryption(mut self, encryption: EncryptionConfig) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Enable group commit of the writes received by the Zenoh plugin.
+    pub fn with_group_commit(mut self, group_commit: GroupCommitConfig) -> Self {
+        self.group_commit = Some(group_commit);
+        self
+    }
+
+    /// Whether any time-to-live is configured.
+    pub fn has_ttl(&self) -> bool {
+        self.ttl_secs.is_some_and(|ttl| ttl > 0) || self.ttl_overrides.values().any(|&ttl| ttl > 0)
+    }
+
+    /// Whether the oldest entries are evicted to meet a configured quota.
+    pub fn evicts_oldest(&self) -> bool {
+        self.quota_policy == QuotaPolicy::EvictOldest
+            && (self.max_entries.is_some() || self.max_bytes.is_some())
+    }
+
+    /// Get the time-to-live applying to `key`, if it expires.
+    pub fn ttl_for(&self, key: &str) -> Option<Duration> {
+        let ttl_secs = self
+            .ttl_overrides
+            .iter()
+            .filter(|(prefix, _)| key.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, &ttl)| ttl)
+            .or(self.ttl_secs)?;
+
+        (ttl_secs > 0).then(|| Duration::from_secs(ttl_secs))
+    }
+
     /// Get the effective database path for a given storage name and backend config.
     pub fn effective_db_path(
         &self,
@@ -187,7 +560,27 @@ fn default_true() -> bool {
 }
 
 fn default_table_name() -> String {
-    "zenoh_kv".to_string()
+    DEFAULT_TABLE_NAME.to_string()
+}
+
+fn default_max_versions() -> Option<usize> {
+    Some(DEFAULT_MAX_VERSIONS)
+}
+
+fn default_compression_min_size() -> usize {
+    64
+}
+
+fn default_zstd_level() -> i32 {
+    3
+}
+
+fn default_group_commit_max_delay_ms() -> u64 {
+    2
+}
+
+fn default_group_commit_max_batch_size() -> usize {
+    1024
 }
 
 #[cfg(test)]
@@ -199,6 +592,20 @@ mod tests {
         let config = RedbBackendConfig::default();
         assert_eq!(config.base_dir, PathBuf::from("./zenoh_redb_backend"));
         assert!(config.create_dir);
+        assert!(!config.open_existing);
+    }
+
+    #[test]
+    fn test_default_storage_config_matches_serde_defaults() {
+        let config = RedbStorageConfig::default();
+        let deserialized: RedbStorageConfig = serde_json::from_str("{}").unwrap();
+
+        assert!(config.fsync);
+        assert!(config.create_db);
+        assert_eq!(config.table_name, "zenoh_kv");
+        assert_eq!(config.fsync, deserialized.fsync);
+        assert_eq!(config.create_db, deserialized.create_db);
+        assert_eq!(config.table_name, deserialized.table_name);
     }
 
     #[test]
@@ -213,6 +620,100 @@ mod tests {
         assert!(!config.fsync);
     }
 
+    #[test]
+    fn test_max_versions_serde() {
+        let config: RedbStorageConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.max_versions, Some(DEFAULT_MAX_VERSIONS));
+        assert_eq!(
+            RedbStorageConfig::default().max_versions,
+            config.max_versions
+        );
+
+        let config: RedbStorageConfig = serde_json::from_str(r#"{"max_versions": null}"#).unwrap();
+        assert_eq!(config.max_versions, None);
+        let json = serde_json::to_string(&config).unwrap();
+        let deserialized: RedbStorageConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.max_versions, None);
+    }
+
+    #[test]
+    fn test_quota_policy_serde() {
+        let config: RedbStorageConfig =
+            serde_json::from_str(r#"{"max_entries": 10, "quota_policy": "evict_oldest"}"#).unwrap();
+        assert_eq!(config.max_entries, Some(10));
+        assert_eq!(config.quota_policy, QuotaPolicy::EvictOldest);
+        assert_eq!(
+            RedbStorageConfig::default().quota_policy,
+            QuotaPolicy::Reject
+        );
+    }
+
+    #[test]
+    fn test_layout_serde() {
+        let config: RedbStorageConfig =
+            serde_json::from_str(r#"{"layout": "single_table"}"#).unwrap();
+        assert_eq!(config.layout, StorageLayout::SingleTable);
+        assert_eq!(
+            RedbStorageConfig::default().layout,
+            StorageLayout::TwoTables
+        );
+    }
+
+    #[test]
+    fn test_group_commit_serde() {
+        let config: RedbStorageConfig =
+            serde_json::from_str(r#"{"group_commit": {"max_delay_ms": 5}}"#).unwrap();
+        assert_eq!(
+            config.group_commit,
+            Some(GroupCommitConfig::default().with_max_delay_ms(5))
+        );
+        assert_eq!(RedbStorageConfig::default().group_commit, None);
+    }
+
+    #[test]
+    fn test_compression_serde() {
+        let config: RedbStorageConfig =
+            serde_json::from_str(r#"{"compression": {"codec": "zstd"}}"#).unwrap();
+        assert_eq!(config.compression, Compression::Zstd { level: 3 });
+        assert_eq!(config.compression_min_size, 64);
+
+        let config: RedbStorageConfig =
+            serde_json::from_str(r#"{"compression": {"codec": "lz4"}}"#).unwrap();
+        assert_eq!(config.compression, Compression::Lz4);
+        assert_eq!(RedbStorageConfig::default().compression, Compression::None);
+    }
+
+    #[test]
+    fn test_encryption_config_serde() {
+        let config: RedbStorageConfig = serde_json::from_str(
+            r#"{"encryption": {"active_key": 2, "keys": [
+                {"id": 1, "file": "/etc/zenoh/key1"},
+                {"id": 2, "env": "REDB_KEY_2"}
+            ]}}"#,
+        )
+        .unwrap();
+
+        let expected = EncryptionConfig::new(2)
+            .with_key_file(1, PathBuf::from("/etc/zenoh/key1"))
+            .with_key_env(2, "REDB_KEY_2".to_string());
+        assert_eq!(config.encryption, Some(expected));
+    }
+
+    #[test]
+    fn test_ttl_for() {
+        let config = RedbStorageConfig::new()
+            .with_ttl_secs(60)
+            .with_ttl_override("presence/".to_string(), 10)
+            .with_ttl_override("presence/static/".to_string(), 0);
+
+        assert!(config.has_ttl());
+        assert_eq!(config.ttl_for("sensor/temp"), Some(Duration::from_secs(60)));
+        assert_eq!(config.ttl_for("presence/a"), Some(Duration::from_secs(10)));
+        assert_eq!(config.ttl_for("presence/static/a"), None);
+        assert!(!RedbStorageConfig::new().has_ttl());
+        assert_eq!(RedbStorageConfig::new().ttl_for("sensor/temp"), None);
+    }
+
     #[test]
     fn test_effective_db_path() {
         let backend_config = RedbBackendConfig::default();
@@ -0,0 +1,298 @@
+//! Encryption at rest for the zenoh-backend-redb storage backend.
+//!
+//! Payloads and encodings are sealed with XChaCha20-Poly1305. Each sealed value is
+//! bound to the key of its entry, so ciphertexts cannot be swapped between keys
+//! without failing authentication. The identifier of the encryption key is
+//! recorded in the DataInfo of each entry, which allows keys to be rotated.
+//!
+//! The rest of the DataInfo (timestamp, tombstone flag, codec, checksum) and the
+//! keys themselves stay in plaintext, as they are needed to order, expire and query
+//! entries without decrypting them.
+
+use crate::config::{EncryptionConfig, KeySource};
+use crate::error::{RedbBackendError, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::collections::HashMap;
+use std::env::VarError;
+use std::fmt;
+
+/// Key identifier of entries stored in plaintext.
+pub(crate) const KEY_ID_NONE: u32 = 0;
+
+/// Size of an encryption key in bytes.
+const KEY_SIZE: usize = 32;
+
+/// Size of the random nonce stored in front of each ciphertext.
+const NONCE_SIZE: usize = 24;
+
+/// Domain separating what a ciphertext holds, so a sealed payload cannot be
+/// passed off as the sealed encoding of the same entry.
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub(crate) enum Domain {
+    Payload = 1,
+    Encoding = 2,
+}
+
+/// Ciphers of the configured keys, indexed by key identifier.
+pub(crate) struct Cipher {
+    active: u32,
+    keys: HashMap<u32, XChaCha20Poly1305>,
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids: Vec<_> = self.keys.keys().collect();
+        ids.sort();
+        f.debug_struct("Cipher")
+            .field("active", &self.active)
+            .field("keys", &ids)
+            .finish()
+    }
+}
+
+impl Cipher {
+    /// Load the keys of an encryption configuration.
+    pub fn from_config(config: &EncryptionConfig) -> Result<Self> {
+        Self::from_config_with_env(config, |var| std::env::var(var))
+    }
+
+    /// Load the keys of an encryption configuration, reading environment variables
+    /// with `env`.
+    fn from_config_with_env<F>(config: &EncryptionConfig, env: F) -> Result<Self>
+    where
+        F: Fn(&str) -> std::result::Result<String, VarError>,
+    {
+        let mut keys = HashMap::with_capacity(config.keys.len());
+        for key_config in &config.keys {
+            if key_config.id == KEY_ID_NONE {
+                return Err(RedbBackendError::config(
+                    "Encryption key id 0 is reserved for plaintext entries",
+                ));
+            }
+            let key = load_key(&key_config.source, &env)?;
+            let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+            if keys.insert(key_config.id, cipher).is_some() {
+                return Err(RedbBackendError::config(format!(
+                    "Duplicate encryption key id: {}",
+                    key_config.id
+                )));
+            }
+        }
+
+        if !keys.contains_key(&config.active_key) {
+            return Err(RedbBackendError::config(format!(
+                "Active encryption key {} is not configured",
+                config.active_key
+            )));
+        }
+
+        Ok(Self {
+            active: config.active_key,
+            keys,
+        })
+    }
+
+    /// Identifier of the key used for new writes.
+    pub fn active_key_id(&self) -> u32 {
+        self.active
+    }
+
+    /// Encrypt `plaintext` with the active key.
+    ///
+    /// Returns the nonce followed by the ciphertext and its authentication tag.
+    pub fn seal(&self, domain: Domain, key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let cipher = &self.keys[&self.active];
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = associated_data(domain, key);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| RedbBackendError::other("Failed to encrypt value"))?;
+
+        let mut sealed = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a value sealed with key `key_id`.
+    pub fn open(&self, key_id: u32, domain: Domain, key: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        let cipher = self.keys.get(&key_id).ok_or_else(|| {
+            RedbBackendError::decryption(format!("Encryption key {} is not configured", key_id))
+        })?;
+        if sealed.len() < NONCE_SIZE {
+            return Err(RedbBackendError::decryption("Encrypted value is truncated"));
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+        let aad = associated_data(domain, key);
+        cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                RedbBackendError::decryption(format!(
+                    "Authentication failed with encryption key {}",
+                    key_id
+                ))
+            })
+    }
+}
+
+/// Associated data binding a ciphertext to its domain and entry key.
+fn associated_data(domain: Domain, key: &[u8]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(1 + key.len());
+    aad.push(domain as u8);
+    aad.extend_from_slice(key);
+    aad
+}
+
+/// Read the key material of a key source.
+///
+/// Files hold the key as raw bytes or hexadecimal text, environment variables, read
+/// with `env`, as hexadecimal text.
+fn load_key<F>(source: &KeySource, env: F) -> Result<[u8; KEY_SIZE]>
+where
+    F: Fn(&str) -> std::result::Result<String, VarError>,
+{
+    let bytes = match source {
+        KeySource::File(path) => {
+            let content = std::fs::read(path).map_err(|e| {
+                RedbBackendError::config(format!(
+                    "Failed to read encryption key file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            if content.len() == KEY_SIZE {
+                content
+            } else {
+                decode_hex_key(String::from_utf8_lossy(&content).trim())
+                    .map_err(|e| RedbBackendError::config(format!("{} in {}", e, path.display())))?
+            }
+        }
+        KeySource::Env(var) => {
+            let value = env(var).map_err(|e| {
+                RedbBackendError::config(format!(
+                    "Failed to read encryption key variable {}: {}",
+                    var, e
+                ))
+            })?;
+            decode_hex_key(value.trim())
+                .map_err(|e| RedbBackendError::config(format!("{} in ${}", e, var)))?
+        }
+    };
+
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        RedbBackendError::config(format!(
+            "Encryption key must be {} bytes, got {}",
+            KEY_SIZE,
+            bytes.len()
+        ))
+    })
+}
+
+/// Decode a key written as hexadecimal text.
+fn decode_hex_key(text: &str) -> std::result::Result<Vec<u8>, String> {
+    hex::decode(text).map_err(|e| format!("Invalid hexadecimal encryption key ({})", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncryptionConfig;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// Environment without any variable set.
+    fn no_env(_: &str) -> std::result::Result<String, VarError> {
+        Err(VarError::NotPresent)
+    }
+
+    fn key_file(dir: &TempDir, name: &str, content: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let dir = TempDir::new().unwrap();
+        let config = EncryptionConfig::new(1).with_key_file(1, key_file(&dir, "k1", &[7u8; 32]));
+        let cipher = Cipher::from_config(&config).unwrap();
+
+        let sealed = cipher.seal(Domain::Payload, b"a/b", b"secret").unwrap();
+        assert!(!sealed.windows(6).any(|w| w == b"secret"));
+        let opened = cipher.open(1, Domain::Payload, b"a/b", &sealed).unwrap();
+        assert_eq!(opened, b"secret");
+
+        // Ciphertexts are bound to their key and domain
+        for (domain, key) in [(Domain::Payload, &b"a/c"[..]), (Domain::Encoding, b"a/b")] {
+            assert!(matches!(
+                cipher.open(1, domain, key, &sealed),
+                Err(RedbBackendError::DecryptionError(_))
+            ));
+        }
+        assert!(matches!(
+            cipher.open(2, Domain::Payload, b"a/b", &sealed),
+            Err(RedbBackendError::DecryptionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_hex_keys() {
+        let dir = TempDir::new().unwrap();
+        let hex_key = hex::encode([9u8; 32]);
+        let path = key_file(&dir, "k1", format!("{}\n", hex_key).as_bytes());
+        assert_eq!(load_key(&KeySource::File(path), no_env).unwrap(), [9u8; 32]);
+
+        let env = |var: &str| match var {
+            "STORAGE_KEY" => Ok(format!(" {} ", hex_key)),
+            _ => Err(VarError::NotPresent),
+        };
+        let source = KeySource::Env("STORAGE_KEY".to_string());
+        assert_eq!(load_key(&source, env).unwrap(), [9u8; 32]);
+        let config = EncryptionConfig::new(1).with_key_env(1, "STORAGE_KEY".to_string());
+        assert_eq!(
+            Cipher::from_config_with_env(&config, env)
+                .unwrap()
+                .active_key_id(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        let dir = TempDir::new().unwrap();
+        let short = key_file(&dir, "short", b"abcd");
+        let good = key_file(&dir, "good", &[1u8; 32]);
+
+        let configs = [
+            EncryptionConfig::new(1).with_key_file(1, short),
+            EncryptionConfig::new(1).with_key_file(1, dir.path().join("missing")),
+            EncryptionConfig::new(2).with_key_file(1, good.clone()),
+            EncryptionConfig::new(0).with_key_file(0, good.clone()),
+            EncryptionConfig::new(1)
+                .with_key_file(1, good.clone())
+                .with_key_file(1, good),
+            EncryptionConfig::new(1).with_key_env(1, "UNSET_KEY".to_string()),
+        ];
+        for config in configs {
+            assert!(matches!(
+                Cipher::from_config_with_env(&config, no_env),
+                Err(RedbBackendError::ConfigError(_))
+            ));
+        }
+    }
+}
@@ -50,6 +50,30 @@ pub enum RedbBackendError {
     #[error("Storage not found: {0}")]
     StorageNotFound(String),
 
+    /// Database file not found and creation is disabled.
+    #[error("Database not found: {0} (create_db is disabled)")]
+    DatabaseNotFound(String),
+
+    /// Encrypted data could not be decrypted (wrong or missing key, or tampered data).
+    #[error("Decryption error: {0}")]
+    DecryptionError(String),
+
+    /// Stored data failed an integrity check.
+    #[error("Data corruption: {0}")]
+    CorruptionError(String),
+
+    /// The database file uses an unsupported or invalid schema.
+    #[error("Schema error: {0}")]
+    SchemaError(String),
+
+    /// The storage is opened with settings incompatible with how its data was written.
+    #[error("Configuration mismatch: {0}")]
+    ConfigMismatch(String),
+
+    /// Write rejected because the storage reached its size quota.
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
     /// I/O error.
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
@@ -94,6 +118,36 @@ impl RedbBackendError {
         RedbBackendError::StorageNotFound(name.into())
     }
 
+    /// Create a database not found error.
+    pub fn database_not_found<S: Into<String>>(path: S) -> Self {
+        RedbBackendError::DatabaseNotFound(path.into())
+    }
+
+    /// Create a decryption error.
+    pub fn decryption<S: Into<String>>(msg: S) -> Self {
+        RedbBackendError::DecryptionError(msg.into())
+    }
+
+    /// Create a data corruption error.
+    pub fn corruption<S: Into<String>>(msg: S) -> Self {
+        RedbBackendError::CorruptionError(msg.into())
+    }
+
+    /// Create a schema error.
+    pub fn schema<S: Into<String>>(msg: S) -> Self {
+        RedbBackendError::SchemaError(msg.into())
+    }
+
+    /// Create a configuration mismatch error.
+    pub fn config_mismatch<S: Into<String>>(msg: S) -> Self {
+        RedbBackendError::ConfigMismatch(msg.into())
+    }
+
+    /// Create a quota exceeded error.
+    pub fn quota_exceeded<S: Into<String>>(msg: S) -> Self {
+        RedbBackendError::QuotaExceeded(msg.into())
+    }
+
     /// Create a Zenoh error.
     pub fn zenoh<S: Into<String>>(msg: S) -> Self {
         RedbBackendError::ZenohError(msg.into())
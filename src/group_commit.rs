@@ -0,0 +1,267 @@
+//! Group commit for the Zenoh plugin of the zenoh-backend-redb storage backend.
+//!
+//! The puts and deletes received by a storage are queued to a single writer task,
+//! which commits them together with [`RedbStorage::write_batch`] and then completes
+//! each caller with the outcome of its own write. Under bursts of writes this turns
+//! many fsync'd commits into a few.
+
+use crate::config::GroupCommitConfig;
+use crate::error::{RedbBackendError, Result};
+use crate::storage::{RedbStorage, WriteOp, WriteOutcome};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::{debug, trace, warn};
+
+/// A queued write and the channel completing its caller.
+struct PendingWrite {
+    op: WriteOp,
+    reply: oneshot::Sender<Result<WriteOutcome>>,
+}
+
+/// Queue of the writes committed by the writer task of a storage.
+///
+/// The writer task stops once the queue has been dropped and drained.
+pub(crate) struct GroupCommitter {
+    queue: mpsc::Sender<PendingWrite>,
+}
+
+impl GroupCommitter {
+    /// Spawn the writer task of `storage`.
+    pub fn spawn(storage: Arc<Mutex<Arc<RedbStorage>>>, config: GroupCommitConfig) -> Self {
+        let max_batch_size = config.max_batch_size.max(1);
+        let max_delay = Duration::from_millis(config.max_delay_ms);
+
+        // Callers wait for room in the queue when the writer falls behind
+        let (queue, receiver) = mpsc::channel(max_batch_size);
+        tokio::spawn(run_writer(storage, receiver, max_delay, max_batch_size));
+
+        Self { queue }
+    }
+
+    /// Queue `op` and wait until it has been committed.
+    pub async fn write(&self, op: WriteOp) -> Result<WriteOutcome> {
+        let stopped = || RedbBackendError::other("Group commit writer has stopped");
+
+        let (reply, outcome) = oneshot::channel();
+        self.queue
+            .send(PendingWrite { op, reply })
+            .await
+            .map_err(|_| stopped())?;
+        outcome.await.map_err(|_| stopped())?
+    }
+}
+
+/// Commit the queued writes in batches until the queue is closed.
+///
+/// A batch starts with the first queued write and is committed once it holds
+/// `max_batch_size` writes or `max_delay` has elapsed. Commits wait for fsync, so
+/// they run on the blocking thread pool rather than on the async runtime.
+async fn run_writer(
+    storage: Arc<Mutex<Arc<RedbStorage>>>,
+    mut receiver: mpsc::Receiver<PendingWrite>,
+    max_delay: Duration,
+    max_batch_size: usize,
+) {
+    while let Some(first) = receiver.recv().await {
+        let mut batch = Vec::with_capacity(max_batch_size);
+        batch.push(first);
+
+        let deadline = Instant::now() + max_delay;
+        while batch.len() < max_batch_size {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(write)) => batch.push(write),
+                // The queue was closed or the delay elapsed
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        let storage = storage.lock().await;
+        let committed = Arc::clone(&storage);
+        if let Err(e) = tokio::task::spawn_blocking(move || commit(&committed, batch)).await {
+            // The callers of the batch are completed with an error as their replies drop
+            warn!("Group commit to storage '{}' failed: {}", storage.name(), e);
+        }
+    }
+
+    debug!("Group commit writer stopped");
+}
+
+/// Commit a batch of writes and complete their callers.
+fn commit(storage: &RedbStorage, batch: Vec<PendingWrite>) {
+    let (ops, replies): (Vec<_>, Vec<_>) = batch
+        .into_iter()
+        .map(|write| (write.op, write.reply))
+        .unzip();
+
+    trace!(
+        "Committing a group of {} writes to storage '{}'",
+        ops.len(),
+        storage.name()
+    );
+    commit_ops(storage, &ops, replies);
+}
+
+/// Commit `ops` in one transaction and complete their callers.
+///
+/// If the transaction fails, each half of the writes is committed on its own, in
+/// order, down to single writes, so that only the callers whose write fails get an
+/// error.
+fn commit_ops(
+    storage: &RedbStorage,
+    ops: &[WriteOp],
+    mut replies: Vec<oneshot::Sender<Result<WriteOutcome>>>,
+) {
+    // Callers that stopped waiting are not completed
+    match storage.write_batch(ops) {
+        Ok(outcomes) => {
+            for (reply, outcome) in replies.into_iter().zip(outcomes) {
+                let _ = reply.send(Ok(outcome));
+            }
+        }
+        Err(e) if ops.len() == 1 => {
+            let _ = replies.into_iter().next().map(|reply| reply.send(Err(e)));
+        }
+        Err(e) => {
+            debug!(
+                "Group commit of {} writes to storage '{}' failed ({}), committing each half",
+                ops.len(),
+                storage.name(),
+                e
+            );
+            let (first, second) = ops.split_at(ops.len() / 2);
+            let second_replies = replies.split_off(first.len());
+            commit_ops(storage, first, replies);
+            commit_ops(storage, second, second_replies);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RedbStorageConfig;
+    use crate::storage::StoredValue;
+    use tempfile::TempDir;
+    use zenoh::bytes::Encoding;
+    use zenoh::time::{NTP64, Timestamp, TimestampId};
+
+    fn create_committer(
+        config: RedbStorageConfig,
+        group_commit: GroupCommitConfig,
+    ) -> (GroupCommitter, Arc<Mutex<Arc<RedbStorage>>>, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = RedbStorage::new(
+            temp_dir.path().join("group_commit.redb"),
+            config,
+            "group_commit".to_string(),
+        )
+        .unwrap();
+        let storage = Arc::new(Mutex::new(Arc::new(storage)));
+        let committer = GroupCommitter::spawn(storage.clone(), group_commit);
+        (committer, storage, temp_dir)
+    }
+
+    fn put(key: &str, time: u64) -> WriteOp {
+        WriteOp::Put {
+            key: key.to_string(),
+            value: StoredValue::new(
+                key.as_bytes().to_vec(),
+                Timestamp::new(NTP64(time), TimestampId::rand()),
+                Encoding::ZENOH_STRING,
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writes_are_committed() {
+        let (committer, storage, _temp_dir) = create_committer(
+            RedbStorageConfig::new(),
+            GroupCommitConfig::default().with_max_batch_size(16),
+        );
+        let committer = Arc::new(committer);
+
+        let tasks: Vec<_> = (0..100)
+            .map(|i| {
+                let committer = committer.clone();
+                tokio::spawn(async move { committer.write(put(&format!("key/{}", i), 100)).await })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), WriteOutcome::Inserted);
+        }
+        assert_eq!(storage.lock().await.count().unwrap(), 100);
+
+        // Later writes follow last-writer-wins against the committed ones
+        assert_eq!(
+            committer.write(put("key/0", 50)).await.unwrap(),
+            WriteOutcome::Outdated
+        );
+        let delete = WriteOp::Delete {
+            key: "key/1".to_string(),
+            timestamp: Timestamp::new(NTP64(200), TimestampId::rand()),
+        };
+        assert_eq!(
+            committer.write(delete).await.unwrap(),
+            WriteOutcome::Deleted
+        );
+        assert!(storage.lock().await.get("key/1").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_does_not_fail_its_group() {
+        // The writes wait long enough to be committed together
+        let (committer, storage, _temp_dir) = create_committer(
+            RedbStorageConfig::new().with_max_entries(1),
+            GroupCommitConfig::default().with_max_delay_ms(200),
+        );
+
+        let (first, second) = tokio::join!(
+            committer.write(put("key/1", 100)),
+            committer.write(put("key/2", 100))
+        );
+        assert_eq!(first.unwrap(), WriteOutcome::Inserted);
+        assert!(matches!(second, Err(RedbBackendError::QuotaExceeded(_))));
+        assert_eq!(storage.lock().await.count().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_is_isolated_by_halves() {
+        let (committer, storage, _temp_dir) = create_committer(
+            RedbStorageConfig::new().with_max_bytes(1000),
+            GroupCommitConfig::default()
+                .with_max_delay_ms(200)
+                .with_max_batch_size(8),
+        );
+        let committer = Arc::new(committer);
+
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let committer = committer.clone();
+                let op = if i == 5 {
+                    WriteOp::Put {
+                        key: "key/large".to_string(),
+                        value: StoredValue::new(
+                            vec![0; 2000],
+                            Timestamp::new(NTP64(100), TimestampId::rand()),
+                            Encoding::ZENOH_BYTES,
+                        ),
+                    }
+                } else {
+                    put(&format!("key/{}", i), 100)
+                };
+                tokio::spawn(async move { committer.write(op).await })
+            })
+            .collect();
+        for (i, task) in tasks.into_iter().enumerate() {
+            let outcome = task.await.unwrap();
+            if i == 5 {
+                assert!(matches!(outcome, Err(RedbBackendError::QuotaExceeded(_))));
+            } else {
+                assert_eq!(outcome.unwrap(), WriteOutcome::Inserted);
+            }
+        }
+        assert_eq!(storage.lock().await.count().unwrap(), 7);
+    }
+}
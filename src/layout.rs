@@ -0,0 +1,460 @@
+//! Table layouts of the zenoh-backend-redb storage backend.
+//!
+//! With [`StorageLayout::TwoTables`] the payload and the data_info of an entry are
+//! kept in separate tables, like the column families of the RocksDB backend. With
+//! [`StorageLayout::SingleTable`] they are stored together in one record: a compact
+//! header, the data_info, then the payload. Each read or write of an entry then
+//! touches a single B-tree.
+//!
+//! [`EntryTables`] gives the rest of the storage the same view of both layouts,
+//! for the latest values as well as for the past versions kept in history mode.
+
+use crate::config::StorageLayout;
+use crate::error::{RedbBackendError, Result};
+use crate::storage::{QuarantineTable, TableNames};
+use redb::{
+    AccessGuard, Range, ReadOnlyTable, ReadTransaction, ReadableTable, Table, TableDefinition,
+    TableHandle, WriteTransaction,
+};
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+
+/// Size of the header of single-table records: a flags byte followed by the
+/// little-endian u32 length of the data_info.
+const RECORD_HEADER_SIZE: usize = 5;
+
+/// Record flag set when the record holds a payload, i.e. for live entries.
+const RECORD_HAS_PAYLOAD: u8 = 0x01;
+
+/// Encode a single-table record.
+pub(crate) fn encode_record(info: &[u8], payload: Option<&[u8]>) -> Result<Vec<u8>> {
+    let info_len = u32::try_from(info.len())
+        .map_err(|_| RedbBackendError::serialization("Data-info too large for a record"))?;
+    let (flags, payload) = match payload {
+        Some(payload) => (RECORD_HAS_PAYLOAD, payload),
+        None => (0, &[][..]),
+    };
+
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + info.len() + payload.len());
+    record.push(flags);
+    record.extend_from_slice(&info_len.to_le_bytes());
+    record.extend_from_slice(info);
+    record.extend_from_slice(payload);
+    Ok(record)
+}
+
+/// Split a single-table record into its data_info and payload.
+pub(crate) fn decode_record(record: &[u8]) -> Result<(&[u8], Option<&[u8]>)> {
+    let invalid = || RedbBackendError::serialization("Invalid record header");
+    let (header, body) = record
+        .split_at_checked(RECORD_HEADER_SIZE)
+        .ok_or_else(invalid)?;
+    let info_len = u32::from_le_bytes(header[1..].try_into().expect("4 bytes")) as usize;
+    let (info, payload) = body.split_at_checked(info_len).ok_or_else(invalid)?;
+    match header[0] {
+        0 if payload.is_empty() => Ok((info, None)),
+        RECORD_HAS_PAYLOAD => Ok((info, Some(payload))),
+        _ => Err(invalid()),
+    }
+}
+
+/// Payload bytes of an entry, borrowed from a record or held by their own guard.
+pub(crate) enum PayloadRef<'a> {
+    Inline(&'a [u8]),
+    Guard(AccessGuard<'a, &'static [u8]>),
+}
+
+impl PayloadRef<'_> {
+    pub fn value(&self) -> &[u8] {
+        match self {
+            PayloadRef::Inline(payload) => payload,
+            PayloadRef::Guard(guard) => guard.value(),
+        }
+    }
+}
+
+/// Stored bytes detached from the lifetime of the tables they were read from.
+pub(crate) type OwnedGuard = AccessGuard<'static, &'static [u8]>;
+
+/// The tables holding entries in a given layout.
+///
+/// Entries are looked up and iterated through their *index* table: the data_info
+/// table with two tables, the records table with a single table. The index value of
+/// an entry gives its data_info with [`info`](Self::info) and its payload with
+/// [`payload`](Self::payload).
+pub(crate) enum EntryTables<T> {
+    TwoTables { payloads: T, data_info: T },
+    SingleTable { records: T },
+}
+
+/// Table definitions of the entries, or of the past versions, in `layout`.
+pub(crate) fn definitions(
+    tables: &TableNames,
+    layout: StorageLayout,
+    history: bool,
+) -> Vec<TableDefinition<'_, &'static [u8], &'static [u8]>> {
+    match (layout, history) {
+        (StorageLayout::TwoTables, false) => vec![tables.payloads(), tables.data_info()],
+        (StorageLayout::TwoTables, true) => {
+            vec![tables.history_payloads(), tables.history_data_info()]
+        }
+        (StorageLayout::SingleTable, false) => vec![tables.records()],
+        (StorageLayout::SingleTable, true) => vec![tables.history_records()],
+    }
+}
+
+impl<'txn> EntryTables<Table<'txn, &'static [u8], &'static [u8]>> {
+    /// Open (creating them if needed) the tables of the entries, or of the past
+    /// versions if `history` is set.
+    pub fn open(
+        write_txn: &'txn WriteTransaction,
+        tables: &TableNames,
+        layout: StorageLayout,
+        history: bool,
+    ) -> Result<Self> {
+        Ok(match definitions(tables, layout, history).as_slice() {
+            [payloads, data_info] => EntryTables::TwoTables {
+                payloads: write_txn.open_table(*payloads)?,
+                data_info: write_txn.open_table(*data_info)?,
+            },
+            [records] => EntryTables::SingleTable {
+                records: write_txn.open_table(*records)?,
+            },
+            _ => unreachable!("a layout has one or two tables"),
+        })
+    }
+
+    /// Store an entry, returning the length of the payload it replaces, if any.
+    ///
+    /// Tombstones are stored without payload.
+    pub fn insert(
+        &mut self,
+        key: &[u8],
+        info: &[u8],
+        payload: Option<&[u8]>,
+    ) -> Result<Option<usize>> {
+        match self {
+            EntryTables::TwoTables {
+                payloads,
+                data_info,
+            } => {
+                let previous = match payload {
+                    Some(payload) => payloads.insert(key, payload)?,
+                    None => payloads.remove(key)?,
+                };
+                let previous_len = previous.map(|previous| previous.value().len());
+                data_info.insert(key, info)?;
+                Ok(previous_len)
+            }
+            EntryTables::SingleTable { records } => {
+                let record = encode_record(info, payload)?;
+                match records.insert(key, record.as_slice())? {
+                    Some(previous) => Ok(decode_record(previous.value())?.1.map(<[u8]>::len)),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Remove an entry, returning the length of its payload, if any.
+    pub fn remove(&mut self, key: &[u8]) -> Result<Option<usize>> {
+        self.remove_into(key, None)
+    }
+
+    /// Remove an entry, copying its raw records to `quarantine` if given, and
+    /// returning the length of its payload, if any.
+    ///
+    /// Unlike [`remove`](Self::remove), this does not require the entry to be valid.
+    pub fn remove_into(
+        &mut self,
+        key: &[u8],
+        mut quarantine: Option<&mut QuarantineTable>,
+    ) -> Result<Option<usize>> {
+        match self {
+            EntryTables::TwoTables {
+                payloads,
+                data_info,
+            } => {
+                let payloads_name = payloads.name().to_string();
+                let data_info_name = data_info.name().to_string();
+
+                let payload_len = match payloads.remove(key)? {
+                    Some(removed) => {
+                        if let Some(quarantine) = quarantine.as_deref_mut() {
+                            quarantine.insert((payloads_name.as_str(), key), removed.value())?;
+                        }
+                        Some(removed.value().len())
+                    }
+                    None => None,
+                };
+                if let Some(removed) = data_info.remove(key)?
+                    && let Some(quarantine) = quarantine
+                {
+                    quarantine.insert((data_info_name.as_str(), key), removed.value())?;
+                }
+                Ok(payload_len)
+            }
+            EntryTables::SingleTable { records } => {
+                let records_name = records.name().to_string();
+                match records.remove(key)? {
+                    Some(removed) => {
+                        if let Some(quarantine) = quarantine {
+                            quarantine.insert((records_name.as_str(), key), removed.value())?;
+                        }
+                        Ok(decode_record(removed.value())
+                            .ok()
+                            .and_then(|(_, payload)| payload.map(<[u8]>::len)))
+                    }
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+impl EntryTables<ReadOnlyTable<&'static [u8], &'static [u8]>> {
+    /// Open the tables of the entries, or of the past versions if `history` is set.
+    pub fn open_read(
+        read_txn: &ReadTransaction,
+        tables: &TableNames,
+        layout: StorageLayout,
+        history: bool,
+    ) -> Result<Self> {
+        Ok(match definitions(tables, layout, history).as_slice() {
+            [payloads, data_info] => EntryTables::TwoTables {
+                payloads: read_txn.open_table(*payloads)?,
+                data_info: read_txn.open_table(*data_info)?,
+            },
+            [records] => EntryTables::SingleTable {
+                records: read_txn.open_table(*records)?,
+            },
+            _ => unreachable!("a layout has one or two tables"),
+        })
+    }
+
+    /// Iterate over the keys and index values of the entries within `range`.
+    ///
+    /// Unlike [`range`](Self::range), the iterator keeps the read transaction alive
+    /// by itself, so it is not tied to the lifetime of the tables.
+    pub fn owned_range<'a, KR>(
+        &self,
+        range: impl RangeBounds<KR>,
+    ) -> Result<Range<'static, &'static [u8], &'static [u8]>>
+    where
+        KR: Borrow<&'a [u8]>,
+    {
+        let index = match self {
+            EntryTables::TwoTables { data_info, .. } => data_info,
+            EntryTables::SingleTable { records } => records,
+        };
+        Ok(index.range(range)?)
+    }
+
+    /// Get the index value of an entry, detached from the lifetime of the tables.
+    pub fn owned_get(&self, key: &[u8]) -> Result<Option<OwnedGuard>> {
+        let index = match self {
+            EntryTables::TwoTables { data_info, .. } => data_info,
+            EntryTables::SingleTable { records } => records,
+        };
+        Ok(index.get(key)?)
+    }
+
+    /// The guard holding the payload of an entry, given its key and index value, and
+    /// the offset of the payload within the guarded bytes.
+    pub fn payload_guard(
+        &self,
+        key: &[u8],
+        value: OwnedGuard,
+    ) -> Result<Option<(OwnedGuard, usize)>> {
+        match self {
+            EntryTables::TwoTables { payloads, .. } => {
+                Ok(payloads.get(key)?.map(|payload| (payload, 0)))
+            }
+            EntryTables::SingleTable { .. } => {
+                let record = value.value();
+                match decode_record(record)?.1 {
+                    // The payload ends the record
+                    Some(payload) => {
+                        let offset = record.len() - payload.len();
+                        Ok(Some((value, offset)))
+                    }
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+impl<T> EntryTables<T>
+where
+    T: ReadableTable<&'static [u8], &'static [u8]>,
+{
+    /// The table entries are looked up and iterated through.
+    fn index(&self) -> &T {
+        match self {
+            EntryTables::TwoTables { data_info, .. } => data_info,
+            EntryTables::SingleTable { records } => records,
+        }
+    }
+
+    /// Get the index value of an entry.
+    pub fn get(&self, key: &[u8]) -> Result<Option<AccessGuard<'_, &'static [u8]>>> {
+        Ok(self.index().get(key)?)
+    }
+
+    /// Iterate over the keys and index values of all entries.
+    pub fn iter(&self) -> Result<Range<'_, &'static [u8], &'static [u8]>> {
+        Ok(self.index().iter()?)
+    }
+
+    /// Iterate over the keys and index values of the entries within `range`.
+    pub fn range<'a, KR>(
+        &self,
+        range: impl RangeBounds<KR> + 'a,
+    ) -> Result<Range<'_, &'static [u8], &'static [u8]>>
+    where
+        KR: Borrow<&'a [u8]> + 'a,
+    {
+        Ok(self.index().range(range)?)
+    }
+
+    /// Iterate over the keys and index values of the entries following the key
+    /// `after`, or of every entry without one.
+    pub fn range_after(
+        &self,
+        after: Option<&[u8]>,
+    ) -> Result<Range<'_, &'static [u8], &'static [u8]>> {
+        match after {
+            Some(after) => self.range::<&[u8]>((Bound::Excluded(after), Bound::Unbounded)),
+            None => self.range::<&[u8]>(..),
+        }
+    }
+
+    /// The data_info bytes of an entry, given its index value.
+    pub fn info<'v>(&self, value: &'v [u8]) -> Result<&'v [u8]> {
+        match self {
+            EntryTables::TwoTables { .. } => Ok(value),
+            EntryTables::SingleTable { .. } => Ok(decode_record(value)?.0),
+        }
+    }
+
+    /// The payload of an entry, given its key and index value.
+    pub fn payload<'a>(&'a self, key: &[u8], value: &'a [u8]) -> Result<Option<PayloadRef<'a>>> {
+        match self {
+            EntryTables::TwoTables { payloads, .. } => {
+                Ok(payloads.get(key)?.map(PayloadRef::Guard))
+            }
+            EntryTables::SingleTable { .. } => Ok(decode_record(value)?.1.map(PayloadRef::Inline)),
+        }
+    }
+}
+
+/// Delete the tables of the entries, or of the past versions, in `layout`.
+pub(crate) fn delete_tables(
+    write_txn: &WriteTransaction,
+    tables: &TableNames,
+    layout: StorageLayout,
+    history: bool,
+) -> Result<()> {
+    for definition in definitions(tables, layout, history) {
+        write_txn.delete_table(definition)?;
+    }
+    Ok(())
+}
+
+/// Rename the tables of the entries, or of the past versions, in `layout` from
+/// their names in `from` to their names in `to`.
+pub(crate) fn rename_tables(
+    write_txn: &WriteTransaction,
+    from: &TableNames,
+    to: &TableNames,
+    layout: StorageLayout,
+    history: bool,
+) -> Result<()> {
+    let targets = definitions(to, layout, history);
+    for (source, target) in definitions(from, layout, history).into_iter().zip(targets) {
+        write_txn.rename_table(source, target)?;
+    }
+    Ok(())
+}
+
+/// Whether the index table of the entries, or of the past versions, exists.
+pub(crate) fn tables_exist(
+    write_txn: &WriteTransaction,
+    tables: &TableNames,
+    layout: StorageLayout,
+    history: bool,
+) -> Result<bool> {
+    let index = definitions(tables, layout, history)
+        .pop()
+        .expect("a layout has at least one table");
+    Ok(write_txn
+        .list_tables()?
+        .any(|table| table.name() == index.name()))
+}
+
+/// Move the entries, or the past versions, from the tables of layout `from` to
+/// the tables of layout `to`, deleting the former. Returns the number of entries
+/// moved.
+pub(crate) fn convert(
+    write_txn: &WriteTransaction,
+    tables: &TableNames,
+    from: StorageLayout,
+    to: StorageLayout,
+    history: bool,
+) -> Result<u64> {
+    let mut moved = 0;
+    {
+        let source = EntryTables::open(write_txn, tables, from, history)?;
+        let mut target = EntryTables::open(write_txn, tables, to, history)?;
+        for item in source.iter()? {
+            let (key, value) = item?;
+            let info = source.info(value.value())?;
+            let payload = source.payload(key.value(), value.value())?;
+            target.insert(key.value(), info, payload.as_ref().map(PayloadRef::value))?;
+            moved += 1;
+        }
+    }
+    delete_tables(write_txn, tables, from, history)?;
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_roundtrip() {
+        let record = encode_record(b"info", Some(b"payload")).unwrap();
+        assert_eq!(record.len(), RECORD_HEADER_SIZE + 4 + 7);
+        assert_eq!(
+            decode_record(&record).unwrap(),
+            (&b"info"[..], Some(&b"payload"[..]))
+        );
+
+        // Empty payloads are distinguished from tombstones
+        let record = encode_record(b"info", Some(b"")).unwrap();
+        assert_eq!(
+            decode_record(&record).unwrap(),
+            (&b"info"[..], Some(&b""[..]))
+        );
+        let record = encode_record(b"info", None).unwrap();
+        assert_eq!(decode_record(&record).unwrap(), (&b"info"[..], None));
+    }
+
+    #[test]
+    fn test_invalid_records() {
+        let record = encode_record(b"info", None).unwrap();
+        for invalid in [
+            &record[..3],
+            &record[..RECORD_HEADER_SIZE + 2],
+            b"\x02\x00\x00\x00\x00",
+        ] {
+            assert!(decode_record(invalid).is_err());
+        }
+        // Tombstones carry no payload
+        let mut record = record;
+        record.push(0);
+        assert!(decode_record(&record).is_err());
+    }
+}
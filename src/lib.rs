@@ -6,10 +6,10 @@
 //!
 //! # Features
 //!
-//! - Pure Rust implementation with no C dependencies
+//! - Pure Rust implementation (the optional `zstd` feature links the C zstd library)
 //! - ACID compliance with MVCC (Multi-Version Concurrency Control)
 //! - Zero-copy reads for excellent performance
-//! - Support for Zenoh wildcard queries (`*` and `**`)
+//! - Support for Zenoh wildcard queries (`*`, `**` and `$*`)
 //! - Configurable per-storage settings
 //! - Read-only mode support
 //! - Prefix stripping for efficient key storage
@@ -39,18 +39,32 @@
 
 // Module declarations
 pub mod backend;
+mod compression;
 pub mod config;
+mod encryption;
 pub mod error;
+#[cfg(feature = "plugin")]
+mod group_commit;
+mod layout;
+mod read_only;
+mod schema;
 pub mod storage;
 
 #[cfg(feature = "plugin")]
 pub mod plugin;
 
 // Re-export main types for convenience
-pub use backend::RedbBackend;
-pub use config::{RedbBackendConfig, RedbStorageConfig};
+pub use backend::{OnDiskStorage, RedbBackend};
+pub use config::{
+    Compression, EncryptionConfig, EncryptionKeyConfig, GroupCommitConfig, KeySource, QuotaPolicy,
+    RedbBackendConfig, RedbStorageConfig, StorageLayout,
+};
 pub use error::{RedbBackendError, Result};
-pub use storage::{RedbStorage, StoredValue};
+pub use schema::SCHEMA_VERSION;
+pub use storage::{
+    EntryIter, IntegrityIssue, IntegrityIssueKind, NONE_KEY, RedbStorage, RepairMode, StorageUsage,
+    StoredValue, ValueView, VerifyReport, WriteOp, WriteOutcome,
+};
 
 #[cfg(feature = "plugin")]
 pub use plugin::{DEFAULT_ROOT_DIR, RedbBackendPlugin, RedbVolume, SCOPE_ENV_VAR};
@@ -94,7 +108,8 @@ mod tests {
         assert_eq!(retrieved.unwrap().payload, value.payload);
 
         // Delete the value
-        storage.delete("test/key").unwrap();
+        let delete_timestamp = Timestamp::new(NTP64(12346), TimestampId::rand());
+        storage.delete("test/key", delete_timestamp).unwrap();
 
         // Verify it's gone
         let retrieved = storage.get("test/key").unwrap();
@@ -4,25 +4,32 @@
 //! Zenoh's plugin system, implementing the required traits for Volume and Storage.
 
 use crate::backend::RedbBackend;
-use crate::config::{RedbBackendConfig, RedbStorageConfig};
+use crate::config::{
+    Compression, DEFAULT_MAX_VERSIONS, EncryptionConfig, GroupCommitConfig, QuotaPolicy,
+    RedbBackendConfig, RedbStorageConfig, StorageLayout,
+};
+use crate::group_commit::GroupCommitter;
 
-use crate::storage::{RedbStorage, StoredValue};
+use crate::storage::{RedbStorage, StoredValue, WriteOp, WriteOutcome, now_since_epoch};
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::ops::{Bound, RangeBounds};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info, warn};
 use zenoh::{
     Result as ZResult,
     bytes::{Encoding, ZBytes},
     internal::{bail, zenoh_home, zerror},
     key_expr::OwnedKeyExpr,
-    time::Timestamp,
+    query::{Parameters, TimeBound, TimeRange, ZenohParameters},
+    time::{NTP64, Timestamp},
     try_init_log_from_env,
 };
 use zenoh_backend_traits::{
     Capability, History, Persistence, Storage, StorageInsertionResult, StoredData, Volume,
-    config::{StorageConfig, VolumeConfig},
+    config::{GarbageCollectionConfig, StorageConfig, VolumeConfig},
 };
 use zenoh_plugin_trait::{Plugin, plugin_long_version, plugin_version};
 use zenoh_util::ffi::JsonValue;
@@ -33,6 +40,9 @@ pub const SCOPE_ENV_VAR: &str = "ZENOH_BACKEND_REDB_ROOT";
 /// The default root directory (within zenoh's home directory) if ZENOH_BACKEND_REDB_ROOT is not specified.
 pub const DEFAULT_ROOT_DIR: &str = "zenoh_backend_redb";
 
+// Volume configuration properties
+pub const PROP_VOLUME_HISTORY: &str = "history";
+
 // Storage configuration properties
 pub const PROP_STORAGE_DIR: &str = "dir";
 pub const PROP_STORAGE_DB_FILE: &str = "db_file";
@@ -40,9 +50,23 @@ pub const PROP_STORAGE_CREATE_DB: &str = "create_db";
 pub const PROP_STORAGE_READ_ONLY: &str = "read_only";
 pub const PROP_STORAGE_CACHE_SIZE: &str = "cache_size";
 pub const PROP_STORAGE_FSYNC: &str = "fsync";
+pub const PROP_STORAGE_HISTORY: &str = "history";
+pub const PROP_STORAGE_MAX_VERSIONS: &str = "max_versions";
+pub const PROP_STORAGE_TTL: &str = "ttl";
+pub const PROP_STORAGE_TTL_OVERRIDES: &str = "ttl_overrides";
+pub const PROP_STORAGE_MAX_ENTRIES: &str = "max_entries";
+pub const PROP_STORAGE_MAX_BYTES: &str = "max_bytes";
+pub const PROP_STORAGE_QUOTA_POLICY: &str = "quota_policy";
+pub const PROP_STORAGE_COMPRESSION: &str = "compression";
+pub const PROP_STORAGE_COMPRESSION_LEVEL: &str = "compression_level";
+pub const PROP_STORAGE_COMPRESSION_MIN_SIZE: &str = "compression_min_size";
+pub const PROP_STORAGE_ENCRYPTION: &str = "encryption";
+pub const PROP_STORAGE_LAYOUT: &str = "layout";
+pub const PROP_STORAGE_GROUP_COMMIT: &str = "group_commit";
+pub const PROP_STORAGE_TABLE_NAME: &str = "table_name";
 
 // Special key for None (when the prefix being stripped exactly matches the key)
-pub const NONE_KEY: &str = "@@none_key@@";
+pub use crate::storage::NONE_KEY;
 
 /// The redb backend plugin.
 pub struct RedbBackendPlugin {}
@@ -58,10 +82,25 @@ impl Plugin for RedbBackendPlugin {
     const PLUGIN_VERSION: &'static str = plugin_version!();
     const PLUGIN_LONG_VERSION: &'static str = plugin_long_version!();
 
-    fn start(_name: &str, _config: &Self::StartArgs) -> ZResult<Self::Instance> {
+    fn start(_name: &str, config: &Self::StartArgs) -> ZResult<Self::Instance> {
         try_init_log_from_env();
         info!("redb backend {}", Self::PLUGIN_LONG_VERSION);
 
+        // Parse history property: the capability of the volume, which its storages can
+        // keep every version under
+        let volume_cfg = serde_json::Map::from(&config.rest);
+        let history = match volume_cfg.get(PROP_VOLUME_HISTORY) {
+            None => History::Latest,
+            Some(serde_json::Value::String(h)) if h == "latest" => History::Latest,
+            Some(serde_json::Value::String(h)) if h == "all" => History::All,
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb volume configurations must be \"latest\" or \"all\"",
+                    PROP_VOLUME_HISTORY
+                )
+            }
+        };
+
         // Determine root directory
         let root = if let Some(dir) = std::env::var_os(SCOPE_ENV_VAR) {
             PathBuf::from(dir)
@@ -84,6 +123,13 @@ impl Plugin for RedbBackendPlugin {
         let mut properties = HashMap::new();
         properties.insert("root".to_string(), root.to_string_lossy().to_string());
         properties.insert("version".to_string(), Self::PLUGIN_VERSION.to_string());
+        properties.insert(
+            PROP_VOLUME_HISTORY.to_string(),
+            match history {
+                History::Latest => "latest".to_string(),
+                History::All => "all".to_string(),
+            },
+        );
 
         let admin_status: serde_json::Value = properties
             .into_iter()
@@ -93,14 +139,19 @@ impl Plugin for RedbBackendPlugin {
         Ok(Box::new(RedbVolume {
             admin_status,
             backend: Arc::new(backend),
+            history,
         }))
     }
 }
 
 /// Volume implementation for redb backend.
+///
+/// Zenoh reads the history capability per volume, so a storage can only keep
+/// every version on a volume declaring `history: "all"`.
 pub struct RedbVolume {
     admin_status: serde_json::Value,
     backend: Arc<RedbBackend>,
+    history: History,
 }
 
 #[async_trait]
@@ -112,7 +163,7 @@ impl Volume for RedbVolume {
     fn get_capability(&self) -> Capability {
         Capability {
             persistence: Persistence::Durable,
-            history: History::Latest,
+            history: self.history.clone(),
         }
     }
 
@@ -182,6 +233,258 @@ impl Volume for RedbVolume {
             }
         };
 
+        // Parse history property, defaulting to the capability of the volume
+        let keep_history = match volume_cfg.get(PROP_STORAGE_HISTORY) {
+            None => self.history == History::All,
+            Some(serde_json::Value::String(h)) if h == "latest" => false,
+            Some(serde_json::Value::String(h)) if h == "all" => {
+                if self.history != History::All {
+                    bail!(
+                        "Storage `{}: \"all\"` needs a redb volume declaring `{}: \"all\"`, \
+                         since Zenoh reads the history capability per volume",
+                        PROP_STORAGE_HISTORY,
+                        PROP_VOLUME_HISTORY
+                    )
+                }
+                true
+            }
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be \"latest\" or \"all\"",
+                    PROP_STORAGE_HISTORY
+                )
+            }
+        };
+
+        // Parse max_versions property (only meaningful when the storage keeps history),
+        // `null` keeping every version
+        let max_versions = match volume_cfg.get(PROP_STORAGE_MAX_VERSIONS) {
+            None => Some(DEFAULT_MAX_VERSIONS),
+            Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::Number(n)) => match n.as_u64() {
+                Some(max) if max > 0 => Some(max as usize),
+                _ => bail!(
+                    "Optional property `{}` of redb storage configurations must be a positive number",
+                    PROP_STORAGE_MAX_VERSIONS
+                ),
+            },
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be a number or null",
+                    PROP_STORAGE_MAX_VERSIONS
+                )
+            }
+        };
+        if keep_history && max_versions.is_none() {
+            warn!(
+                "Storage '{}' keeps every version of each key without `{}`: its history grows without bound",
+                config.name, PROP_STORAGE_MAX_VERSIONS
+            );
+        }
+
+        // Parse ttl property (seconds)
+        let ttl = match volume_cfg.get(PROP_STORAGE_TTL) {
+            None => None,
+            Some(serde_json::Value::Number(n)) => match n.as_u64() {
+                Some(ttl) => Some(ttl),
+                None => bail!(
+                    "Optional property `{}` of redb storage configurations must be a positive number",
+                    PROP_STORAGE_TTL
+                ),
+            },
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be a number",
+                    PROP_STORAGE_TTL
+                )
+            }
+        };
+
+        // Parse ttl_overrides property (key prefix -> seconds)
+        let mut ttl_overrides = Vec::new();
+        match volume_cfg.get(PROP_STORAGE_TTL_OVERRIDES) {
+            None => {}
+            Some(serde_json::Value::Object(overrides)) => {
+                for (prefix, ttl) in overrides {
+                    match ttl.as_u64() {
+                        Some(ttl) => ttl_overrides.push((prefix.clone(), ttl)),
+                        None => bail!(
+                            "TTL of prefix `{}` in `{}` must be a positive number",
+                            prefix,
+                            PROP_STORAGE_TTL_OVERRIDES
+                        ),
+                    }
+                }
+            }
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be an object",
+                    PROP_STORAGE_TTL_OVERRIDES
+                )
+            }
+        }
+
+        // Parse max_entries property
+        let max_entries = match volume_cfg.get(PROP_STORAGE_MAX_ENTRIES) {
+            None => None,
+            Some(serde_json::Value::Number(n)) => match n.as_u64() {
+                Some(max) => Some(max),
+                None => bail!(
+                    "Optional property `{}` of redb storage configurations must be a positive number",
+                    PROP_STORAGE_MAX_ENTRIES
+                ),
+            },
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be a number",
+                    PROP_STORAGE_MAX_ENTRIES
+                )
+            }
+        };
+
+        // Parse max_bytes property
+        let max_bytes = match volume_cfg.get(PROP_STORAGE_MAX_BYTES) {
+            None => None,
+            Some(serde_json::Value::Number(n)) => match n.as_u64() {
+                Some(max) => Some(max),
+                None => bail!(
+                    "Optional property `{}` of redb storage configurations must be a positive number",
+                    PROP_STORAGE_MAX_BYTES
+                ),
+            },
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be a number",
+                    PROP_STORAGE_MAX_BYTES
+                )
+            }
+        };
+
+        // Parse quota_policy property
+        let quota_policy = match volume_cfg.get(PROP_STORAGE_QUOTA_POLICY) {
+            None => QuotaPolicy::default(),
+            Some(serde_json::Value::String(p)) if p == "reject" => QuotaPolicy::Reject,
+            Some(serde_json::Value::String(p)) if p == "evict_oldest" => QuotaPolicy::EvictOldest,
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be \"reject\" or \"evict_oldest\"",
+                    PROP_STORAGE_QUOTA_POLICY
+                )
+            }
+        };
+
+        // Parse layout property
+        let layout = match volume_cfg.get(PROP_STORAGE_LAYOUT) {
+            None => StorageLayout::default(),
+            Some(serde_json::Value::String(l)) if l == "two_tables" => StorageLayout::TwoTables,
+            Some(serde_json::Value::String(l)) if l == "single_table" => StorageLayout::SingleTable,
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be \"two_tables\" or \"single_table\"",
+                    PROP_STORAGE_LAYOUT
+                )
+            }
+        };
+
+        // Parse table_name property
+        let table_name = match volume_cfg.get(PROP_STORAGE_TABLE_NAME) {
+            None => None,
+            Some(serde_json::Value::String(name)) => Some(name.clone()),
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be a string",
+                    PROP_STORAGE_TABLE_NAME
+                )
+            }
+        };
+
+        // Parse compression_level property
+        let compression_level = match volume_cfg.get(PROP_STORAGE_COMPRESSION_LEVEL) {
+            None => None,
+            Some(serde_json::Value::Number(n)) => match n.as_i64() {
+                Some(level) => Some(level as i32),
+                None => bail!(
+                    "Optional property `{}` of redb storage configurations must be an integer",
+                    PROP_STORAGE_COMPRESSION_LEVEL
+                ),
+            },
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be a number",
+                    PROP_STORAGE_COMPRESSION_LEVEL
+                )
+            }
+        };
+
+        // Parse compression property
+        let compression = match volume_cfg.get(PROP_STORAGE_COMPRESSION) {
+            None => Compression::None,
+            Some(serde_json::Value::String(c)) if c == "none" => Compression::None,
+            Some(serde_json::Value::String(c)) if c == "lz4" => Compression::Lz4,
+            Some(serde_json::Value::String(c)) if c == "zstd" => Compression::Zstd {
+                level: compression_level.unwrap_or(3),
+            },
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be \"none\", \"lz4\" or \"zstd\"",
+                    PROP_STORAGE_COMPRESSION
+                )
+            }
+        };
+
+        // Parse compression_min_size property
+        let compression_min_size = match volume_cfg.get(PROP_STORAGE_COMPRESSION_MIN_SIZE) {
+            None => None,
+            Some(serde_json::Value::Number(n)) => match n.as_u64() {
+                Some(size) => Some(size as usize),
+                None => bail!(
+                    "Optional property `{}` of redb storage configurations must be a positive number",
+                    PROP_STORAGE_COMPRESSION_MIN_SIZE
+                ),
+            },
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be a number",
+                    PROP_STORAGE_COMPRESSION_MIN_SIZE
+                )
+            }
+        };
+
+        // Parse encryption property
+        let encryption = match volume_cfg.get(PROP_STORAGE_ENCRYPTION) {
+            None => None,
+            Some(value @ serde_json::Value::Object(_)) => {
+                match serde_json::from_value::<EncryptionConfig>(value.clone()) {
+                    Ok(encryption) => Some(encryption),
+                    Err(e) => bail!("Invalid property `{}`: {}", PROP_STORAGE_ENCRYPTION, e),
+                }
+            }
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be an object",
+                    PROP_STORAGE_ENCRYPTION
+                )
+            }
+        };
+
+        // Parse group_commit property: `true` for the defaults, or an object
+        let group_commit = match volume_cfg.get(PROP_STORAGE_GROUP_COMMIT) {
+            None | Some(serde_json::Value::Bool(false)) => None,
+            Some(serde_json::Value::Bool(true)) => Some(GroupCommitConfig::default()),
+            Some(value @ serde_json::Value::Object(_)) => {
+                match serde_json::from_value::<GroupCommitConfig>(value.clone()) {
+                    Ok(group_commit) => Some(group_commit),
+                    Err(e) => bail!("Invalid property `{}`: {}", PROP_STORAGE_GROUP_COMMIT, e),
+                }
+            }
+            _ => {
+                bail!(
+                    "Optional property `{}` of redb storage configurations must be a boolean or an object",
+                    PROP_STORAGE_GROUP_COMMIT
+                )
+            }
+        };
+
         // Determine database path
         let db_path = if let Some(serde_json::Value::String(dir)) = volume_cfg.get(PROP_STORAGE_DIR)
         {
@@ -211,25 +514,92 @@ impl Volume for RedbVolume {
             .with_db_path(db_path.clone())
             .with_create_db(create_db)
             .with_read_only(read_only)
-            .with_fsync(fsync);
+            .with_fsync(fsync)
+            .with_keep_history(keep_history)
+            .with_quota_policy(quota_policy)
+            .with_compression(compression)
+            .with_layout(layout);
 
         if let Some(size) = cache_size {
             storage_config = storage_config.with_cache_size(size);
         }
 
+        storage_config = match max_versions {
+            Some(max) => storage_config.with_max_versions(max),
+            None => storage_config.without_max_versions(),
+        };
+
+        if let Some(ttl) = ttl {
+            storage_config = storage_config.with_ttl_secs(ttl);
+        }
+
+        if let Some(size) = compression_min_size {
+            storage_config = storage_config.with_compression_min_size(size);
+        }
+
+        if let Some(encryption) = encryption {
+            storage_config = storage_config.with_encryption(encryption);
+        }
+
+        if let Some(group_commit) = group_commit {
+            storage_config = storage_config.with_group_commit(group_commit);
+        }
+
+        if let Some(table_name) = table_name {
+            storage_config = storage_config.with_table_name(table_name);
+        }
+
+        if let Some(max) = max_entries {
+            storage_config = storage_config.with_max_entries(max);
+        }
+
+        if let Some(max) = max_bytes {
+            storage_config = storage_config.with_max_bytes(max);
+        }
+
+        for (prefix, ttl) in ttl_overrides {
+            storage_config = storage_config.with_ttl_override(prefix, ttl);
+        }
+
         // Get storage name from config
         let storage_name = config.name.clone();
 
-        // Create the storage directly (not using backend.create_storage to avoid double management)
-        let redb_storage = RedbStorage::new(&db_path, storage_config.clone(), storage_name.clone())
+        // Create the storage through the backend, which shares open database files
+        let redb_storage = self
+            .backend
+            .create_storage(storage_name.clone(), Some(storage_config.clone()))
             .map_err(|e| zerror!("Failed to create redb storage: {}", e))?;
 
         info!("Created redb storage '{}' at {:?}", storage_name, db_path);
 
+        let registration = Registration {
+            backend: self.backend.clone(),
+            storage: redb_storage.clone(),
+        };
+        let storage = Arc::new(tokio::sync::Mutex::new(redb_storage));
+
+        // Periodically expire entries and drop tombstones older than the configured lifespan
+        if !read_only {
+            spawn_maintenance(
+                Arc::downgrade(&storage),
+                config.garbage_collection_config.clone(),
+            );
+        }
+
+        // Writes go through a single writer committing them in groups, if enabled
+        let group_commit = match storage_config.group_commit {
+            Some(group_commit) if !read_only => {
+                Some(GroupCommitter::spawn(storage.clone(), group_commit))
+            }
+            _ => None,
+        };
+
         Ok(Box::new(RedbStoragePlugin {
             config,
-            storage: Arc::new(tokio::sync::Mutex::new(redb_storage)),
+            storage,
             storage_config,
+            group_commit,
+            _registration: Some(registration),
         }))
     }
 }
@@ -237,14 +607,52 @@ impl Volume for RedbVolume {
 /// Storage implementation for redb backend.
 struct RedbStoragePlugin {
     config: StorageConfig,
-    storage: Arc<tokio::sync::Mutex<RedbStorage>>,
+    storage: Arc<tokio::sync::Mutex<Arc<RedbStorage>>>,
     storage_config: RedbStorageConfig,
+    group_commit: Option<GroupCommitter>,
+    /// Registration of the storage in the backend of the volume
+    _registration: Option<Registration>,
+}
+
+/// Registration of a storage in a backend, removed when dropped.
+struct Registration {
+    backend: Arc<RedbBackend>,
+    storage: Arc<RedbStorage>,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        // The storage may have been removed, or replaced, in the meantime
+        let name = self.storage.name();
+        let registered = self.backend.get_storage(name).ok();
+        if registered.is_some_and(|registered| Arc::ptr_eq(&registered, &self.storage))
+            && let Err(e) = self.backend.remove_storage(name)
+        {
+            warn!("Failed to remove storage '{}': {}", name, e);
+        }
+    }
 }
 
 #[async_trait]
 impl Storage for RedbStoragePlugin {
     fn get_admin_status(&self) -> JsonValue {
-        self.config.to_json_value().into()
+        let mut status = self.config.to_json_value();
+
+        // Current usage is reported unless the storage is busy
+        if let Ok(storage) = self.storage.try_lock()
+            && let Ok(usage) = storage.usage()
+            && let Some(status) = status.as_object_mut()
+        {
+            status.insert(
+                "usage".to_string(),
+                serde_json::json!({
+                    "entries": usage.entries,
+                    "bytes": usage.bytes,
+                }),
+            );
+        }
+
+        status.into()
     }
 
     async fn put(
@@ -254,8 +662,6 @@ impl Storage for RedbStoragePlugin {
         encoding: Encoding,
         timestamp: Timestamp,
     ) -> ZResult<StorageInsertionResult> {
-        let storage = self.storage.lock().await;
-
         if self.storage_config.read_only {
             warn!("Received PUT for read-only DB on {:?} - ignored", key);
             return Err("Received update for read-only DB".into());
@@ -274,21 +680,27 @@ impl Storage for RedbStoragePlugin {
         // Create stored value with native Zenoh timestamp (preserves both time and ID)
         let value = StoredValue::new(payload_bytes, timestamp, encoding);
 
-        // Store in database
-        storage
-            .put(&key_str, value)
-            .map_err(|e| zerror!("Failed to put key '{}': {}", key_str, e))?;
+        // Store in database, skipping the update if a newer value is already stored
+        let outcome = match &self.group_commit {
+            Some(group_commit) => {
+                let op = WriteOp::Put {
+                    key: key_str.clone(),
+                    value,
+                };
+                group_commit.write(op).await
+            }
+            None => self.storage.lock().await.put(&key_str, value),
+        }
+        .map_err(|e| zerror!("Failed to put key '{}': {}", key_str, e))?;
 
-        Ok(StorageInsertionResult::Inserted)
+        Ok(insertion_result(outcome))
     }
 
     async fn delete(
         &mut self,
         key: Option<OwnedKeyExpr>,
-        _timestamp: Timestamp,
+        timestamp: Timestamp,
     ) -> ZResult<StorageInsertionResult> {
-        let storage = self.storage.lock().await;
-
         if self.storage_config.read_only {
             warn!("Received DELETE for read-only DB on {:?} - ignored", key);
             return Err("Received update for read-only DB".into());
@@ -299,20 +711,28 @@ impl Storage for RedbStoragePlugin {
             None => NONE_KEY.to_string(),
         };
 
-        debug!("Deleting key: {}", key_str);
+        debug!("Deleting key: {} with timestamp: {}", key_str, timestamp);
 
-        storage
-            .delete(&key_str)
-            .map_err(|e| zerror!("Failed to delete key '{}': {}", key_str, e))?;
+        // Returns Deleted even if the key wasn't found, Outdated if a newer value is stored
+        let outcome = match &self.group_commit {
+            Some(group_commit) => {
+                let op = WriteOp::Delete {
+                    key: key_str.clone(),
+                    timestamp,
+                };
+                group_commit.write(op).await
+            }
+            None => self.storage.lock().await.delete(&key_str, timestamp),
+        }
+        .map_err(|e| zerror!("Failed to delete key '{}': {}", key_str, e))?;
 
-        // Always return Deleted, even if key wasn't found
-        Ok(StorageInsertionResult::Deleted)
+        Ok(insertion_result(outcome))
     }
 
     async fn get(
         &mut self,
         key: Option<OwnedKeyExpr>,
-        _parameters: &str,
+        parameters: &str,
     ) -> ZResult<Vec<StoredData>> {
         let storage = self.storage.lock().await;
 
@@ -321,26 +741,46 @@ impl Storage for RedbStoragePlugin {
             None => NONE_KEY.to_string(),
         };
 
-        debug!("Getting key: {}", key_str);
+        // Restrict the reply to the `_time` range of the selector, if any
+        let time_range = match Parameters::from(parameters).time_range() {
+            None => (Bound::Unbounded, Bound::Unbounded),
+            Some(Ok(range)) => time_range_bounds(&range.resolve()),
+            Some(Err(e)) => bail!("Invalid `_time` parameter in '{}': {}", parameters, e),
+        };
 
-        match storage
-            .get(&key_str)
-            .map_err(|e| zerror!("Failed to get key '{}': {}", key_str, e))?
-        {
-            Some(stored_value) => {
-                // Convert back to Zenoh types
-                let payload = ZBytes::from(stored_value.payload);
-                let encoding = stored_value.encoding.clone();
-                let timestamp = stored_value.timestamp;
-
-                Ok(vec![StoredData {
-                    payload,
-                    encoding,
-                    timestamp,
-                }])
-            }
-            None => Ok(vec![]),
+        debug!("Getting key: {} (time range: {:?})", key_str, time_range);
+
+        // Without history, the latest value is returned if its timestamp is within
+        // the range, sharing its payload instead of copying it
+        if !storage.config().keep_history {
+            let view = storage
+                .get_view(&key_str)
+                .map_err(|e| zerror!("Failed to get key '{}': {}", key_str, e))?;
+            return Ok(view
+                .filter(|view| time_range.contains(view.timestamp().get_time()))
+                .map(|view| StoredData {
+                    encoding: view.encoding().clone(),
+                    timestamp: *view.timestamp(),
+                    payload: view.into_zbytes(),
+                })
+                .into_iter()
+                .collect());
         }
+
+        // With History::All, every recorded version within the range is returned
+        let versions = storage
+            .get_history(&key_str, time_range)
+            .map_err(|e| zerror!("Failed to get key '{}': {}", key_str, e))?;
+
+        // Convert back to Zenoh types
+        Ok(versions
+            .into_iter()
+            .map(|version| StoredData {
+                payload: ZBytes::from(version.payload),
+                encoding: version.encoding,
+                timestamp: version.timestamp,
+            })
+            .collect())
     }
 
     async fn get_all_entries(&self) -> ZResult<Vec<(Option<OwnedKeyExpr>, Timestamp)>> {
@@ -348,12 +788,13 @@ impl Storage for RedbStoragePlugin {
 
         debug!("Getting all entries");
 
+        // Only keys and timestamps are needed, payloads are not read
         let entries = storage
-            .get_all()
+            .list_entries_metadata()
             .map_err(|e| zerror!("Failed to get all entries: {}", e))?;
 
-        let mut result = Vec::new();
-        for (key_str, stored_value) in entries {
+        let mut result = Vec::with_capacity(entries.len());
+        for (key_str, timestamp) in entries {
             // Convert key string back to OwnedKeyExpr
             let key_expr = if key_str == NONE_KEY {
                 None
@@ -367,9 +808,7 @@ impl Storage for RedbStoragePlugin {
                 }
             };
 
-            // Use the stored timestamp directly (preserves both time and ID)
-            let timestamp = stored_value.timestamp;
-
+            // The stored timestamp preserves both time and ID
             result.push((key_expr, timestamp));
         }
 
@@ -378,10 +817,122 @@ impl Storage for RedbStoragePlugin {
     }
 }
 
+/// Spawn a task that periodically expires entries past their TTL and removes
+/// expired tombstones from a storage.
+///
+/// Each sweep runs in batches and releases the storage lock between them, so
+/// that queries and writes are not held up by a large table. The task stops on
+/// its next tick once the storage has been dropped.
+fn spawn_maintenance(
+    storage: Weak<tokio::sync::Mutex<Arc<RedbStorage>>>,
+    gc_config: GarbageCollectionConfig,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(gc_config.period);
+        // The first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let Some(storage) = storage.upgrade() else {
+                debug!("Storage dropped, stopping maintenance");
+                break;
+            };
+            let (name, has_ttl) = {
+                let storage = storage.lock().await;
+                (storage.name().to_string(), storage.config().has_ttl())
+            };
+
+            if has_ttl {
+                let swept = match now_since_epoch() {
+                    Ok(now) => {
+                        sweep_in_batches(&storage, |s, after| s.expire_batch(after, now)).await
+                    }
+                    Err(e) => Err(e),
+                };
+                match swept {
+                    Ok(expired) => debug!(
+                        "Expiry sweep expired {} entries in storage '{}'",
+                        expired, name
+                    ),
+                    Err(e) => warn!("Expiry sweep failed for storage '{}': {}", name, e),
+                }
+            }
+
+            let collected = match now_since_epoch() {
+                Ok(now) => {
+                    let cutoff = NTP64::from(now.saturating_sub(gc_config.lifespan));
+                    sweep_in_batches(&storage, |s, after| s.garbage_collect_batch(after, cutoff))
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match collected {
+                Ok(removed) => debug!(
+                    "Garbage collection removed {} tombstone(s) from storage '{}'",
+                    removed, name
+                ),
+                Err(e) => warn!("Garbage collection failed for storage '{}': {}", name, e),
+            }
+        }
+    });
+}
+
+/// Run a batched sweep over a storage until it reports the table is swept,
+/// taking the storage lock for one batch at a time.
+///
+/// Returns the total count reported by the batches.
+async fn sweep_in_batches<F>(
+    storage: &tokio::sync::Mutex<Arc<RedbStorage>>,
+    mut batch: F,
+) -> crate::error::Result<usize>
+where
+    F: FnMut(&RedbStorage, Option<&[u8]>) -> crate::error::Result<(usize, Option<Vec<u8>>)>,
+{
+    let mut cursor: Option<Vec<u8>> = None;
+    let mut total = 0;
+    loop {
+        let (count, next) = {
+            let storage = storage.lock().await;
+            batch(&storage, cursor.as_deref())?
+        };
+        total += count;
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(total),
+        }
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Convert a resolved Zenoh time range into bounds on timestamp times.
+///
+/// Instants before the UNIX epoch are clamped to it.
+fn time_range_bounds(range: &TimeRange<SystemTime>) -> (Bound<NTP64>, Bound<NTP64>) {
+    let to_ntp64 =
+        |instant: &SystemTime| NTP64::from(instant.duration_since(UNIX_EPOCH).unwrap_or_default());
+    let to_bound = |bound: &TimeBound<SystemTime>| match bound {
+        TimeBound::Inclusive(instant) => Bound::Included(to_ntp64(instant)),
+        TimeBound::Exclusive(instant) => Bound::Excluded(to_ntp64(instant)),
+        TimeBound::Unbounded => Bound::Unbounded,
+    };
+    (to_bound(&range.start), to_bound(&range.end))
+}
+
+/// Map the outcome of a storage write to the result expected by the storage manager.
+fn insertion_result(outcome: WriteOutcome) -> StorageInsertionResult {
+    match outcome {
+        WriteOutcome::Inserted => StorageInsertionResult::Inserted,
+        WriteOutcome::Replaced => StorageInsertionResult::Replaced,
+        WriteOutcome::Deleted => StorageInsertionResult::Deleted,
+        WriteOutcome::Outdated => StorageInsertionResult::Outdated,
+    }
+}
+
 impl Drop for RedbStoragePlugin {
     fn drop(&mut self) {
         debug!("Dropping redb storage plugin");
-        // Storage cleanup is handled automatically by RedbStorage's Drop implementation
     }
 }
 
@@ -421,6 +972,21 @@ mod tests {
         assert_eq!(PROP_STORAGE_READ_ONLY, "read_only");
         assert_eq!(PROP_STORAGE_CACHE_SIZE, "cache_size");
         assert_eq!(PROP_STORAGE_FSYNC, "fsync");
+        assert_eq!(PROP_STORAGE_HISTORY, "history");
+        assert_eq!(PROP_STORAGE_MAX_VERSIONS, "max_versions");
+        assert_eq!(PROP_VOLUME_HISTORY, "history");
+        assert_eq!(PROP_STORAGE_TTL, "ttl");
+        assert_eq!(PROP_STORAGE_TTL_OVERRIDES, "ttl_overrides");
+        assert_eq!(PROP_STORAGE_MAX_ENTRIES, "max_entries");
+        assert_eq!(PROP_STORAGE_MAX_BYTES, "max_bytes");
+        assert_eq!(PROP_STORAGE_QUOTA_POLICY, "quota_policy");
+        assert_eq!(PROP_STORAGE_COMPRESSION, "compression");
+        assert_eq!(PROP_STORAGE_COMPRESSION_LEVEL, "compression_level");
+        assert_eq!(PROP_STORAGE_COMPRESSION_MIN_SIZE, "compression_min_size");
+        assert_eq!(PROP_STORAGE_ENCRYPTION, "encryption");
+        assert_eq!(PROP_STORAGE_LAYOUT, "layout");
+        assert_eq!(PROP_STORAGE_GROUP_COMMIT, "group_commit");
+        assert_eq!(PROP_STORAGE_TABLE_NAME, "table_name");
     }
 
     #[test]
@@ -438,6 +1004,7 @@ mod tests {
         let volume = RedbVolume {
             admin_status,
             backend: Arc::new(backend),
+            history: History::Latest,
         };
 
         // Verify capability
@@ -470,8 +1037,10 @@ mod tests {
                 garbage_collection_config: Default::default(),
                 replication: None,
             },
-            storage: Arc::new(tokio::sync::Mutex::new(redb_storage)),
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
             storage_config,
+            group_commit: None,
+            _registration: None,
         };
 
         // Drop should work without panic
@@ -501,15 +1070,19 @@ mod tests {
                 garbage_collection_config: Default::default(),
                 replication: None,
             },
-            storage: Arc::new(tokio::sync::Mutex::new(redb_storage)),
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
             storage_config,
+            group_commit: None,
+            _registration: None,
         };
 
-        let admin_status = storage_plugin.get_admin_status();
+        let admin_status = storage_plugin.get_admin_status().into_serde_value();
 
-        // Just verify we can get admin status without panicking
-        // The actual structure is implementation detail
-        let _ = admin_status;
+        // Current usage is reported alongside the storage configuration
+        assert_eq!(
+            admin_status["usage"],
+            serde_json::json!({ "entries": 0, "bytes": 0 })
+        );
     }
 
     #[tokio::test]
@@ -535,8 +1108,10 @@ mod tests {
                 garbage_collection_config: Default::default(),
                 replication: None,
             },
-            storage: Arc::new(tokio::sync::Mutex::new(redb_storage)),
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
             storage_config,
+            group_commit: None,
+            _registration: None,
         };
 
         // Put data
@@ -582,8 +1157,10 @@ mod tests {
                 garbage_collection_config: Default::default(),
                 replication: None,
             },
-            storage: Arc::new(tokio::sync::Mutex::new(redb_storage)),
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
             storage_config,
+            group_commit: None,
+            _registration: None,
         };
 
         // Put with None key
@@ -628,8 +1205,10 @@ mod tests {
                 garbage_collection_config: Default::default(),
                 replication: None,
             },
-            storage: Arc::new(tokio::sync::Mutex::new(redb_storage)),
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
             storage_config,
+            group_commit: None,
+            _registration: None,
         };
 
         // Put data
@@ -700,8 +1279,10 @@ mod tests {
                 garbage_collection_config: Default::default(),
                 replication: None,
             },
-            storage: Arc::new(tokio::sync::Mutex::new(ro_storage)),
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(ro_storage))),
             storage_config: ro_config,
+            group_commit: None,
+            _registration: None,
         };
 
         // Try to put - should fail
@@ -758,8 +1339,10 @@ mod tests {
                 garbage_collection_config: Default::default(),
                 replication: None,
             },
-            storage: Arc::new(tokio::sync::Mutex::new(ro_storage)),
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(ro_storage))),
             storage_config: ro_config,
+            group_commit: None,
+            _registration: None,
         };
 
         // Try to delete - should fail
@@ -790,8 +1373,10 @@ mod tests {
                 garbage_collection_config: Default::default(),
                 replication: None,
             },
-            storage: Arc::new(tokio::sync::Mutex::new(redb_storage)),
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
             storage_config,
+            group_commit: None,
+            _registration: None,
         };
 
         // Put multiple entries
@@ -814,4 +1399,572 @@ mod tests {
         let entries = result.unwrap();
         assert_eq!(entries.len(), 3);
     }
+
+    #[tokio::test]
+    async fn test_storage_put_outdated() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.redb");
+
+        let storage_config = RedbStorageConfig::new()
+            .with_db_path(db_path.clone())
+            .with_create_db(true);
+
+        let redb_storage =
+            RedbStorage::new(&db_path, storage_config.clone(), "test".to_string()).unwrap();
+
+        let mut storage_plugin = RedbStoragePlugin {
+            config: StorageConfig {
+                name: "test".to_string(),
+                key_expr: "test/**".parse().unwrap(),
+                strip_prefix: None,
+                volume_cfg: serde_json::Value::Object(Default::default()).into(),
+                volume_id: "test_volume".to_string(),
+                complete: false,
+                garbage_collection_config: Default::default(),
+                replication: None,
+            },
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
+            storage_config,
+            group_commit: None,
+            _registration: None,
+        };
+
+        let key = OwnedKeyExpr::new("test/key1").unwrap();
+        let encoding = Encoding::ZENOH_STRING;
+        let id = zenoh::time::TimestampId::rand();
+        let newer = Timestamp::new(NTP64(200), id);
+        let older = Timestamp::new(NTP64(100), id);
+
+        let result = storage_plugin
+            .put(
+                Some(key.clone()),
+                ZBytes::from("newer"),
+                encoding.clone(),
+                newer,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(result, StorageInsertionResult::Inserted));
+
+        // An older sample arriving late must not overwrite the newer one
+        let result = storage_plugin
+            .put(Some(key.clone()), ZBytes::from("older"), encoding, older)
+            .await
+            .unwrap();
+        assert!(matches!(result, StorageInsertionResult::Outdated));
+
+        // An older delete must not remove the newer value either
+        let result = storage_plugin
+            .delete(Some(key.clone()), older)
+            .await
+            .unwrap();
+        assert!(matches!(result, StorageInsertionResult::Outdated));
+
+        let data = storage_plugin.get(Some(key), "").await.unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].payload.to_bytes(), ZBytes::from("newer").to_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_garbage_collection_task() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.redb");
+
+        let storage_config = RedbStorageConfig::new()
+            .with_db_path(db_path.clone())
+            .with_create_db(true);
+
+        let redb_storage = RedbStorage::new(&db_path, storage_config, "test".to_string()).unwrap();
+
+        // A tombstone far older than the lifespan
+        let id = zenoh::time::TimestampId::rand();
+        redb_storage
+            .delete("test/key1", Timestamp::new(NTP64(100), id))
+            .unwrap();
+
+        let storage = Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage)));
+        spawn_maintenance(
+            Arc::downgrade(&storage),
+            GarbageCollectionConfig {
+                period: std::time::Duration::from_millis(10),
+                lifespan: std::time::Duration::from_secs(60),
+            },
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // Once collected, an older put is accepted again
+        let value = StoredValue::new(
+            b"data".to_vec(),
+            Timestamp::new(NTP64(50), id),
+            Encoding::ZENOH_BYTES,
+        );
+        let outcome = storage.lock().await.put("test/key1", value).unwrap();
+        assert_eq!(outcome, WriteOutcome::Inserted);
+    }
+
+    #[test]
+    fn test_volume_rejects_invalid_history() {
+        let mut rest = serde_json::Map::new();
+        rest.insert(
+            PROP_VOLUME_HISTORY.to_string(),
+            serde_json::Value::String("some".to_string()),
+        );
+        let config = VolumeConfig {
+            name: "redb".to_string(),
+            backend: None,
+            paths: None,
+            required: false,
+            rest: rest.into(),
+        };
+
+        assert!(RedbBackendPlugin::start("redb", &config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_history_all_volume_keeps_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend =
+            RedbBackend::new(RedbBackendConfig::new().with_base_dir(temp_dir.path().to_path_buf()))
+                .unwrap();
+
+        let volume = RedbVolume {
+            admin_status: serde_json::Value::Null,
+            backend: Arc::new(backend),
+            history: History::All,
+        };
+        assert_eq!(volume.get_capability().history, History::All);
+
+        let volume_cfg = serde_json::json!({
+            PROP_STORAGE_DIR: "audit",
+            PROP_STORAGE_MAX_VERSIONS: 2,
+        });
+        let mut storage = volume
+            .create_storage(StorageConfig {
+                name: "audit".to_string(),
+                key_expr: "test/**".parse().unwrap(),
+                strip_prefix: None,
+                volume_cfg: volume_cfg.into(),
+                volume_id: "redb".to_string(),
+                complete: false,
+                garbage_collection_config: Default::default(),
+                replication: None,
+            })
+            .await
+            .unwrap();
+
+        let key = OwnedKeyExpr::new("test/key1").unwrap();
+        let id = zenoh::time::TimestampId::rand();
+        for (time, value) in [(100, "v1"), (200, "v2"), (300, "v3")] {
+            storage
+                .put(
+                    Some(key.clone()),
+                    ZBytes::from(value),
+                    Encoding::ZENOH_STRING,
+                    Timestamp::new(NTP64(time), id),
+                )
+                .await
+                .unwrap();
+        }
+
+        // Only the two most recent versions are retained
+        let data = storage.get(Some(key), "").await.unwrap();
+        let payloads: Vec<_> = data
+            .iter()
+            .map(|d| d.payload.try_to_string().unwrap().into_owned())
+            .collect();
+        assert_eq!(payloads, vec!["v2", "v3"]);
+    }
+
+    #[tokio::test]
+    async fn test_storage_history_property() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(
+            RedbBackend::new(RedbBackendConfig::new().with_base_dir(temp_dir.path().to_path_buf()))
+                .unwrap(),
+        );
+        let storage_config = |name: &str, volume_cfg: serde_json::Value| StorageConfig {
+            name: name.to_string(),
+            key_expr: "test/**".parse().unwrap(),
+            strip_prefix: None,
+            volume_cfg: volume_cfg.into(),
+            volume_id: "redb".to_string(),
+            complete: false,
+            garbage_collection_config: Default::default(),
+            replication: None,
+        };
+
+        // A volume without history cannot host a storage keeping every version
+        let latest_volume = RedbVolume {
+            admin_status: serde_json::Value::Null,
+            backend: backend.clone(),
+            history: History::Latest,
+        };
+        let volume_cfg =
+            serde_json::json!({ PROP_STORAGE_DIR: "audit", PROP_STORAGE_HISTORY: "all" });
+        assert!(
+            latest_volume
+                .create_storage(storage_config("audit", volume_cfg))
+                .await
+                .is_err()
+        );
+
+        // Storages of a history volume keep every version unless they opt out
+        let all_volume = RedbVolume {
+            admin_status: serde_json::Value::Null,
+            backend: backend.clone(),
+            history: History::All,
+        };
+        let _audit = all_volume
+            .create_storage(storage_config(
+                "audit",
+                serde_json::json!({ PROP_STORAGE_DIR: "audit" }),
+            ))
+            .await
+            .unwrap();
+        let audit = backend.get_storage("audit").unwrap();
+        assert!(audit.config().keep_history);
+        assert_eq!(audit.config().max_versions, Some(DEFAULT_MAX_VERSIONS));
+
+        let volume_cfg =
+            serde_json::json!({ PROP_STORAGE_DIR: "state", PROP_STORAGE_HISTORY: "latest" });
+        let _state = all_volume
+            .create_storage(storage_config("state", volume_cfg))
+            .await
+            .unwrap();
+        assert!(!backend.get_storage("state").unwrap().config().keep_history);
+
+        let volume_cfg =
+            serde_json::json!({ PROP_STORAGE_DIR: "log", PROP_STORAGE_MAX_VERSIONS: null });
+        let _log = all_volume
+            .create_storage(storage_config("log", volume_cfg))
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.get_storage("log").unwrap().config().max_versions,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_group_commit_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend =
+            RedbBackend::new(RedbBackendConfig::new().with_base_dir(temp_dir.path().to_path_buf()))
+                .unwrap();
+
+        let volume = RedbVolume {
+            admin_status: serde_json::Value::Null,
+            backend: Arc::new(backend),
+            history: History::Latest,
+        };
+
+        let volume_cfg = serde_json::json!({
+            PROP_STORAGE_DIR: "burst",
+            PROP_STORAGE_GROUP_COMMIT: { "max_delay_ms": 1, "max_batch_size": 64 },
+        });
+        let mut storage = volume
+            .create_storage(StorageConfig {
+                name: "burst".to_string(),
+                key_expr: "test/**".parse().unwrap(),
+                strip_prefix: None,
+                volume_cfg: volume_cfg.into(),
+                volume_id: "redb".to_string(),
+                complete: false,
+                garbage_collection_config: Default::default(),
+                replication: None,
+            })
+            .await
+            .unwrap();
+
+        let key = OwnedKeyExpr::new("test/key1").unwrap();
+        let id = zenoh::time::TimestampId::rand();
+        let result = storage
+            .put(
+                Some(key.clone()),
+                ZBytes::from("v1"),
+                Encoding::ZENOH_STRING,
+                Timestamp::new(NTP64(100), id),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(result, StorageInsertionResult::Inserted));
+
+        let data = storage.get(Some(key.clone()), "").await.unwrap();
+        assert_eq!(data.len(), 1);
+
+        let result = storage
+            .delete(Some(key.clone()), Timestamp::new(NTP64(50), id))
+            .await
+            .unwrap();
+        assert!(matches!(result, StorageInsertionResult::Outdated));
+
+        let result = storage
+            .delete(Some(key.clone()), Timestamp::new(NTP64(200), id))
+            .await
+            .unwrap();
+        assert!(matches!(result, StorageInsertionResult::Deleted));
+        assert!(storage.get(Some(key), "").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_volume_registers_storages_in_backend() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(
+            RedbBackend::new(RedbBackendConfig::new().with_base_dir(temp_dir.path().to_path_buf()))
+                .unwrap(),
+        );
+        let volume = RedbVolume {
+            admin_status: serde_json::Value::Null,
+            backend: backend.clone(),
+            history: History::Latest,
+        };
+        let storage_config = |name: &str| StorageConfig {
+            name: name.to_string(),
+            key_expr: "test/**".parse().unwrap(),
+            strip_prefix: None,
+            volume_cfg: serde_json::json!({
+                PROP_STORAGE_DB_FILE: "shared",
+                PROP_STORAGE_TABLE_NAME: name,
+            })
+            .into(),
+            volume_id: "redb".to_string(),
+            complete: false,
+            garbage_collection_config: Default::default(),
+            replication: None,
+        };
+
+        // Both storages live in the same file
+        let first = volume
+            .create_storage(storage_config("first"))
+            .await
+            .unwrap();
+        let second = volume
+            .create_storage(storage_config("second"))
+            .await
+            .unwrap();
+        let mut names = backend.list_storages().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["first", "second"]);
+        assert!(Arc::ptr_eq(
+            backend.get_storage("first").unwrap().database(),
+            backend.get_storage("second").unwrap().database()
+        ));
+
+        drop(first);
+        assert_eq!(backend.list_storages().unwrap(), vec!["second"]);
+        drop(second);
+        assert!(backend.list_storages().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_volume_rejects_invalid_group_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend =
+            RedbBackend::new(RedbBackendConfig::new().with_base_dir(temp_dir.path().to_path_buf()))
+                .unwrap();
+        let volume = RedbVolume {
+            admin_status: serde_json::Value::Null,
+            backend: Arc::new(backend),
+            history: History::Latest,
+        };
+
+        let volume_cfg = serde_json::json!({
+            PROP_STORAGE_DIR: "burst",
+            PROP_STORAGE_GROUP_COMMIT: "yes",
+        });
+        let result = volume
+            .create_storage(StorageConfig {
+                name: "burst".to_string(),
+                key_expr: "test/**".parse().unwrap(),
+                strip_prefix: None,
+                volume_cfg: volume_cfg.into(),
+                volume_id: "redb".to_string(),
+                complete: false,
+                garbage_collection_config: Default::default(),
+                replication: None,
+            })
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_storage_get_time_selector() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.redb");
+
+        let storage_config = RedbStorageConfig::new().with_keep_history(true);
+        let redb_storage =
+            RedbStorage::new(&db_path, storage_config.clone(), "test".to_string()).unwrap();
+
+        let mut storage_plugin = RedbStoragePlugin {
+            config: StorageConfig {
+                name: "test".to_string(),
+                key_expr: "test/**".parse().unwrap(),
+                strip_prefix: None,
+                volume_cfg: serde_json::Value::Object(Default::default()).into(),
+                volume_id: "test_volume".to_string(),
+                complete: false,
+                garbage_collection_config: Default::default(),
+                replication: None,
+            },
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
+            storage_config,
+            group_commit: None,
+            _registration: None,
+        };
+
+        // One version on 2020-01-01T12:00:00Z, one an hour ago
+        let key = OwnedKeyExpr::new("test/key1").unwrap();
+        let id = zenoh::time::TimestampId::rand();
+        let hour_ago = SystemTime::now() - std::time::Duration::from_secs(3600);
+        let times = [
+            NTP64::from(std::time::Duration::from_secs(1_577_880_000)),
+            NTP64::from(hour_ago.duration_since(UNIX_EPOCH).unwrap()),
+        ];
+        for (time, value) in times.into_iter().zip(["old", "recent"]) {
+            storage_plugin
+                .put(
+                    Some(key.clone()),
+                    ZBytes::from(value),
+                    Encoding::ZENOH_STRING,
+                    Timestamp::new(time, id),
+                )
+                .await
+                .unwrap();
+        }
+
+        let payloads = |data: Vec<StoredData>| -> Vec<String> {
+            data.iter()
+                .map(|d| d.payload.try_to_string().unwrap().into_owned())
+                .collect()
+        };
+
+        let data = storage_plugin.get(Some(key.clone()), "").await.unwrap();
+        assert_eq!(payloads(data), vec!["old", "recent"]);
+
+        let data = storage_plugin
+            .get(Some(key.clone()), "_time=[now(-2h)..]")
+            .await
+            .unwrap();
+        assert_eq!(payloads(data), vec!["recent"]);
+
+        let data = storage_plugin
+            .get(Some(key.clone()), "_time=[now(-10m)..]")
+            .await
+            .unwrap();
+        assert!(data.is_empty());
+
+        let data = storage_plugin
+            .get(
+                Some(key.clone()),
+                "_time=[2020-01-01T00:00:00Z..2020-01-02T00:00:00Z]",
+            )
+            .await
+            .unwrap();
+        assert_eq!(payloads(data), vec!["old"]);
+
+        // Other parameters are ignored
+        let data = storage_plugin
+            .get(Some(key.clone()), "foo=bar;_time=[..now(-30m)]")
+            .await
+            .unwrap();
+        assert_eq!(payloads(data), vec!["old", "recent"]);
+
+        assert!(
+            storage_plugin
+                .get(Some(key), "_time=yesterday")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_storage_get_time_selector_latest() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.redb");
+
+        let storage_config = RedbStorageConfig::new();
+        let redb_storage =
+            RedbStorage::new(&db_path, storage_config.clone(), "test".to_string()).unwrap();
+
+        let mut storage_plugin = RedbStoragePlugin {
+            config: StorageConfig {
+                name: "test".to_string(),
+                key_expr: "test/**".parse().unwrap(),
+                strip_prefix: None,
+                volume_cfg: serde_json::Value::Object(Default::default()).into(),
+                volume_id: "test_volume".to_string(),
+                complete: false,
+                garbage_collection_config: Default::default(),
+                replication: None,
+            },
+            storage: Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage))),
+            storage_config,
+            group_commit: None,
+            _registration: None,
+        };
+
+        let key = OwnedKeyExpr::new("test/key1").unwrap();
+        let hour_ago = SystemTime::now() - std::time::Duration::from_secs(3600);
+        let timestamp = Timestamp::new(
+            NTP64::from(hour_ago.duration_since(UNIX_EPOCH).unwrap()),
+            zenoh::time::TimestampId::rand(),
+        );
+        storage_plugin
+            .put(
+                Some(key.clone()),
+                ZBytes::from("value"),
+                Encoding::ZENOH_STRING,
+                timestamp,
+            )
+            .await
+            .unwrap();
+
+        let data = storage_plugin
+            .get(Some(key.clone()), "_time=[now(-2h)..now(-30m)]")
+            .await
+            .unwrap();
+        assert_eq!(data.len(), 1);
+
+        let data = storage_plugin
+            .get(Some(key), "_time=[now(-10m)..]")
+            .await
+            .unwrap();
+        assert!(data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_task_expires_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.redb");
+
+        let storage_config = RedbStorageConfig::new().with_ttl_secs(60);
+        let redb_storage = RedbStorage::new(&db_path, storage_config, "test".to_string()).unwrap();
+
+        // An entry written an hour ago, far past its TTL
+        let hour_ago = SystemTime::now() - std::time::Duration::from_secs(3600);
+        let timestamp = Timestamp::new(
+            NTP64::from(hour_ago.duration_since(UNIX_EPOCH).unwrap()),
+            zenoh::time::TimestampId::rand(),
+        );
+        redb_storage
+            .put(
+                "test/key1",
+                StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES),
+            )
+            .unwrap();
+
+        let storage = Arc::new(tokio::sync::Mutex::new(Arc::new(redb_storage)));
+        spawn_maintenance(
+            Arc::downgrade(&storage),
+            GarbageCollectionConfig {
+                period: std::time::Duration::from_millis(10),
+                lifespan: std::time::Duration::from_secs(86400),
+            },
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // The sweeper turned the entry into a tombstone
+        assert_eq!(storage.lock().await.expire_entries().unwrap(), 0);
+    }
 }
@@ -0,0 +1,246 @@
+//! Read-only access to database files for the zenoh-backend-redb storage backend.
+//!
+//! redb has no read-only mode: opening a file writes its header to flag it as in
+//! use, and repairs it first if it was not closed cleanly. [`ReadOnlyFile`] serves
+//! the file to redb and keeps those writes in memory, so that inspecting a file
+//! leaves it exactly as it was.
+
+use crate::error::Result;
+use redb::{Builder, Database, StorageBackend};
+use std::collections::HashMap;
+use std::fs::{File, TryLockError};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Size of the blocks of the file whose writes are kept in memory.
+const BLOCK_SIZE: u64 = 4096;
+
+/// Storage backend reading a database file and keeping the writes to it in memory.
+///
+/// The file is locked in shared mode, so files open in another process, which
+/// redb locks exclusively, fail to open instead of being read while written.
+#[derive(Debug)]
+pub(crate) struct ReadOnlyFile {
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    file: File,
+    /// Length of the file as seen by redb
+    len: u64,
+    /// Length of the prefix of the file that has not been truncated by redb
+    file_len: u64,
+    /// Blocks written by redb, by index
+    blocks: HashMap<u64, Vec<u8>>,
+}
+
+impl ReadOnlyFile {
+    /// Open the file at `path` for reading.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        file.try_lock_shared().map_err(|e| match e {
+            TryLockError::WouldBlock => io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "database is open for writing elsewhere",
+            ),
+            TryLockError::Error(e) => e,
+        })?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            state: Mutex::new(State {
+                file,
+                len,
+                file_len: len,
+                blocks: HashMap::new(),
+            }),
+        })
+    }
+
+    fn lock(&self) -> io::Result<std::sync::MutexGuard<'_, State>> {
+        self.state
+            .lock()
+            .map_err(|_| io::Error::other("read-only file state poisoned"))
+    }
+}
+
+impl State {
+    /// Read `buf.len()` bytes at `offset`, which must lie within one block.
+    fn read_into(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        let index = offset / BLOCK_SIZE;
+        let start = (offset % BLOCK_SIZE) as usize;
+        if let Some(block) = self.blocks.get(&index) {
+            buf.copy_from_slice(&block[start..start + buf.len()]);
+            return Ok(());
+        }
+
+        // Bytes past the end of the file read as zeros
+        let available = self.file_len.saturating_sub(offset).min(buf.len() as u64) as usize;
+        let (from_file, zeros) = buf.split_at_mut(available);
+        if !from_file.is_empty() {
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.read_exact(from_file)?;
+        }
+        zeros.fill(0);
+        Ok(())
+    }
+}
+
+impl StorageBackend for ReadOnlyFile {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.lock()?.len)
+    }
+
+    fn read(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut state = self.lock()?;
+        let end = offset + len as u64;
+        if end > state.len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut data = vec![0; len];
+        let mut position = offset;
+        while position < end {
+            let block_end = (position / BLOCK_SIZE + 1) * BLOCK_SIZE;
+            let chunk_end = block_end.min(end);
+            let range = (position - offset) as usize..(chunk_end - offset) as usize;
+            state.read_into(position, &mut data[range])?;
+            position = chunk_end;
+        }
+        Ok(data)
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        let mut state = self.lock()?;
+        if len < state.len {
+            // Truncated bytes read as zeros if the file grows again
+            state.file_len = state.file_len.min(len);
+            state.blocks.retain(|index, _| index * BLOCK_SIZE < len);
+            if let Some(block) = state.blocks.get_mut(&(len / BLOCK_SIZE)) {
+                block[(len % BLOCK_SIZE) as usize..].fill(0);
+            }
+        }
+        state.len = len;
+        Ok(())
+    }
+
+    fn sync_data(&self, _eventual: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        let mut state = self.lock()?;
+        let end = offset + data.len() as u64;
+
+        let mut position = offset;
+        while position < end {
+            let index = position / BLOCK_SIZE;
+            if !state.blocks.contains_key(&index) {
+                let mut block = vec![0; BLOCK_SIZE as usize];
+                state.read_into(index * BLOCK_SIZE, &mut block)?;
+                state.blocks.insert(index, block);
+            }
+            let chunk_end = ((index + 1) * BLOCK_SIZE).min(end);
+            let start = (position % BLOCK_SIZE) as usize;
+            let chunk = &data[(position - offset) as usize..(chunk_end - offset) as usize];
+            let block = state.blocks.get_mut(&index).expect("block was just loaded");
+            block[start..start + chunk.len()].copy_from_slice(chunk);
+            position = chunk_end;
+        }
+        state.len = state.len.max(end);
+        Ok(())
+    }
+}
+
+/// Open the database file at `path` without modifying it.
+pub(crate) fn open_database(path: &Path) -> Result<Database> {
+    let file = ReadOnlyFile::open(path)?;
+    Ok(Builder::new().create_with_backend(file)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redb::TableDefinition;
+    use tempfile::TempDir;
+
+    const TABLE: TableDefinition<&str, u64> = TableDefinition::new("table");
+
+    #[test]
+    fn test_open_leaves_file_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("read_only.redb");
+        {
+            let db = Database::create(&path).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            write_txn
+                .open_table(TABLE)
+                .unwrap()
+                .insert("key", 42)
+                .unwrap();
+            write_txn.commit().unwrap();
+        }
+        let before = std::fs::read(&path).unwrap();
+
+        let db = open_database(&path).unwrap();
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(TABLE).unwrap();
+        assert_eq!(table.get("key").unwrap().unwrap().value(), 42);
+        drop((table, read_txn));
+
+        // Writes stay in memory
+        let write_txn = db.begin_write().unwrap();
+        write_txn
+            .open_table(TABLE)
+            .unwrap()
+            .insert("key", 7)
+            .unwrap();
+        write_txn.commit().unwrap();
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(TABLE).unwrap();
+        assert_eq!(table.get("key").unwrap().unwrap().value(), 7);
+        drop((table, read_txn, db));
+
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn test_unclean_file_is_recovered_in_memory() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("open.redb");
+        let unclean = temp_dir.path().join("unclean.redb");
+
+        // A copy of a file still open was not closed cleanly
+        let db = Database::create(&path).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_txn
+            .open_table(TABLE)
+            .unwrap()
+            .insert("key", 42)
+            .unwrap();
+        write_txn.commit().unwrap();
+        std::fs::copy(&path, &unclean).unwrap();
+        drop(db);
+        let before = std::fs::read(&unclean).unwrap();
+
+        let db = open_database(&unclean).unwrap();
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(TABLE).unwrap();
+        assert_eq!(table.get("key").unwrap().unwrap().value(), 42);
+        drop((table, read_txn, db));
+
+        assert_eq!(std::fs::read(&unclean).unwrap(), before);
+    }
+
+    #[test]
+    fn test_open_fails_while_written() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("open.redb");
+        let db = Database::create(&path).unwrap();
+
+        assert!(open_database(&path).is_err());
+        drop(db);
+        assert!(open_database(&path).is_ok());
+    }
+}
@@ -0,0 +1,500 @@
+//! On-disk schema versioning for the zenoh-backend-redb storage backend.
+//!
+//! The schema version of a database file is recorded in its metadata table. Files
+//! written with an older schema are upgraded in place when a storage opens them:
+//! the migrations from their version to the current one run in a single write
+//! transaction, so a failed migration leaves the file unchanged.
+//!
+//! The table layout of the entries is recorded in the metadata table too. A file
+//! opened with a different [`StorageLayout`] is converted in the same transaction.
+//!
+//! Storages sharing a database file each have their own metadata table, so they
+//! are versioned and converted independently.
+//!
+//! The metadata table also records the name of the storage and the key scope its
+//! keys are stored with, so that opening it with a different `table_name`,
+//! `key_expr` or `strip_prefix` is detected instead of misreading its keys.
+
+use crate::config::StorageLayout;
+use crate::error::{RedbBackendError, Result};
+use crate::layout;
+use crate::storage::{StorageUsage, TableNames, read_usage, write_usage};
+use redb::{ReadableTable, TableHandle, WriteTransaction};
+use std::fmt;
+use tracing::info;
+
+/// Metadata holding the schema version, as a little-endian u32.
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Metadata holding the table layout of the entries.
+const LAYOUT_KEY: &str = "layout";
+
+/// Metadata holding the name the storage was last opened under, as UTF-8.
+const STORAGE_NAME_KEY: &str = "storage_name";
+
+/// Metadata holding the key scope the keys are stored with: a `strip_prefix` byte,
+/// then the `key_expr` as UTF-8, empty without one.
+const KEY_SCOPE_KEY: &str = "key_scope";
+
+/// The configuration fields determining how the keys of a storage are stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct KeyScopeRecord {
+    /// Key expression the keys are included in
+    pub key_expr: Option<String>,
+    /// Whether the literal prefix of `key_expr` is stripped from the keys
+    pub strip_prefix: bool,
+}
+
+impl fmt::Display for KeyScopeRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key_expr {
+            Some(key_expr) => write!(
+                f,
+                "key_expr '{}' and strip_prefix {}",
+                key_expr, self.strip_prefix
+            ),
+            None => write!(f, "no key_expr"),
+        }
+    }
+}
+
+/// Schema version of the database files written by this version of the crate.
+///
+/// Files without a metadata table predate schema versioning and have version 0.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// An in-place upgrade from one schema version to the next.
+struct Migration {
+    /// Version the migration upgrades from, to `from + 1`
+    from: u32,
+    /// What the migration changes, for logging
+    description: &'static str,
+    /// Upgrade the tables of a storage within the opening transaction
+    apply: fn(&WriteTransaction, &TableNames) -> Result<()>,
+}
+
+/// Migrations in order, one per schema version.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "create the usage table and count existing entries",
+        apply: count_usage,
+    },
+    Migration {
+        from: 1,
+        description: "record the table layout",
+        apply: record_layout,
+    },
+];
+
+/// Read the schema version recorded in a metadata table, if any.
+pub(crate) fn read_schema_version<T>(metadata_table: &T) -> Result<Option<u32>>
+where
+    T: ReadableTable<&'static str, &'static [u8]>,
+{
+    match metadata_table.get(SCHEMA_VERSION_KEY)? {
+        Some(value) => {
+            let bytes = value.value().try_into().map_err(|_| {
+                RedbBackendError::schema(format!(
+                    "Invalid schema version record ({} bytes)",
+                    value.value().len()
+                ))
+            })?;
+            Ok(Some(u32::from_le_bytes(bytes)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Upgrade the tables of a storage to [`SCHEMA_VERSION`], returning the version
+/// they had.
+///
+/// A storage without tables yet starts at [`SCHEMA_VERSION`] in `layout`, without
+/// going through the migrations. Fails without modifying the database if the
+/// tables have a newer schema version.
+pub(crate) fn migrate(
+    write_txn: &WriteTransaction,
+    tables: &TableNames,
+    layout: StorageLayout,
+) -> Result<u32> {
+    let fresh = !write_txn
+        .list_tables()?
+        .any(|table| tables.contains(table.name()));
+    let found = read_schema_version(&write_txn.open_table(tables.metadata())?)?.unwrap_or(0);
+
+    if found > SCHEMA_VERSION {
+        return Err(RedbBackendError::schema(format!(
+            "Database has schema version {}, but this version of zenoh-backend-redb only \
+             supports versions up to {}; upgrade zenoh-backend-redb to open it",
+            found, SCHEMA_VERSION
+        )));
+    }
+
+    if fresh {
+        write_usage(
+            &mut write_txn.open_table(tables.usage())?,
+            &StorageUsage::default(),
+        )?;
+        let mut metadata_table = write_txn.open_table(tables.metadata())?;
+        metadata_table.insert(LAYOUT_KEY, layout_name(layout).as_bytes())?;
+    } else {
+        for migration in MIGRATIONS.iter().skip(found as usize) {
+            info!(
+                "Migrating database schema from version {} to {}: {}",
+                migration.from,
+                migration.from + 1,
+                migration.description
+            );
+            (migration.apply)(write_txn, tables)?;
+        }
+    }
+
+    if found != SCHEMA_VERSION {
+        let mut metadata_table = write_txn.open_table(tables.metadata())?;
+        metadata_table.insert(SCHEMA_VERSION_KEY, SCHEMA_VERSION.to_le_bytes().as_slice())?;
+    }
+    Ok(found)
+}
+
+/// Convert the tables of a storage to `layout`, including the past versions kept
+/// in history mode.
+pub(crate) fn convert_layout(
+    write_txn: &WriteTransaction,
+    tables: &TableNames,
+    layout: StorageLayout,
+) -> Result<()> {
+    let mut metadata_table = write_txn.open_table(tables.metadata())?;
+    let current = read_layout(&metadata_table)?;
+    if current == layout {
+        return Ok(());
+    }
+
+    let moved = layout::convert(write_txn, tables, current, layout, false)?;
+    let mut versions = 0;
+    if layout::tables_exist(write_txn, tables, current, true)? {
+        versions = layout::convert(write_txn, tables, current, layout, true)?;
+    }
+    metadata_table.insert(LAYOUT_KEY, layout_name(layout).as_bytes())?;
+
+    info!(
+        "Converted {} entries and {} past versions from the {:?} to the {:?} layout",
+        moved, versions, current, layout
+    );
+    Ok(())
+}
+
+/// Name of a layout in the metadata table.
+fn layout_name(layout: StorageLayout) -> &'static str {
+    match layout {
+        StorageLayout::TwoTables => "two_tables",
+        StorageLayout::SingleTable => "single_table",
+    }
+}
+
+/// Read the table layout recorded in a metadata table.
+fn read_layout<T>(metadata_table: &T) -> Result<StorageLayout>
+where
+    T: ReadableTable<&'static str, &'static [u8]>,
+{
+    let value = metadata_table
+        .get(LAYOUT_KEY)?
+        .ok_or_else(|| RedbBackendError::schema("Missing table layout record"))?;
+    match value.value() {
+        b"two_tables" => Ok(StorageLayout::TwoTables),
+        b"single_table" => Ok(StorageLayout::SingleTable),
+        other => Err(RedbBackendError::schema(format!(
+            "Unknown table layout: {}",
+            String::from_utf8_lossy(other)
+        ))),
+    }
+}
+
+/// Record the name a storage is opened under, so it can be discovered again.
+pub(crate) fn record_storage_name(
+    write_txn: &WriteTransaction,
+    tables: &TableNames,
+    name: &str,
+) -> Result<()> {
+    let mut metadata_table = write_txn.open_table(tables.metadata())?;
+    let recorded = metadata_table
+        .get(STORAGE_NAME_KEY)?
+        .map(|value| value.value() == name.as_bytes());
+    if recorded != Some(true) {
+        metadata_table.insert(STORAGE_NAME_KEY, name.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Read the name recorded in a metadata table, if any.
+///
+/// Files written before storage names were recorded have none.
+pub(crate) fn read_storage_name<T>(metadata_table: &T) -> Result<Option<String>>
+where
+    T: ReadableTable<&'static str, &'static [u8]>,
+{
+    match metadata_table.get(STORAGE_NAME_KEY)? {
+        Some(value) => String::from_utf8(value.value().to_vec())
+            .map(Some)
+            .map_err(|_| RedbBackendError::schema("Invalid storage name record")),
+        None => Ok(None),
+    }
+}
+
+/// Record the key scope the keys of a storage are stored with.
+pub(crate) fn record_key_scope(
+    write_txn: &WriteTransaction,
+    tables: &TableNames,
+    record: &KeyScopeRecord,
+) -> Result<()> {
+    let mut value = vec![u8::from(record.strip_prefix)];
+    value.extend_from_slice(record.key_expr.as_deref().unwrap_or_default().as_bytes());
+    let mut metadata_table = write_txn.open_table(tables.metadata())?;
+    metadata_table.insert(KEY_SCOPE_KEY, value.as_slice())?;
+    Ok(())
+}
+
+/// Read the key scope recorded in a metadata table, if any.
+///
+/// Files written before key scopes were recorded have none.
+pub(crate) fn read_key_scope<T>(metadata_table: &T) -> Result<Option<KeyScopeRecord>>
+where
+    T: ReadableTable<&'static str, &'static [u8]>,
+{
+    let Some(value) = metadata_table.get(KEY_SCOPE_KEY)? else {
+        return Ok(None);
+    };
+    let invalid = || RedbBackendError::schema("Invalid key scope record");
+    let (strip_prefix, key_expr) = value.value().split_first().ok_or_else(invalid)?;
+    let key_expr = std::str::from_utf8(key_expr).map_err(|_| invalid())?;
+    Ok(Some(KeyScopeRecord {
+        key_expr: (!key_expr.is_empty()).then(|| key_expr.to_string()),
+        strip_prefix: *strip_prefix != 0,
+    }))
+}
+
+/// The table name under which a storage named `name` is recorded in the database,
+/// if any.
+pub(crate) fn find_table_name(write_txn: &WriteTransaction, name: &str) -> Result<Option<String>> {
+    let metadata_tables: Vec<_> = write_txn
+        .list_tables()?
+        .map(|table| table.name().to_string())
+        .collect();
+    for table in metadata_tables {
+        let table_name = TableNames::table_name_of(&table);
+        let tables = TableNames::new(table_name)?;
+        if tables.metadata().name() != table {
+            continue;
+        }
+        let metadata_table = write_txn.open_table(tables.metadata())?;
+        if read_storage_name(&metadata_table)?.as_deref() == Some(name) {
+            return Ok(Some(table_name.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Check that the tables of a storage can be opened by this version of the crate,
+/// returning their table layout.
+///
+/// Unlike [`migrate`], this only reads the metadata table, which files written
+/// before schema versioning don't have.
+pub(crate) fn inspect<T>(metadata_table: Option<&T>) -> Result<StorageLayout>
+where
+    T: ReadableTable<&'static str, &'static [u8]>,
+{
+    let Some(metadata_table) = metadata_table else {
+        return Ok(StorageLayout::TwoTables);
+    };
+    let found = read_schema_version(metadata_table)?.unwrap_or(0);
+    if found > SCHEMA_VERSION {
+        return Err(RedbBackendError::schema(format!(
+            "Database has schema version {}, but this version of zenoh-backend-redb only \
+             supports versions up to {}",
+            found, SCHEMA_VERSION
+        )));
+    }
+    if found < 2 {
+        // The layout is recorded from version 2, and was always two tables before
+        return Ok(StorageLayout::TwoTables);
+    }
+    read_layout(metadata_table)
+}
+
+/// Version 0 to 1: track the usage of live entries, counting the existing ones.
+fn count_usage(write_txn: &WriteTransaction, tables: &TableNames) -> Result<()> {
+    let payloads_table = write_txn.open_table(tables.payloads())?;
+    write_txn.open_table(tables.data_info())?;
+
+    let mut usage_table = write_txn.open_table(tables.usage())?;
+    if read_usage(&usage_table)?.is_none() {
+        let mut usage = StorageUsage::default();
+        for item in payloads_table.iter()? {
+            let (key_bytes, payload_bytes) = item?;
+            usage.add(key_bytes.value(), payload_bytes.value().len());
+        }
+        write_usage(&mut usage_table, &usage)?;
+    }
+    Ok(())
+}
+
+/// Version 1 to 2: record the table layout, which was always two tables.
+fn record_layout(write_txn: &WriteTransaction, tables: &TableNames) -> Result<()> {
+    let mut metadata_table = write_txn.open_table(tables.metadata())?;
+    if metadata_table.get(LAYOUT_KEY)?.is_none() {
+        let name = layout_name(StorageLayout::TwoTables);
+        metadata_table.insert(LAYOUT_KEY, name.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_TABLE_NAME;
+    use redb::Database;
+    use tempfile::TempDir;
+
+    fn default_tables() -> TableNames {
+        TableNames::new(DEFAULT_TABLE_NAME).unwrap()
+    }
+
+    #[test]
+    fn test_migrations_cover_every_version() {
+        assert_eq!(MIGRATIONS.len(), SCHEMA_VERSION as usize);
+        for (version, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, version as u32);
+        }
+    }
+
+    #[test]
+    fn test_migrate_records_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::create(temp_dir.path().join("schema.redb")).unwrap();
+        let tables = default_tables();
+
+        let write_txn = db.begin_write().unwrap();
+        assert_eq!(
+            migrate(&write_txn, &tables, StorageLayout::TwoTables).unwrap(),
+            0
+        );
+        write_txn.commit().unwrap();
+
+        let write_txn = db.begin_write().unwrap();
+        assert_eq!(
+            migrate(&write_txn, &tables, StorageLayout::TwoTables).unwrap(),
+            SCHEMA_VERSION
+        );
+        let metadata_table = write_txn.open_table(tables.metadata()).unwrap();
+        assert_eq!(
+            read_schema_version(&metadata_table).unwrap(),
+            Some(SCHEMA_VERSION)
+        );
+    }
+
+    #[test]
+    fn test_migrate_fresh_single_table() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::create(temp_dir.path().join("schema.redb")).unwrap();
+        let tables = default_tables();
+
+        let write_txn = db.begin_write().unwrap();
+        migrate(&write_txn, &tables, StorageLayout::SingleTable).unwrap();
+        let names: Vec<_> = write_txn
+            .list_tables()
+            .unwrap()
+            .map(|table| table.name().to_string())
+            .collect();
+        assert!(!names.contains(&tables.payloads().name().to_string()));
+        assert!(!names.contains(&tables.data_info().name().to_string()));
+
+        let metadata_table = write_txn.open_table(tables.metadata()).unwrap();
+        assert_eq!(
+            inspect(Some(&metadata_table)).unwrap(),
+            StorageLayout::SingleTable
+        );
+        let usage_table = write_txn.open_table(tables.usage()).unwrap();
+        assert_eq!(
+            read_usage(&usage_table).unwrap(),
+            Some(StorageUsage::default())
+        );
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::create(temp_dir.path().join("schema.redb")).unwrap();
+        let tables = default_tables();
+
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut metadata_table = write_txn.open_table(tables.metadata()).unwrap();
+            let newer = (SCHEMA_VERSION + 1).to_le_bytes();
+            metadata_table
+                .insert(SCHEMA_VERSION_KEY, newer.as_slice())
+                .unwrap();
+        }
+        write_txn.commit().unwrap();
+
+        let write_txn = db.begin_write().unwrap();
+        let result = migrate(&write_txn, &tables, StorageLayout::TwoTables);
+        assert!(matches!(result, Err(RedbBackendError::SchemaError(_))));
+        let metadata_table = write_txn.open_table(tables.metadata()).unwrap();
+        let result = inspect(Some(&metadata_table));
+        assert!(matches!(result, Err(RedbBackendError::SchemaError(_))));
+    }
+
+    #[test]
+    fn test_record_key_scope() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::create(temp_dir.path().join("schema.redb")).unwrap();
+        let tables = default_tables();
+
+        let write_txn = db.begin_write().unwrap();
+        migrate(&write_txn, &tables, StorageLayout::TwoTables).unwrap();
+        for record in [
+            KeyScopeRecord {
+                key_expr: Some("demo/**".to_string()),
+                strip_prefix: true,
+            },
+            KeyScopeRecord {
+                key_expr: None,
+                strip_prefix: false,
+            },
+        ] {
+            record_key_scope(&write_txn, &tables, &record).unwrap();
+            let metadata_table = write_txn.open_table(tables.metadata()).unwrap();
+            assert_eq!(read_key_scope(&metadata_table).unwrap(), Some(record));
+        }
+    }
+
+    #[test]
+    fn test_record_storage_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::create(temp_dir.path().join("schema.redb")).unwrap();
+        let tables = default_tables();
+
+        let write_txn = db.begin_write().unwrap();
+        migrate(&write_txn, &tables, StorageLayout::TwoTables).unwrap();
+        {
+            let metadata_table = write_txn.open_table(tables.metadata()).unwrap();
+            assert_eq!(read_storage_name(&metadata_table).unwrap(), None);
+            assert_eq!(
+                inspect(Some(&metadata_table)).unwrap(),
+                StorageLayout::TwoTables
+            );
+        }
+        record_storage_name(&write_txn, &tables, "first").unwrap();
+        record_storage_name(&write_txn, &tables, "second").unwrap();
+        let metadata_table = write_txn.open_table(tables.metadata()).unwrap();
+        assert_eq!(
+            read_storage_name(&metadata_table).unwrap(),
+            Some("second".to_string())
+        );
+        drop(metadata_table);
+        assert_eq!(
+            find_table_name(&write_txn, "second").unwrap(),
+            Some(DEFAULT_TABLE_NAME.to_string())
+        );
+        assert_eq!(find_table_name(&write_txn, "first").unwrap(), None);
+    }
+}
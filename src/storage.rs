@@ -1,19 +1,35 @@
 //! Storage implementation for the zenoh-backend-redb storage backend.
 //!
-//! This implementation separates payload and metadata (data_info) into different tables,
-//! similar to the RocksDB backend design using column families.
+//! The entries are stored with a configurable table layout, either
+//! [`StorageLayout::TwoTables`](crate::config::StorageLayout::TwoTables) or
+//! [`StorageLayout::SingleTable`](crate::config::StorageLayout::SingleTable): see
+//! `layout.rs` for how their records are stored.
 
-use crate::config::RedbStorageConfig;
+use crate::compression::{self, CODEC_NONE};
+use crate::config::{DEFAULT_TABLE_NAME, RedbStorageConfig};
+use crate::encryption::{Cipher, Domain, KEY_ID_NONE};
 use crate::error::{RedbBackendError, Result};
-use redb::{Database, ReadableTable, TableDefinition};
+use crate::layout::{self, EntryTables, OwnedGuard, PayloadRef};
+use crate::schema::{self, KeyScopeRecord};
+use redb::{
+    Builder, Database, Durability, Key, Range, ReadOnlyTable, ReadTransaction, ReadableTable,
+    Table, TableDefinition, TableError, TableHandle, Value, WriteTransaction,
+};
+use std::any::Any;
+use std::borrow::{Borrow, Cow};
 use std::cell::RefCell;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
 use std::path::Path;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::{debug, info, trace, warn};
 use zenoh::bytes::{Encoding, ZBytes};
-use zenoh::internal::buffers::ZSlice;
+use zenoh::internal::buffers::{ZBuf, ZSlice, ZSliceBuffer};
+use zenoh::key_expr::{KeyExpr, OwnedKeyExpr, keyexpr};
 use zenoh::time::{NTP64, Timestamp, TimestampId};
-use zenoh_ext::{z_deserialize, z_serialize};
+use zenoh_ext::{ZDeserializer, ZSerializer, z_deserialize, z_serialize};
 
 // Thread-local buffers for zero-allocation PUT/GET operations
 thread_local! {
@@ -21,18 +37,209 @@ thread_local! {
     static VALUE_BUFFER: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(1024));
 }
 
-/// Table definition for storing payloads.
-/// Key: Zenoh key expression as bytes
-/// Value: Raw payload bytes
-const PAYLOADS_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("payloads");
+/// Key stored in place of a key equal to the stripped prefix, which would otherwise
+/// be stored as an empty string.
+pub const NONE_KEY: &str = "@@none_key@@";
 
-/// Table definition for storing data info (metadata).
-/// Key: Zenoh key expression as bytes
-/// Value: Serialized DataInfo (timestamp, encoding, deleted flag)
-const DATA_INFO_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("data_info");
+/// Names of the tables of a storage.
+///
+/// Storages sharing a database file each have their own tables, named after their
+/// `table_name`. Storages with the default table name use the unprefixed names of
+/// the files written before tables could be shared, so these open unchanged.
+#[derive(Debug, Clone)]
+pub(crate) struct TableNames {
+    payloads: String,
+    data_info: String,
+    history_payloads: String,
+    history_data_info: String,
+    records: String,
+    history_records: String,
+    usage: String,
+    quarantine: String,
+    metadata: String,
+    eviction_index: String,
+}
+
+impl TableNames {
+    /// Separator between the table name of a storage and the names of its tables.
+    const SEPARATOR: char = ':';
+
+    /// The names of the tables of a storage with table name `table_name`.
+    pub fn new(table_name: &str) -> Result<Self> {
+        if table_name.is_empty() || table_name.contains(Self::SEPARATOR) {
+            return Err(RedbBackendError::config(format!(
+                "Invalid table name '{}': must be non-empty and not contain '{}'",
+                table_name,
+                Self::SEPARATOR
+            )));
+        }
+        let name = |table: &str| {
+            if table_name == DEFAULT_TABLE_NAME {
+                table.to_string()
+            } else {
+                format!("{}{}{}", table_name, Self::SEPARATOR, table)
+            }
+        };
+        Ok(Self {
+            payloads: name("payloads"),
+            data_info: name("data_info"),
+            history_payloads: name("history_payloads"),
+            history_data_info: name("history_data_info"),
+            records: name("records"),
+            history_records: name("history_records"),
+            usage: name("usage"),
+            quarantine: name("quarantine"),
+            metadata: name("metadata"),
+            eviction_index: name("eviction_index"),
+        })
+    }
+
+    /// Names of the tables a rewrite of the storage is staged in, within the
+    /// transaction replacing the tables with them.
+    pub fn staging(&self) -> Self {
+        let name = |table: &str| format!("{}~staging", table);
+        Self {
+            payloads: name(&self.payloads),
+            data_info: name(&self.data_info),
+            history_payloads: name(&self.history_payloads),
+            history_data_info: name(&self.history_data_info),
+            records: name(&self.records),
+            history_records: name(&self.history_records),
+            usage: name(&self.usage),
+            quarantine: name(&self.quarantine),
+            metadata: name(&self.metadata),
+            eviction_index: name(&self.eviction_index),
+        }
+    }
+
+    /// The table name of the storage owning a table of the database.
+    pub fn table_name_of(table: &str) -> &str {
+        table
+            .split_once(Self::SEPARATOR)
+            .map_or(DEFAULT_TABLE_NAME, |(table_name, _)| table_name)
+    }
+
+    /// Whether a table of the database belongs to the storage.
+    pub fn contains(&self, table: &str) -> bool {
+        [
+            &self.payloads,
+            &self.data_info,
+            &self.history_payloads,
+            &self.history_data_info,
+            &self.records,
+            &self.history_records,
+            &self.usage,
+            &self.quarantine,
+            &self.metadata,
+            &self.eviction_index,
+        ]
+        .into_iter()
+        .any(|name| name == table)
+    }
+
+    /// Table definition for storing payloads.
+    /// Key: Zenoh key expression as bytes
+    /// Value: Raw payload bytes
+    pub fn payloads(&self) -> TableDefinition<'_, &'static [u8], &'static [u8]> {
+        TableDefinition::new(&self.payloads)
+    }
+
+    /// Table definition for storing data info (metadata).
+    /// Key: Zenoh key expression as bytes
+    /// Value: Serialized DataInfo (timestamp, encoding, deleted flag)
+    pub fn data_info(&self) -> TableDefinition<'_, &'static [u8], &'static [u8]> {
+        TableDefinition::new(&self.data_info)
+    }
+
+    /// Table definition for storing the payloads of past versions (history mode only).
+    /// Key: Zenoh key expression as bytes, 0x00, big-endian timestamp time, then the
+    /// timestamp ID bytes
+    /// Value: Raw payload bytes
+    pub fn history_payloads(&self) -> TableDefinition<'_, &'static [u8], &'static [u8]> {
+        TableDefinition::new(&self.history_payloads)
+    }
+
+    /// Table definition for storing the data info of past versions (history mode only).
+    /// Key: Same composite key as `history_payloads`
+    /// Value: Serialized DataInfo (timestamp, encoding, deleted flag)
+    pub fn history_data_info(&self) -> TableDefinition<'_, &'static [u8], &'static [u8]> {
+        TableDefinition::new(&self.history_data_info)
+    }
+
+    /// Table definition for storing data info and payloads together (single-table layout).
+    /// Key: Zenoh key expression as bytes
+    /// Value: Record header, serialized DataInfo and raw payload bytes
+    pub fn records(&self) -> TableDefinition<'_, &'static [u8], &'static [u8]> {
+        TableDefinition::new(&self.records)
+    }
+
+    /// Table definition for storing past versions (single-table layout, history mode only).
+    /// Key: Same composite key as `history_payloads`
+    /// Value: Same record as `records`
+    pub fn history_records(&self) -> TableDefinition<'_, &'static [u8], &'static [u8]> {
+        TableDefinition::new(&self.history_records)
+    }
+
+    /// Table definition for storage usage counters.
+    /// Key: Counter name (`entries` or `bytes`)
+    /// Value: Counter value
+    pub fn usage(&self) -> TableDefinition<'_, &'static str, u64> {
+        TableDefinition::new(&self.usage)
+    }
+
+    /// Table definition for records removed from the other tables by a repair.
+    /// Key: Name of the table the record was in, and its key
+    /// Value: Raw record bytes
+    pub fn quarantine(&self) -> TableDefinition<'_, (&'static str, &'static [u8]), &'static [u8]> {
+        TableDefinition::new(&self.quarantine)
+    }
+
+    /// Table definition for database metadata.
+    /// Key: Metadata name
+    /// Value: Metadata value, encoded according to its name
+    pub fn metadata(&self) -> TableDefinition<'_, &'static str, &'static [u8]> {
+        TableDefinition::new(&self.metadata)
+    }
+
+    /// Table definition for the live entries ordered by timestamp, kept when the
+    /// oldest entries are evicted to meet a quota.
+    /// Key: Big-endian timestamp time, the timestamp ID bytes, then the key
+    /// Value: None
+    pub fn eviction_index(&self) -> TableDefinition<'_, &'static [u8], ()> {
+        TableDefinition::new(&self.eviction_index)
+    }
+}
+
+/// Open quarantine table, keyed by table name and record key.
+pub(crate) type QuarantineTable<'txn> = Table<'txn, (&'static str, &'static [u8]), &'static [u8]>;
+
+/// Open eviction index, keyed by the timestamp and key of the live entries.
+type EvictionIndex<'txn> = Table<'txn, &'static [u8], ()>;
+
+/// Usage counter holding the number of live entries.
+const USAGE_ENTRIES: &str = "entries";
+
+/// Usage counter holding the total size of live keys and payloads.
+const USAGE_BYTES: &str = "bytes";
+
+/// Maximum number of entries scanned per transaction by the expiry sweeper and
+/// garbage collection.
+const MAINTENANCE_BATCH_SIZE: usize = 1000;
+
+/// Separator between the key and the timestamp in history keys.
+/// Key expressions never contain a NUL byte, so versions of a key sort together.
+const HISTORY_KEY_SEPARATOR: u8 = 0x00;
+
+/// Length of the suffix of history keys: the separator, then the timestamp time
+/// and ID.
+const HISTORY_SUFFIX_LEN: usize = 1 + 8 + 16;
+
+/// Length of the prefix of eviction index keys: the timestamp time and ID.
+const EVICTION_PREFIX_LEN: usize = 8 + 16;
 
 /// Metadata associated with a stored value.
-/// This matches the RocksDB backend's DataInfo structure.
+/// This matches the RocksDB backend's DataInfo structure, extended with the codec
+/// of the stored payload, the key it is encrypted with and its checksum.
 #[derive(Debug, Clone)]
 struct DataInfo {
     /// Zenoh timestamp with both time and ID components
@@ -41,13 +248,81 @@ struct DataInfo {
     pub deleted: bool,
     /// Encoding format of the payload
     pub encoding: Encoding,
+    /// Compression codec of the stored payload
+    pub codec: u8,
+    /// Identifier of the encryption key, or `KEY_ID_NONE` for plaintext entries
+    pub key_id: u32,
+    /// Encoding sealed with the encryption key; `encoding` is the default when set
+    pub sealed_encoding: Vec<u8>,
+    /// CRC32C of the stored payload bytes, absent for tombstones and older entries
+    pub checksum: Option<u32>,
 }
 
 /// Tuple representation for serialization of DataInfo.
 /// Format: (timestamp_time, timestamp_id, deleted, encoding_id, encoding_schema)
+///
+/// Fields added later (the codec, the encryption key id and sealed encoding, then
+/// the payload checksum) are serialized after the tuple, so entries
+/// written before they existed still decode, with default values.
 type DataInfoTuple = (u64, [u8; 16], bool, u16, Vec<u8>);
 
 impl DataInfo {
+    /// Metadata of a live entry.
+    pub fn new(encoding: Encoding, timestamp: Timestamp, codec: u8) -> Self {
+        DataInfo {
+            timestamp,
+            deleted: false,
+            encoding,
+            codec,
+            key_id: KEY_ID_NONE,
+            sealed_encoding: Vec::new(),
+            checksum: None,
+        }
+    }
+
+    /// Metadata of a live entry whose payload and encoding are encrypted.
+    pub fn encrypted(
+        timestamp: Timestamp,
+        codec: u8,
+        key_id: u32,
+        sealed_encoding: Vec<u8>,
+    ) -> Self {
+        DataInfo {
+            timestamp,
+            deleted: false,
+            encoding: Encoding::default(),
+            codec,
+            key_id,
+            sealed_encoding,
+            checksum: None,
+        }
+    }
+
+    /// Record the checksum of the payload bytes stored for this entry.
+    pub fn with_checksum(mut self, stored_payload: &[u8]) -> Self {
+        self.checksum = Some(crc32c::crc32c(stored_payload));
+        self
+    }
+
+    /// Check the stored payload bytes against the recorded checksum, if any.
+    pub fn verify_checksum(&self, stored_payload: &[u8]) -> bool {
+        self.checksum
+            .is_none_or(|checksum| checksum == crc32c::crc32c(stored_payload))
+    }
+
+    /// Metadata of a tombstone.
+    pub fn tombstone(timestamp: Timestamp) -> Self {
+        DataInfo {
+            timestamp,
+            deleted: true,
+            encoding: Encoding::default(),
+            codec: CODEC_NONE,
+            key_id: KEY_ID_NONE,
+            sealed_encoding: Vec::new(),
+            checksum: None,
+        }
+    }
+
     /// Convert DataInfo to tuple format for serialization.
     pub fn as_tuple(&self) -> DataInfoTuple {
         let timestamp_time = self.timestamp.get_time().as_u64();
@@ -71,6 +346,7 @@ impl DataInfo {
     /// Create DataInfo from tuple format during deserialization.
     pub fn from_tuple(
         (timestamp_time, timestamp_id, deleted, encoding_id, encoding_schema): DataInfoTuple,
+        codec: u8,
     ) -> Result<Self> {
         let timestamp_id = TimestampId::try_from(timestamp_id)
             .map_err(|e| RedbBackendError::serialization(format!("Invalid timestamp ID: {}", e)))?;
@@ -85,31 +361,74 @@ impl DataInfo {
             timestamp,
             deleted,
             encoding,
+            codec,
+            key_id: KEY_ID_NONE,
+            sealed_encoding: Vec::new(),
+            checksum: None,
         })
     }
 }
 
 /// Encode DataInfo into bytes using Zenoh's serialization.
-fn encode_data_info(encoding: Encoding, timestamp: &Timestamp, deleted: bool) -> Result<Vec<u8>> {
-    let data_info = DataInfo {
-        timestamp: *timestamp,
-        deleted,
-        encoding,
-    };
-    let bytes = z_serialize(&data_info.as_tuple());
-    Ok(bytes.to_bytes().into_owned())
+fn encode_data_info(data_info: &DataInfo) -> Result<Vec<u8>> {
+    let mut serializer = ZSerializer::new();
+    serializer.serialize(data_info.as_tuple());
+    serializer.serialize(data_info.codec);
+    serializer.serialize(data_info.key_id);
+    serializer.serialize(&data_info.sealed_encoding);
+    if let Some(checksum) = data_info.checksum {
+        serializer.serialize(checksum);
+    }
+    Ok(serializer.finish().to_bytes().into_owned())
 }
 
 /// Decode DataInfo from bytes.
-fn decode_data_info(buf: &[u8]) -> Result<(Encoding, Timestamp, bool)> {
-    let bytes = ZBytes::from(buf);
-    let tuple: DataInfoTuple = z_deserialize(&bytes).map_err(|_| {
+fn decode_data_info(buf: &[u8]) -> Result<DataInfo> {
+    let decode_error = || {
         RedbBackendError::serialization(
             "Failed to decode data-info (encoding, deleted, timestamp)".to_string(),
         )
-    })?;
-    let data_info = DataInfo::from_tuple(tuple)?;
-    Ok((data_info.encoding, data_info.timestamp, data_info.deleted))
+    };
+
+    let bytes = ZBytes::from(buf);
+    let mut deserializer = ZDeserializer::new(&bytes);
+    let tuple: DataInfoTuple = deserializer.deserialize().map_err(|_| decode_error())?;
+    // Entries written before compression support have no codec
+    let codec = if deserializer.done() {
+        CODEC_NONE
+    } else {
+        deserializer.deserialize().map_err(|_| decode_error())?
+    };
+    let mut info = DataInfo::from_tuple(tuple, codec)?;
+    // Entries written before encryption support have no encryption key id
+    if !deserializer.done() {
+        info.key_id = deserializer.deserialize().map_err(|_| decode_error())?;
+        info.sealed_encoding = deserializer.deserialize().map_err(|_| decode_error())?;
+    }
+    // Tombstones and entries written before checksum support have no checksum
+    if !deserializer.done() {
+        info.checksum = Some(deserializer.deserialize().map_err(|_| decode_error())?);
+    }
+    if !deserializer.done() {
+        return Err(decode_error());
+    }
+    Ok(info)
+}
+
+/// Encode an encoding so it can be sealed with the payload of an encrypted entry.
+fn encode_encoding(encoding: &Encoding) -> Vec<u8> {
+    let schema = encoding.schema().map(|s| s.to_vec()).unwrap_or_default();
+    z_serialize(&(encoding.id(), schema))
+        .to_bytes()
+        .into_owned()
+}
+
+/// Decode an encoding unsealed from an encrypted entry.
+fn decode_encoding(buf: &[u8]) -> Result<Encoding> {
+    let (id, schema): (u16, Vec<u8>) = z_deserialize(&ZBytes::from(buf))
+        .map_err(|_| RedbBackendError::serialization("Failed to decode encrypted encoding"))?;
+    let schema = (!schema.is_empty()).then(|| ZSlice::from(schema));
+    Ok(Encoding::new(id, schema))
 }
 
 /// Represents a value stored in the database with associated metadata.
@@ -149,482 +468,4132 @@ impl StoredValue {
     }
 }
 
-/// The main storage implementation using redb.
-pub struct RedbStorage {
-    /// The redb database instance
-    db: Arc<Database>,
-
-    /// Storage configuration
-    config: RedbStorageConfig,
-
-    /// Storage name for logging
-    name: String,
+/// A value read by [`RedbStorage::get_view`], whose payload is shared rather than
+/// copied.
+///
+/// Payloads stored uncompressed and unencrypted are referenced in place, in the
+/// database page holding them: the read transaction they were read in stays open
+/// until the view and every [`ZBytes`] made from it are dropped. Other payloads
+/// are decoded into their own buffer.
+#[derive(Debug, Clone)]
+pub struct ValueView {
+    payload: ZSlice,
+    timestamp: Timestamp,
+    encoding: Encoding,
 }
 
-impl RedbStorage {
-    /// Create a new RedbStorage instance.
-    pub fn new<P: AsRef<Path>>(path: P, config: RedbStorageConfig, name: String) -> Result<Self> {
-        info!("Creating redb storage at: {:?}", path.as_ref());
+impl ValueView {
+    /// Get the timestamp.
+    pub fn timestamp(&self) -> &Timestamp {
+        &self.timestamp
+    }
 
-        let db = Database::create(path.as_ref())?;
+    /// Get the encoding.
+    pub fn encoding(&self) -> &Encoding {
+        &self.encoding
+    }
 
-        // Initialize both tables
-        let write_txn = db.begin_write()?;
-        {
-            // Create both tables if they don't exist
-            write_txn.open_table(PAYLOADS_TABLE)?;
-            write_txn.open_table(DATA_INFO_TABLE)?;
-        }
-        write_txn.commit()?;
+    /// Get the payload.
+    pub fn payload(&self) -> &[u8] {
+        self.payload.as_slice()
+    }
 
-        info!("Redb storage created successfully");
+    /// Whether the payload is referenced in place in the database.
+    pub fn is_zero_copy(&self) -> bool {
+        self.payload.downcast_ref::<PageSlice>().is_some()
+    }
 
-        Ok(Self {
-            db: Arc::new(db),
-            config,
-            name,
-        })
+    /// Convert the payload into [`ZBytes`] without copying it.
+    pub fn into_zbytes(self) -> ZBytes {
+        ZBytes::from(ZBuf::from(self.payload))
     }
+}
 
-    /// Get the storage name.
-    pub fn name(&self) -> &str {
-        &self.name
+/// Bytes of a database page, kept readable by their read transaction.
+struct PageSlice {
+    guard: OwnedGuard,
+    // Keeps the snapshot the page belongs to alive
+    _read_txn: ReadTransaction,
+}
+
+impl fmt::Debug for PageSlice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageSlice")
+            .field("len", &self.guard.value().len())
+            .finish()
     }
+}
 
-    /// Get the storage configuration.
-    pub fn config(&self) -> &RedbStorageConfig {
-        &self.config
+impl ZSliceBuffer for PageSlice {
+    fn as_slice(&self) -> &[u8] {
+        self.guard.value()
     }
 
-    /// Store a key-value pair with metadata.
-    pub fn put(&self, key: &str, value: StoredValue) -> Result<()> {
-        if self.config.read_only {
-            return Err(RedbBackendError::other("Storage is read-only"));
-        }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 
-        trace!("Putting key: {}", key);
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
 
-        // Use thread-local buffers to avoid allocations
-        KEY_BUFFER.with(|key_buf| {
-            VALUE_BUFFER.with(|_val_buf| {
-                let mut key_buf = key_buf.borrow_mut();
+/// Outcome of a write operation (put or delete) on a storage.
+///
+/// Writes follow last-writer-wins semantics: an update whose timestamp is not
+/// newer than the one already stored for the key is skipped and reported as
+/// [`WriteOutcome::Outdated`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    /// The key did not exist and a new entry was inserted.
+    Inserted,
+    /// An existing entry was replaced by a newer value.
+    Replaced,
+    /// The key was deleted.
+    Deleted,
+    /// The update was older than the stored entry and was skipped.
+    Outdated,
+}
 
-                // Encode key into reusable buffer
-                key_buf.clear();
-                self.encode_key_into(key, &mut key_buf)?;
+/// A put or delete applied by [`RedbStorage::write_batch`].
+#[derive(Debug, Clone)]
+pub enum WriteOp {
+    /// Store `value` under `key`, like [`RedbStorage::put`].
+    Put { key: String, value: StoredValue },
+    /// Delete `key` at `timestamp`, like [`RedbStorage::delete`].
+    Delete { key: String, timestamp: Timestamp },
+}
 
-                // Encode data_info
-                let data_info_bytes = encode_data_info(
-                    value.encoding.clone(),
-                    &value.timestamp,
-                    false, // not deleted
-                )?;
+impl WriteOp {
+    /// Key the operation applies to.
+    pub fn key(&self) -> &str {
+        match self {
+            WriteOp::Put { key, .. } | WriteOp::Delete { key, .. } => key,
+        }
+    }
+}
 
-                let write_txn = self.db.begin_write()?;
-                {
-                    // Store payload
-                    let mut payloads_table = write_txn.open_table(PAYLOADS_TABLE)?;
-                    payloads_table.insert(key_buf.as_slice(), value.payload.as_slice())?;
+/// Payload and data_info of a put, as written to the tables.
+struct EncodedPut<'a> {
+    timestamp: Timestamp,
+    stored_payload: Cow<'a, [u8]>,
+    data_info_bytes: Vec<u8>,
+}
 
-                    // Store data_info
-                    let mut data_info_table = write_txn.open_table(DATA_INFO_TABLE)?;
-                    data_info_table.insert(key_buf.as_slice(), data_info_bytes.as_slice())?;
-                }
-                write_txn.commit()?;
+/// Space used by the live entries of a storage.
+///
+/// Only the latest values are counted: tombstones and the past versions kept in
+/// history mode are not included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageUsage {
+    /// Number of live entries
+    pub entries: u64,
+    /// Total size in bytes of the keys and payloads of live entries
+    pub bytes: u64,
+}
 
-                debug!("Stored key: {}", key);
-                Ok(())
-            })
-        })
+impl StorageUsage {
+    /// Account for a live entry being added.
+    pub(crate) fn add(&mut self, key: &[u8], payload_len: usize) {
+        self.entries += 1;
+        self.bytes += (key.len() + payload_len) as u64;
     }
 
-    /// Retrieve a value by its exact key.
-    pub fn get(&self, key: &str) -> Result<Option<StoredValue>> {
-        trace!("Getting key: {}", key);
+    /// Account for a live entry being removed.
+    fn remove(&mut self, key: &[u8], payload_len: usize) {
+        self.entries = self.entries.saturating_sub(1);
+        self.bytes = self.bytes.saturating_sub((key.len() + payload_len) as u64);
+    }
+}
 
-        // Use thread-local buffer to avoid allocation
-        KEY_BUFFER.with(|key_buf| {
-            let mut key_buf = key_buf.borrow_mut();
-            key_buf.clear();
-            self.encode_key_into(key, &mut key_buf)?;
+/// Kind of inconsistency found by [`RedbStorage::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityIssueKind {
+    /// A payload has no data_info record.
+    OrphanedPayload,
+    /// The data_info record of a live entry has no payload.
+    OrphanedDataInfo,
+    /// A data_info record cannot be decoded.
+    UndecodableDataInfo,
+    /// A payload does not match the checksum recorded in its data_info.
+    ChecksumMismatch,
+}
 
-            let read_txn = self.db.begin_read()?;
-            let payloads_table = read_txn.open_table(PAYLOADS_TABLE)?;
-            let data_info_table = read_txn.open_table(DATA_INFO_TABLE)?;
+/// An inconsistent entry found by [`RedbStorage::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityIssue {
+    /// Key of the entry (lossily decoded if it is not valid UTF-8)
+    pub key: String,
+    /// Time of the version, for past versions kept in history mode
+    pub version: Option<NTP64>,
+    /// What is wrong with the entry
+    pub kind: IntegrityIssueKind,
+}
 
-            // Try to get both payload and data_info
-            let payload_result = payloads_table.get(key_buf.as_slice())?;
-            let data_info_result = data_info_table.get(key_buf.as_slice())?;
+/// Result of an integrity check of a storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Number of entries checked, including tombstones and past versions
+    pub checked: u64,
+    /// Inconsistent entries
+    pub issues: Vec<IntegrityIssue>,
+}
 
-            match (payload_result, data_info_result) {
-                (Some(payload_guard), Some(info_guard)) => {
-                    let payload_bytes = payload_guard.value();
-                    let info_bytes = info_guard.value();
+impl VerifyReport {
+    /// Whether no inconsistency was found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
 
-                    let (encoding, timestamp, deleted) = decode_data_info(info_bytes)?;
+/// What [`RedbStorage::repair`] does with the records of inconsistent entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RepairMode {
+    /// Move the records to the `quarantine` table, keyed by their table name and key.
+    #[default]
+    Quarantine,
+    /// Delete the records.
+    Remove,
+}
 
-                    if deleted {
-                        // This is a tombstone, treat as not found
-                        trace!("Key found but marked as deleted: {}", key);
-                        Ok(None)
-                    } else {
-                        let stored_value = StoredValue::new(
-                            payload_bytes.to_vec(),
-                            timestamp,
-                            encoding,
-                        );
-                        debug!("Found key: {}", key);
-                        Ok(Some(stored_value))
-                    }
-                }
-                (Some(_), None) => {
-                    warn!("Payload exists but data_info missing for key: {} - possible database corruption", key);
-                    Ok(None)
-                }
-                (None, Some(_)) => {
-                    // Data info exists but no payload - treat as deleted or corrupted
-                    trace!("Data info exists but no payload for key: {}", key);
-                    Ok(None)
-                }
-                (None, None) => {
-                    trace!("Key not found: {}", key);
-                    Ok(None)
+/// Check the tables of entries, adding inconsistent entries to `issues` with their
+/// raw key. Returns the number of entries checked.
+fn find_integrity_issues<T>(
+    entries: &EntryTables<T>,
+    issues: &mut Vec<(Vec<u8>, IntegrityIssueKind)>,
+) -> Result<u64>
+where
+    T: ReadableTable<&'static [u8], &'static [u8]>,
+{
+    let mut checked = 0;
+    for item in entries.iter()? {
+        let (key_bytes, value) = item?;
+        checked += 1;
+        let info = entries.info(value.value()).and_then(decode_data_info).ok();
+        let kind = match info {
+            None => Some(IntegrityIssueKind::UndecodableDataInfo),
+            Some(info) => match entries.payload(key_bytes.value(), value.value())? {
+                None if !info.deleted => Some(IntegrityIssueKind::OrphanedDataInfo),
+                Some(payload) if !info.verify_checksum(payload.value()) => {
+                    Some(IntegrityIssueKind::ChecksumMismatch)
                 }
-            }
-        })
+                _ => None,
+            },
+        };
+        if let Some(kind) = kind {
+            issues.push((key_bytes.value().to_vec(), kind));
+        }
     }
 
-    /// Delete a key-value pair.
-    pub fn delete(&self, key: &str) -> Result<()> {
-        if self.config.read_only {
-            return Err(RedbBackendError::other("Storage is read-only"));
+    // Payloads can only be orphaned when they have their own table
+    if let EntryTables::TwoTables {
+        payloads,
+        data_info,
+    } = entries
+    {
+        for item in payloads.iter()? {
+            let (key_bytes, _) = item?;
+            if data_info.get(key_bytes.value())?.is_none() {
+                checked += 1;
+                issues.push((
+                    key_bytes.value().to_vec(),
+                    IntegrityIssueKind::OrphanedPayload,
+                ));
+            }
         }
+    }
 
-        trace!("Deleting key: {}", key);
+    Ok(checked)
+}
 
-        KEY_BUFFER.with(|key_buf| {
-            let mut key_buf = key_buf.borrow_mut();
-            key_buf.clear();
-            self.encode_key_into(key, &mut key_buf)?;
+/// Count the usage of the live entries of a storage.
+fn count_usage<T>(entries: &EntryTables<T>) -> Result<StorageUsage>
+where
+    T: ReadableTable<&'static [u8], &'static [u8]>,
+{
+    let mut usage = StorageUsage::default();
+    for item in entries.iter()? {
+        let (key_bytes, value) = item?;
+        let info = decode_data_info(entries.info(value.value())?)?;
+        if !info.deleted
+            && let Some(payload) = entries.payload(key_bytes.value(), value.value())?
+        {
+            usage.add(key_bytes.value(), payload.value().len());
+        }
+    }
+    Ok(usage)
+}
 
-            let write_txn = self.db.begin_write()?;
-            {
-                // Delete from both tables
-                let mut payloads_table = write_txn.open_table(PAYLOADS_TABLE)?;
-                let mut data_info_table = write_txn.open_table(DATA_INFO_TABLE)?;
+/// Read the usage counters, or `None` if they were never initialized.
+pub(crate) fn read_usage<T>(usage_table: &T) -> Result<Option<StorageUsage>>
+where
+    T: ReadableTable<&'static str, u64>,
+{
+    let entries = usage_table.get(USAGE_ENTRIES)?.map(|v| v.value());
+    let bytes = usage_table.get(USAGE_BYTES)?.map(|v| v.value());
+    Ok(entries
+        .zip(bytes)
+        .map(|(entries, bytes)| StorageUsage { entries, bytes }))
+}
 
-                payloads_table.remove(key_buf.as_slice())?;
-                data_info_table.remove(key_buf.as_slice())?;
-            }
-            write_txn.commit()?;
+/// Write the usage counters.
+pub(crate) fn write_usage(
+    usage_table: &mut Table<&'static str, u64>,
+    usage: &StorageUsage,
+) -> Result<()> {
+    usage_table.insert(USAGE_ENTRIES, usage.entries)?;
+    usage_table.insert(USAGE_BYTES, usage.bytes)?;
+    Ok(())
+}
 
-            debug!("Deleted key: {}", key);
-            Ok(())
-        })
+/// Read the timestamp and tombstone flag currently stored for a key, if any.
+fn stored_timestamp<T>(entries: &EntryTables<T>, key: &[u8]) -> Result<Option<(Timestamp, bool)>>
+where
+    T: ReadableTable<&'static [u8], &'static [u8]>,
+{
+    match entries.get(key)? {
+        Some(value) => {
+            let info = decode_data_info(entries.info(value.value())?)?;
+            Ok(Some((info.timestamp, info.deleted)))
+        }
+        None => Ok(None),
     }
+}
 
-    /// Retrieve all key-value pairs from the storage.
-    pub fn get_all(&self) -> Result<Vec<(String, StoredValue)>> {
-        trace!("Getting all entries");
+/// Copy the records of a table, if it exists, into the same table of another
+/// database.
+fn copy_table<K, V>(
+    read_txn: &ReadTransaction,
+    write_txn: &WriteTransaction,
+    definition: TableDefinition<K, V>,
+) -> Result<()>
+where
+    K: Key + 'static,
+    V: Value + 'static,
+{
+    let source = match read_txn.open_table(definition) {
+        Ok(table) => table,
+        Err(TableError::TableDoesNotExist(_)) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut target = write_txn.open_table(definition)?;
+    for item in source.iter()? {
+        let (key, value) = item?;
+        target.insert(key.value(), value.value())?;
+    }
+    Ok(())
+}
 
-        let read_txn = self.db.begin_read()?;
-        let payloads_table = read_txn.open_table(PAYLOADS_TABLE)?;
-        let data_info_table = read_txn.open_table(DATA_INFO_TABLE)?;
+/// The main storage implementation using redb.
+pub struct RedbStorage {
+    /// The redb database instance
+    db: Arc<Database>,
 
-        let mut results = Vec::new();
+    /// Storage configuration
+    config: RedbStorageConfig,
 
-        // Iterate over data_info table (it's the authoritative source for what exists)
-        for item in data_info_table.iter()? {
-            let (key_bytes, info_bytes) = item?;
-            let key = self.decode_key(key_bytes.value())?;
+    /// Storage name for logging
+    name: String,
 
-            let (encoding, timestamp, deleted) = decode_data_info(info_bytes.value())?;
+    /// Cipher sealing entries, when encryption at rest is enabled
+    cipher: Option<Cipher>,
 
-            if !deleted {
-                // Get the payload
-                if let Some(payload_guard) = payloads_table.get(key_bytes.value())? {
-                    let payload_bytes = payload_guard.value();
-                    let stored_value =
-                        StoredValue::new(payload_bytes.to_vec(), timestamp, encoding);
-                    results.push((key, stored_value));
-                } else {
-                    warn!(
-                        "Data info exists but no payload for key: {} - skipping",
-                        key
-                    );
-                }
-            }
-        }
+    /// Key expression the keys must be included in, when configured
+    scope: Option<KeyScope>,
 
-        debug!("Retrieved {} entries", results.len());
-        Ok(results)
+    /// Names of the tables of the storage, derived from its `table_name`
+    tables: TableNames,
+
+    /// Set once the storage is destroyed, after which its handles fail
+    closed: AtomicBool,
+}
+
+/// Open, or create if `create_db` is set, the database file of a storage.
+pub(crate) fn open_database(path: &Path, config: &RedbStorageConfig) -> Result<Database> {
+    let mut builder = Builder::new();
+    if let Some(cache_size) = config.cache_size {
+        builder.set_cache_size(cache_size);
     }
 
-    /// Retrieve all key-value pairs matching a given prefix.
-    pub fn get_by_prefix(&self, prefix: &str) -> Result<Vec<(String, StoredValue)>> {
-        trace!("Getting entries by prefix: {}", prefix);
+    if config.create_db {
+        Ok(builder.create(path)?)
+    } else if path.exists() {
+        Ok(builder.open(path)?)
+    } else {
+        Err(RedbBackendError::database_not_found(
+            path.display().to_string(),
+        ))
+    }
+}
 
-        let read_txn = self.db.begin_read()?;
-        let payloads_table = read_txn.open_table(PAYLOADS_TABLE)?;
-        let data_info_table = read_txn.open_table(DATA_INFO_TABLE)?;
+impl RedbStorage {
+    /// Create a new RedbStorage instance.
+    pub fn new<P: AsRef<Path>>(path: P, config: RedbStorageConfig, name: String) -> Result<Self> {
+        info!("Creating redb storage at: {:?}", path.as_ref());
 
-        let mut results = Vec::new();
+        let db = open_database(path.as_ref(), &config)?;
+        Self::from_database(Arc::new(db), config, name)
+    }
 
-        for item in data_info_table.iter()? {
-            let (key_bytes, info_bytes) = item?;
-            let key = self.decode_key(key_bytes.value())?;
+    /// Create a RedbStorage instance in an open database, which other storages
+    /// with different table names may share.
+    ///
+    /// The `cache_size` and `create_db` options of `config` only apply when opening
+    /// the database, so they are ignored.
+    pub fn from_database(
+        db: Arc<Database>,
+        config: RedbStorageConfig,
+        name: String,
+    ) -> Result<Self> {
+        compression::validate(config.compression)?;
+        let cipher = config
+            .encryption
+            .as_ref()
+            .map(Cipher::from_config)
+            .transpose()?;
+        let scope = KeyScope::from_config(&config)?;
+        let tables = TableNames::new(&config.table_name)?;
+        let storage = Self {
+            db,
+            config,
+            name,
+            cipher,
+            scope,
+            tables,
+            closed: AtomicBool::new(false),
+        };
 
-            if key.starts_with(prefix) {
-                let (encoding, timestamp, deleted) = decode_data_info(info_bytes.value())?;
+        // Storages that are up to date are opened from a read snapshot, without
+        // taking the write lock of the database
+        let (required, recordable) = storage.pending_changes(&storage.db.begin_read()?)?;
+        if required.is_empty() && (!recordable || storage.config.read_only) {
+            info!("Redb storage created successfully");
+            return Ok(storage);
+        }
+        if storage.config.read_only {
+            return Err(RedbBackendError::config(format!(
+                "Storage '{}' is read-only, but opening it requires to {}",
+                storage.name,
+                required.join(", ")
+            )));
+        }
 
-                if !deleted && let Some(payload_guard) = payloads_table.get(key_bytes.value())? {
-                    let payload_bytes = payload_guard.value();
-                    let stored_value =
-                        StoredValue::new(payload_bytes.to_vec(), timestamp, encoding);
-                    results.push((key, stored_value));
-                }
-            }
+        // A storage recorded under another table name would open empty tables
+        let write_txn = storage.db.begin_write()?;
+        let fresh = !write_txn
+            .list_tables()?
+            .any(|table| storage.tables.contains(table.name()));
+        if fresh && let Some(table_name) = schema::find_table_name(&write_txn, &storage.name)? {
+            return Err(RedbBackendError::config_mismatch(format!(
+                "Storage '{}' is stored with table name '{}', but is opened with table name '{}'",
+                storage.name, table_name, storage.config.table_name
+            )));
         }
 
-        debug!(
-            "Retrieved {} entries with prefix '{}'",
-            results.len(),
-            prefix
-        );
-        Ok(results)
+        // Bring the schema, layout and keys up to date, then create the tables that
+        // don't exist
+        let layout = storage.config.layout;
+        schema::migrate(&write_txn, &storage.tables, layout)?;
+        schema::convert_layout(&write_txn, &storage.tables, layout)?;
+        storage.check_key_scope(&write_txn)?;
+        schema::record_storage_name(&write_txn, &storage.tables, &storage.name)?;
+        EntryTables::open(&write_txn, &storage.tables, layout, false)?;
+        if storage.config.keep_history {
+            EntryTables::open(&write_txn, &storage.tables, layout, true)?;
+        }
+        let indexed = write_txn
+            .list_tables()?
+            .any(|table| table.name() == storage.tables.eviction_index().name());
+        if indexed != storage.config.evicts_oldest() {
+            storage.rebuild_eviction_index(&write_txn)?;
+        }
+        write_txn.commit()?;
+
+        info!("Redb storage created successfully");
+
+        Ok(storage)
+    }
+
+    /// Find from a read snapshot what opening the storage has to write: the changes
+    /// required to use it, described for errors, and whether there is metadata to
+    /// record or an eviction index to update, which read-only storages do without.
+    fn pending_changes(&self, read_txn: &ReadTransaction) -> Result<(Vec<String>, bool)> {
+        let existing: Vec<String> = read_txn
+            .list_tables()?
+            .map(|table| table.name().to_string())
+            .collect();
+        let exists = |name: &str| existing.iter().any(|table| table == name);
+        if !existing.iter().any(|table| self.tables.contains(table)) {
+            return Ok((vec!["create its tables".to_string()], true));
+        }
+
+        let metadata_table = match read_txn.open_table(self.tables.metadata()) {
+            Ok(table) => Some(table),
+            Err(TableError::TableDoesNotExist(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let current_layout = schema::inspect(metadata_table.as_ref())?;
+        let version = match &metadata_table {
+            Some(metadata_table) => schema::read_schema_version(metadata_table)?.unwrap_or(0),
+            None => 0,
+        };
+        let Some(metadata_table) = metadata_table.filter(|_| version == schema::SCHEMA_VERSION)
+        else {
+            // The rest of the metadata is only recorded by the latest schema
+            return Ok((
+                vec![format!(
+                    "migrate its schema from version {} to {}",
+                    version,
+                    schema::SCHEMA_VERSION
+                )],
+                true,
+            ));
+        };
+
+        let mut required = Vec::new();
+        let layout = self.config.layout;
+        if current_layout != layout {
+            required.push(format!(
+                "convert its entries from the {:?} to the {:?} layout",
+                current_layout, layout
+            ));
+        } else {
+            for history in [false, self.config.keep_history] {
+                let missing = layout::definitions(&self.tables, layout, history)
+                    .iter()
+                    .any(|definition| !exists(definition.name()));
+                if missing {
+                    required.push("create its tables".to_string());
+                    break;
+                }
+            }
+        }
+
+        let current = KeyScope::record(self.scope.as_ref());
+        let recorded = schema::read_key_scope(&metadata_table)?;
+        match &recorded {
+            Some(recorded) if *recorded != current => {
+                required.push(format!("rewrite its keys from {} to {}", recorded, current))
+            }
+            _ => {}
+        }
+
+        let recordable = recorded.is_none()
+            || schema::read_storage_name(&metadata_table)?.as_deref() != Some(self.name.as_str())
+            || exists(self.tables.eviction_index().name()) != self.config.evicts_oldest();
+        Ok((required, recordable))
+    }
+
+    /// Check that the keys are stored with the key scope of the configuration,
+    /// rewriting them if `migrate_keys` is set, then record it.
+    ///
+    /// Files written before key scopes were recorded are assumed to match.
+    fn check_key_scope(&self, write_txn: &WriteTransaction) -> Result<()> {
+        let current = KeyScope::record(self.scope.as_ref());
+        let recorded = schema::read_key_scope(&write_txn.open_table(self.tables.metadata())?)?;
+        match recorded {
+            Some(recorded) if recorded == current => return Ok(()),
+            Some(recorded) if !self.config.migrate_keys => {
+                return Err(RedbBackendError::config_mismatch(format!(
+                    "Storage '{}' stores its keys with {}, but is opened with {}; \
+                     set migrate_keys to rewrite them",
+                    self.name, recorded, current
+                )));
+            }
+            Some(recorded) => {
+                let from = KeyScope::from_record(&recorded)?;
+                let rewritten = self.rewrite_keys(write_txn, from.as_ref())?;
+                info!(
+                    "Rewrote {} keys of storage '{}' from {} to {}",
+                    rewritten, self.name, recorded, current
+                );
+            }
+            None => {}
+        }
+        schema::record_key_scope(write_txn, &self.tables, &current)
+    }
+
+    /// Rewrite the stored keys of the entries and past versions, stored with the
+    /// scope `from`, with the scope of the storage, returning the number of records
+    /// rewritten.
+    ///
+    /// Encrypted payloads are bound to their stored key, so they are sealed again.
+    /// Keys outside the new key expression fail the rewrite. The records are copied
+    /// one at a time into staging tables, which then replace the tables, so the
+    /// rewrite does not hold the storage in memory. Quarantined records are left as
+    /// they are.
+    fn rewrite_keys(&self, write_txn: &WriteTransaction, from: Option<&KeyScope>) -> Result<u64> {
+        let layout = self.config.layout;
+        let staging = self.tables.staging();
+        let mut rewritten = 0;
+        for history in [false, true] {
+            if !layout::tables_exist(write_txn, &self.tables, layout, history)? {
+                continue;
+            }
+            {
+                let source = EntryTables::open(write_txn, &self.tables, layout, history)?;
+                let mut target = EntryTables::open(write_txn, &staging, layout, history)?;
+                for item in source.iter()? {
+                    let (stored_key, value) = item?;
+                    let stored_key = stored_key.value();
+                    let info_bytes = source.info(value.value())?;
+                    let payload = source.payload(stored_key, value.value())?;
+                    let payload = payload.as_ref().map(PayloadRef::value);
+
+                    let split = if history {
+                        stored_key
+                            .len()
+                            .checked_sub(HISTORY_SUFFIX_LEN)
+                            .ok_or_else(|| {
+                                RedbBackendError::key_encoding("History key without a timestamp")
+                            })?
+                    } else {
+                        stored_key.len()
+                    };
+                    let (old_key, suffix) = stored_key.split_at(split);
+                    let key = String::from_utf8(old_key.to_vec()).map_err(|e| {
+                        RedbBackendError::serialization(format!("Invalid UTF-8 in key: {}", e))
+                    })?;
+                    let key = match from {
+                        Some(scope) => scope.restore(key),
+                        None => key,
+                    };
+                    let mut new_key = Vec::with_capacity(stored_key.len());
+                    self.encode_key_into(&key, &mut new_key)?;
+
+                    let info = decode_data_info(info_bytes)?;
+                    if !info.deleted
+                        && info.key_id != KEY_ID_NONE
+                        && let Some(payload) = payload
+                    {
+                        let value = self.stored_value(info, old_key, payload)?;
+                        let encoded = self.encode_put(&new_key, &value)?;
+                        new_key.extend_from_slice(suffix);
+                        target.insert(
+                            &new_key,
+                            &encoded.data_info_bytes,
+                            Some(&encoded.stored_payload),
+                        )?;
+                    } else {
+                        new_key.extend_from_slice(suffix);
+                        target.insert(&new_key, info_bytes, payload)?;
+                    }
+                    rewritten += 1;
+                }
+            }
+            layout::delete_tables(write_txn, &self.tables, layout, history)?;
+            layout::rename_tables(write_txn, &staging, &self.tables, layout, history)?;
+        }
+
+        // The usage counts the size of the keys, and the eviction index holds them
+        let usage = count_usage(&EntryTables::open(write_txn, &self.tables, layout, false)?)?;
+        write_usage(&mut write_txn.open_table(self.tables.usage())?, &usage)?;
+        self.rebuild_eviction_index(write_txn)?;
+        Ok(rewritten)
+    }
+
+    /// Open the eviction index, if the oldest entries are evicted to meet a quota.
+    fn open_eviction_index<'txn>(
+        &self,
+        write_txn: &'txn WriteTransaction,
+    ) -> Result<Option<EvictionIndex<'txn>>> {
+        if !self.config.evicts_oldest() {
+            return Ok(None);
+        }
+        Ok(Some(write_txn.open_table(self.tables.eviction_index())?))
+    }
+
+    /// Index the live entries in a new eviction index, or delete the index if the
+    /// oldest entries are not evicted.
+    fn rebuild_eviction_index(&self, write_txn: &WriteTransaction) -> Result<()> {
+        write_txn.delete_table(self.tables.eviction_index())?;
+        let Some(mut index) = self.open_eviction_index(write_txn)? else {
+            return Ok(());
+        };
+        let entries = EntryTables::open(write_txn, &self.tables, self.config.layout, false)?;
+        for item in entries.iter()? {
+            let (key_bytes, value) = item?;
+            let info = decode_data_info(entries.info(value.value())?)?;
+            if !info.deleted {
+                index.insert(
+                    eviction_key(key_bytes.value(), &info.timestamp).as_slice(),
+                    (),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The database holding the storage, possibly shared with other storages.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.db
+    }
+
+    /// Delete the tables of the storage, leaving those of the other storages of its
+    /// database.
+    pub(crate) fn delete_tables(&self) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        let owned: Vec<_> = write_txn
+            .list_tables()?
+            .filter(|table| self.tables.contains(table.name()))
+            .collect();
+        for table in owned {
+            write_txn.delete_table(table)?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    /// Whether the database of the storage holds tables of other storages, whether
+    /// they are open or not.
+    pub(crate) fn shares_database(&self) -> Result<bool> {
+        let read_txn = self.begin_read()?;
+        let other_table = read_txn
+            .list_tables()?
+            .any(|table| TableNames::table_name_of(table.name()) != self.config.table_name);
+        Ok(other_table || read_txn.list_multimap_tables()?.next().is_some())
+    }
+
+    /// Copy the tables of the storage into `write_txn` of another database, from a
+    /// single read snapshot.
+    pub(crate) fn copy_tables(&self, write_txn: &WriteTransaction) -> Result<()> {
+        let read_txn = self.begin_read()?;
+        copy_table(&read_txn, write_txn, self.tables.payloads())?;
+        copy_table(&read_txn, write_txn, self.tables.data_info())?;
+        copy_table(&read_txn, write_txn, self.tables.history_payloads())?;
+        copy_table(&read_txn, write_txn, self.tables.history_data_info())?;
+        copy_table(&read_txn, write_txn, self.tables.records())?;
+        copy_table(&read_txn, write_txn, self.tables.history_records())?;
+        copy_table(&read_txn, write_txn, self.tables.usage())?;
+        copy_table(&read_txn, write_txn, self.tables.quarantine())?;
+        copy_table(&read_txn, write_txn, self.tables.metadata())?;
+        copy_table(&read_txn, write_txn, self.tables.eviction_index())?;
+        Ok(())
+    }
+
+    /// Get the storage name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the storage configuration.
+    pub fn config(&self) -> &RedbStorageConfig {
+        &self.config
+    }
+
+    /// Mark the storage as destroyed, or no longer destroyed if restoring it fails.
+    ///
+    /// The handles of a destroyed storage fail with
+    /// [`StorageNotFound`](RedbBackendError::StorageNotFound), instead of reaching its
+    /// deleted data.
+    pub(crate) fn set_closed(&self, closed: bool) {
+        self.closed.store(closed, Ordering::Release);
+    }
+
+    /// Fail if the storage has been destroyed.
+    fn check_open(&self) -> Result<()> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(RedbBackendError::storage_not_found(self.name.as_str()));
+        }
+        Ok(())
+    }
+
+    /// Begin a read transaction, unless the storage has been destroyed.
+    fn begin_read(&self) -> Result<ReadTransaction> {
+        self.check_open()?;
+        Ok(self.db.begin_read()?)
+    }
+
+    /// Begin a write transaction using the durability configured for this storage.
+    ///
+    /// When `fsync` is disabled, commits use [`Durability::Eventual`] so they are
+    /// written without waiting for the data to be flushed to disk.
+    fn begin_write(&self) -> Result<WriteTransaction> {
+        self.check_open()?;
+        let mut write_txn = self.db.begin_write()?;
+        if !self.config.fsync {
+            write_txn.set_durability(Durability::Eventual);
+        }
+        Ok(write_txn)
+    }
+
+    /// Store a key-value pair with metadata.
+    ///
+    /// The write is skipped if the storage already holds an entry or a tombstone for
+    /// `key` with a timestamp greater than or equal to `value.timestamp`.
+    ///
+    /// When `keep_history` is enabled, every sample is also recorded as a version of
+    /// `key`, including samples older than the latest value. Such samples are reported
+    /// as [`WriteOutcome::Inserted`]; only a sample whose version is already recorded
+    /// is [`WriteOutcome::Outdated`].
+    ///
+    /// If the write would take the storage over `max_entries` or `max_bytes`, it fails
+    /// with [`RedbBackendError::QuotaExceeded`] or evicts the oldest entries, depending
+    /// on the configured [`QuotaPolicy`](crate::config::QuotaPolicy).
+    pub fn put(&self, key: &str, value: StoredValue) -> Result<WriteOutcome> {
+        if self.config.read_only {
+            return Err(RedbBackendError::other("Storage is read-only"));
+        }
+
+        trace!("Putting key: {}", key);
+
+        // Use thread-local buffers to avoid allocations
+        KEY_BUFFER.with(|key_buf| {
+            VALUE_BUFFER.with(|_val_buf| {
+                let mut key_buf = key_buf.borrow_mut();
+
+                // Encode key into reusable buffer
+                key_buf.clear();
+                self.encode_key_into(key, &mut key_buf)?;
+
+                let encoded = self.encode_put(&key_buf, &value)?;
+
+                let write_txn = self.begin_write()?;
+                let outcome = {
+                    let mut entries =
+                        EntryTables::open(&write_txn, &self.tables, self.config.layout, false)?;
+                    let mut index = self.open_eviction_index(&write_txn)?;
+                    let mut usage_table = write_txn.open_table(self.tables.usage())?;
+                    let mut usage = read_usage(&usage_table)?.unwrap_or_default();
+
+                    let outcome = self.apply_put(
+                        &write_txn,
+                        &mut entries,
+                        &mut index,
+                        &mut usage,
+                        key_buf.as_slice(),
+                        &encoded,
+                    )?;
+                    write_usage(&mut usage_table, &usage)?;
+                    outcome
+                };
+
+                if outcome == WriteOutcome::Outdated {
+                    write_txn.abort()?;
+                    debug!(
+                        "Skipped outdated put for key: {} (timestamp: {})",
+                        key, value.timestamp
+                    );
+                } else {
+                    write_txn.commit()?;
+                    debug!("Stored key: {}", key);
+                }
+
+                Ok(outcome)
+            })
+        })
+    }
+
+    /// Compress and, if configured, encrypt the payload of `value`.
+    fn encode_put<'a>(&self, key: &[u8], value: &'a StoredValue) -> Result<EncodedPut<'a>> {
+        // Compress the payload and record the codec in data_info
+        let (codec, stored_payload) = compression::compress(
+            &value.payload,
+            self.config.compression,
+            self.config.compression_min_size,
+        )?;
+        let (stored_payload, data_info) = match &self.cipher {
+            Some(cipher) => {
+                let sealed_payload = cipher.seal(Domain::Payload, key, &stored_payload)?;
+                let sealed_encoding =
+                    cipher.seal(Domain::Encoding, key, &encode_encoding(&value.encoding))?;
+                let info = DataInfo::encrypted(
+                    value.timestamp,
+                    codec,
+                    cipher.active_key_id(),
+                    sealed_encoding,
+                );
+                (Cow::Owned(sealed_payload), info)
+            }
+            None => (
+                stored_payload,
+                DataInfo::new(value.encoding.clone(), value.timestamp, codec),
+            ),
+        };
+        let data_info_bytes = encode_data_info(&data_info.with_checksum(&stored_payload))?;
+        Ok(EncodedPut {
+            timestamp: value.timestamp,
+            stored_payload,
+            data_info_bytes,
+        })
+    }
+
+    /// Apply an encoded put within `write_txn`, updating `index` and `usage`.
+    ///
+    /// Dropping the transaction on error aborts the write.
+    fn apply_put(
+        &self,
+        write_txn: &WriteTransaction,
+        entries: &mut EntryTables<Table<&'static [u8], &'static [u8]>>,
+        index: &mut Option<EvictionIndex>,
+        usage: &mut StorageUsage,
+        key: &[u8],
+        encoded: &EncodedPut,
+    ) -> Result<WriteOutcome> {
+        let EncodedPut {
+            timestamp,
+            stored_payload,
+            data_info_bytes,
+        } = encoded;
+
+        // Last-writer-wins: compare against the stored timestamp in the same transaction
+        let latest = match stored_timestamp(entries, key)? {
+            Some((stored, _)) if stored >= *timestamp => WriteOutcome::Outdated,
+            stored => {
+                let previous_usage = *usage;
+
+                // Store payload and data_info, accounting for the payload replaced
+                let previous_len = entries.insert(key, data_info_bytes, Some(stored_payload))?;
+                if let Some(previous_len) = previous_len {
+                    usage.remove(key, previous_len);
+                }
+                usage.add(key, stored_payload.len());
+                if let Some(index) = index {
+                    if let Some((previous, false)) = stored {
+                        index.remove(eviction_key(key, &previous).as_slice())?;
+                    }
+                    index.insert(eviction_key(key, timestamp).as_slice(), ())?;
+                }
+
+                // Only writes that grow the storage are held to the quota
+                let grew =
+                    usage.entries > previous_usage.entries || usage.bytes > previous_usage.bytes;
+                if grew && self.exceeds_quota(usage) {
+                    self.enforce_quota(entries, index, usage, key)?;
+                }
+
+                match stored {
+                    Some((_, false)) => WriteOutcome::Replaced,
+                    _ => WriteOutcome::Inserted,
+                }
+            }
+        };
+
+        let recorded = self.config.keep_history
+            && self.record_version(write_txn, key, timestamp, stored_payload, data_info_bytes)?;
+
+        Ok(match latest {
+            WriteOutcome::Outdated if recorded => WriteOutcome::Inserted,
+            outcome => outcome,
+        })
+    }
+
+    /// Record a version of `key` in the history tables and enforce `max_versions`.
+    ///
+    /// Returns false if this exact version (same timestamp) was already recorded.
+    fn record_version(
+        &self,
+        write_txn: &WriteTransaction,
+        key: &[u8],
+        timestamp: &Timestamp,
+        stored_payload: &[u8],
+        data_info_bytes: &[u8],
+    ) -> Result<bool> {
+        let version_key = history_key(key, timestamp);
+
+        let mut history = EntryTables::open(write_txn, &self.tables, self.config.layout, true)?;
+        if history.get(version_key.as_slice())?.is_some() {
+            return Ok(false);
+        }
+
+        history.insert(
+            version_key.as_slice(),
+            data_info_bytes,
+            Some(stored_payload),
+        )?;
+
+        if let Some(max_versions) = self.config.max_versions {
+            let (start, end) = history_key_range(key, ..);
+            let versions = history
+                .range::<&[u8]>(start.as_slice()..end.as_slice())?
+                .count();
+
+            if versions > max_versions {
+                // Versions are sorted by timestamp, so the oldest come first
+                let expired: Vec<Vec<u8>> = history
+                    .range::<&[u8]>(start.as_slice()..end.as_slice())?
+                    .take(versions - max_versions)
+                    .map(|entry| entry.map(|(k, _)| k.value().to_vec()))
+                    .collect::<std::result::Result<_, _>>()?;
+
+                for version_key in &expired {
+                    history.remove(version_key.as_slice())?;
+                }
+
+                trace!("Dropped {} old version(s) of key", expired.len());
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Retrieve the versions of `key` whose timestamp falls within `time_range`,
+    /// oldest first.
+    ///
+    /// When `keep_history` is disabled only the latest value is available, so at
+    /// most one version is returned.
+    pub fn get_history<R>(&self, key: &str, time_range: R) -> Result<Vec<StoredValue>>
+    where
+        R: RangeBounds<NTP64>,
+    {
+        trace!("Getting history of key: {}", key);
+
+        if !self.config.keep_history {
+            return Ok(self
+                .get(key)?
+                .filter(|value| time_range.contains(value.timestamp.get_time()))
+                .into_iter()
+                .collect());
+        }
+
+        let mut key_bytes = Vec::with_capacity(key.len());
+        self.encode_key_into(key, &mut key_bytes)?;
+        let (start, end) = history_key_range(&key_bytes, time_range);
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        let read_txn = self.begin_read()?;
+        let history = EntryTables::open_read(&read_txn, &self.tables, self.config.layout, true)?;
+
+        let mut versions = Vec::new();
+        for entry in history.range::<&[u8]>(start.as_slice()..end.as_slice())? {
+            let (version_key, value) = entry?;
+            let info = decode_data_info(history.info(value.value())?)?;
+
+            match history.payload(version_key.value(), value.value())? {
+                Some(payload) => {
+                    versions.push(self.stored_value(info, &key_bytes, payload.value())?)
+                }
+                None => warn!(
+                    "Data inconsistency: version of key '{}' at {} has data_info but no payload",
+                    key, info.timestamp
+                ),
+            }
+        }
+
+        debug!("Found {} version(s) of key: {}", versions.len(), key);
+        Ok(versions)
+    }
+
+    /// Retrieve a value by its exact key.
+    pub fn get(&self, key: &str) -> Result<Option<StoredValue>> {
+        trace!("Getting key: {}", key);
+
+        // Use thread-local buffer to avoid allocation
+        KEY_BUFFER.with(|key_buf| {
+            let mut key_buf = key_buf.borrow_mut();
+            key_buf.clear();
+            self.encode_key_into(key, &mut key_buf)?;
+
+            let read_txn = self.begin_read()?;
+            let entries = EntryTables::open_read(&read_txn, &self.tables, self.config.layout, false)?;
+
+            let Some(value) = entries.get(key_buf.as_slice())? else {
+                trace!("Key not found: {}", key);
+                return Ok(None);
+            };
+            let info = decode_data_info(entries.info(value.value())?)?;
+
+            if info.deleted {
+                // This is a tombstone, treat as not found
+                trace!("Key has been deleted (tombstone): {}", key);
+                return Ok(None);
+            }
+            if self.is_expired(key, &info.timestamp, self.expiry_now()) {
+                trace!("Key found but expired: {}", key);
+                return Ok(None);
+            }
+
+            match entries.payload(key_buf.as_slice(), value.value())? {
+                Some(payload) => {
+                    let stored_value = self.stored_value(info, &key_buf, payload.value())?;
+                    debug!("Found key: {}", key);
+                    Ok(Some(stored_value))
+                }
+                None => {
+                    // Data info exists but no payload - treat as corrupted
+                    warn!(
+                        "Data info exists but no payload for key: {} - possible database corruption, run verify() to check the storage",
+                        key
+                    );
+                    Ok(None)
+                }
+            }
+        })
+    }
+
+    /// Retrieve a value by its exact key, sharing its payload instead of copying it.
+    ///
+    /// Unlike [`get`](Self::get), a payload stored uncompressed and unencrypted is
+    /// not copied out of the database; see [`ValueView`].
+    pub fn get_view(&self, key: &str) -> Result<Option<ValueView>> {
+        trace!("Getting view of key: {}", key);
+
+        let mut key_bytes = Vec::with_capacity(key.len());
+        self.encode_key_into(key, &mut key_bytes)?;
+
+        let read_txn = self.begin_read()?;
+        let entries = EntryTables::open_read(&read_txn, &self.tables, self.config.layout, false)?;
+
+        let Some(value) = entries.owned_get(&key_bytes)? else {
+            return Ok(None);
+        };
+        let info = decode_data_info(entries.info(value.value())?)?;
+        if info.deleted || self.is_expired(key, &info.timestamp, self.expiry_now()) {
+            return Ok(None);
+        }
+
+        let Some((guard, offset)) = entries.payload_guard(&key_bytes, value)? else {
+            warn!(
+                "Data info exists but no payload for key: {} - possible database corruption, run verify() to check the storage",
+                key
+            );
+            return Ok(None);
+        };
+
+        // Payloads that need decoding get their own buffer
+        if info.key_id != KEY_ID_NONE || info.codec != CODEC_NONE {
+            let value = self.stored_value(info, &key_bytes, &guard.value()[offset..])?;
+            return Ok(Some(ValueView {
+                payload: ZSlice::from(value.payload),
+                timestamp: value.timestamp,
+                encoding: value.encoding,
+            }));
+        }
+
+        if !info.verify_checksum(&guard.value()[offset..]) {
+            return Err(RedbBackendError::corruption(format!(
+                "Checksum mismatch for key '{}'",
+                key
+            )));
+        }
+
+        drop(entries);
+        let end = guard.value().len();
+        let page = PageSlice {
+            guard,
+            _read_txn: read_txn,
+        };
+        let payload = ZSlice::new(Arc::new(page), offset, end)
+            .map_err(|_| RedbBackendError::other("Invalid payload bounds"))?;
+
+        Ok(Some(ValueView {
+            payload,
+            timestamp: info.timestamp,
+            encoding: info.encoding,
+        }))
+    }
+
+    /// Delete a key-value pair.
+    ///
+    /// The payload is removed and a tombstone carrying `timestamp` is kept in the
+    /// data_info table, so that a late put with an older timestamp cannot bring the
+    /// key back. Tombstones are removed by [`RedbStorage::garbage_collect`].
+    ///
+    /// The delete is skipped if the storage holds an entry for `key` with a timestamp
+    /// newer than `timestamp`.
+    pub fn delete(&self, key: &str, timestamp: Timestamp) -> Result<WriteOutcome> {
+        if self.config.read_only {
+            return Err(RedbBackendError::other("Storage is read-only"));
+        }
+
+        trace!("Deleting key: {}", key);
+
+        KEY_BUFFER.with(|key_buf| {
+            let mut key_buf = key_buf.borrow_mut();
+            key_buf.clear();
+            self.encode_key_into(key, &mut key_buf)?;
+
+            let write_txn = self.begin_write()?;
+            let outcome = {
+                let mut entries =
+                    EntryTables::open(&write_txn, &self.tables, self.config.layout, false)?;
+                let mut index = self.open_eviction_index(&write_txn)?;
+                let mut usage_table = write_txn.open_table(self.tables.usage())?;
+                let mut usage = read_usage(&usage_table)?.unwrap_or_default();
+
+                let outcome = self.apply_delete(
+                    &mut entries,
+                    &mut index,
+                    &mut usage,
+                    key_buf.as_slice(),
+                    timestamp,
+                )?;
+                write_usage(&mut usage_table, &usage)?;
+                outcome
+            };
+
+            if outcome == WriteOutcome::Outdated {
+                write_txn.abort()?;
+                debug!(
+                    "Skipped outdated delete for key: {} (timestamp: {})",
+                    key, timestamp
+                );
+            } else {
+                write_txn.commit()?;
+                debug!("Deleted key: {}", key);
+            }
+
+            Ok(outcome)
+        })
+    }
+
+    /// Apply a delete within the transaction of `entries`, updating `index` and
+    /// `usage`.
+    fn apply_delete(
+        &self,
+        entries: &mut EntryTables<Table<&'static [u8], &'static [u8]>>,
+        index: &mut Option<EvictionIndex>,
+        usage: &mut StorageUsage,
+        key: &[u8],
+        timestamp: Timestamp,
+    ) -> Result<WriteOutcome> {
+        let stored = stored_timestamp(entries, key)?;
+        if let Some((stored, _)) = stored
+            && stored > timestamp
+        {
+            return Ok(WriteOutcome::Outdated);
+        }
+
+        // Drop the payload and replace data_info with a tombstone
+        let tombstone_bytes = encode_data_info(&DataInfo::tombstone(timestamp))?;
+        if let Some(removed_len) = entries.insert(key, &tombstone_bytes, None)? {
+            usage.remove(key, removed_len);
+        }
+        if let Some(index) = index
+            && let Some((previous, false)) = stored
+        {
+            index.remove(eviction_key(key, &previous).as_slice())?;
+        }
+        Ok(WriteOutcome::Deleted)
+    }
+
+    /// Apply a batch of puts and deletes atomically, in a single write transaction.
+    ///
+    /// Operations are applied in order with the same last-writer-wins rules as
+    /// [`put`](Self::put) and [`delete`](Self::delete), including against earlier
+    /// operations of the batch on the same key. Returns the outcome of each
+    /// operation, in order.
+    ///
+    /// If any operation fails, for instance because it would exceed a quota with
+    /// [`QuotaPolicy::Reject`](crate::config::QuotaPolicy::Reject), the whole batch
+    /// is discarded.
+    pub fn write_batch<I>(&self, ops: I) -> Result<Vec<WriteOutcome>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<WriteOp>,
+    {
+        if self.config.read_only {
+            return Err(RedbBackendError::other("Storage is read-only"));
+        }
+
+        let ops = ops.into_iter();
+        let mut outcomes = Vec::with_capacity(ops.size_hint().0);
+        let mut key_bytes = Vec::with_capacity(256);
+
+        let write_txn = self.begin_write()?;
+        {
+            let mut entries =
+                EntryTables::open(&write_txn, &self.tables, self.config.layout, false)?;
+            let mut index = self.open_eviction_index(&write_txn)?;
+            let mut usage_table = write_txn.open_table(self.tables.usage())?;
+            let mut usage = read_usage(&usage_table)?.unwrap_or_default();
+
+            for op in ops {
+                let op = op.borrow();
+                key_bytes.clear();
+                self.encode_key_into(op.key(), &mut key_bytes)?;
+
+                let outcome = match op {
+                    WriteOp::Put { value, .. } => {
+                        let encoded = self.encode_put(&key_bytes, value)?;
+                        self.apply_put(
+                            &write_txn,
+                            &mut entries,
+                            &mut index,
+                            &mut usage,
+                            &key_bytes,
+                            &encoded,
+                        )?
+                    }
+                    WriteOp::Delete { timestamp, .. } => self.apply_delete(
+                        &mut entries,
+                        &mut index,
+                        &mut usage,
+                        &key_bytes,
+                        *timestamp,
+                    )?,
+                };
+                outcomes.push(outcome);
+            }
+
+            write_usage(&mut usage_table, &usage)?;
+        }
+
+        let applied = outcomes
+            .iter()
+            .filter(|outcome| **outcome != WriteOutcome::Outdated)
+            .count();
+        if applied == 0 {
+            write_txn.abort()?;
+        } else {
+            write_txn.commit()?;
+        }
+
+        debug!(
+            "Applied batch of {} operations ({} outdated)",
+            outcomes.len(),
+            outcomes.len() - applied
+        );
+        Ok(outcomes)
+    }
+
+    /// Retrieve all key-value pairs from the storage.
+    ///
+    /// Every value is loaded in memory; use [`iter_all`](Self::iter_all) to go
+    /// through large storages.
+    pub fn get_all(&self) -> Result<Vec<(String, StoredValue)>> {
+        trace!("Getting all entries");
+
+        let results = self.iter_all()?.collect::<Result<Vec<_>>>()?;
+
+        debug!("Retrieved {} entries", results.len());
+        Ok(results)
+    }
+
+    /// Retrieve all key-value pairs matching a given prefix.
+    pub fn get_by_prefix(&self, prefix: &str) -> Result<Vec<(String, StoredValue)>> {
+        trace!("Getting entries by prefix: {}", prefix);
+
+        let results = self.iter_by_prefix(prefix)?.collect::<Result<Vec<_>>>()?;
+
+        debug!(
+            "Retrieved {} entries with prefix '{}'",
+            results.len(),
+            prefix
+        );
+        Ok(results)
+    }
+
+    /// Retrieve all key-value pairs whose key intersects a key expression.
+    ///
+    /// The pattern accepts anything convertible to a [`KeyExpr`] (`&str`, `&keyexpr`,
+    /// `OwnedKeyExpr`, ...) and supports the full Zenoh wildcard syntax: `*`, `**`
+    /// and sub-chunk `$*` wildcards. Only the range of keys starting with the
+    /// longest literal prefix of the pattern is scanned.
+    pub fn get_by_wildcard<'a, P>(&self, pattern: P) -> Result<Vec<(String, StoredValue)>>
+    where
+        P: TryInto<KeyExpr<'a>>,
+        P::Error: std::fmt::Display,
+    {
+        let pattern: KeyExpr<'a> = pattern
+            .try_into()
+            .map_err(|e| RedbBackendError::invalid_key(e.to_string()))?;
+
+        let results = self
+            .iter_by_wildcard(pattern.clone())?
+            .collect::<Result<Vec<_>>>()?;
+
+        debug!(
+            "Retrieved {} entries matching wildcard '{}'",
+            results.len(),
+            pattern
+        );
+        Ok(results)
+    }
+
+    /// List the keys and timestamps of the live entries, in key order.
+    ///
+    /// Only the data_info of each entry is read: payloads are neither loaded nor
+    /// decrypted, so listing stays cheap on storages holding large values.
+    pub fn list_entries_metadata(&self) -> Result<Vec<(String, Timestamp)>> {
+        trace!("Listing entries metadata");
+
+        let read_txn = self.begin_read()?;
+        let entries = EntryTables::open_read(&read_txn, &self.tables, self.config.layout, false)?;
+
+        let mut results = Vec::new();
+        let now = self.expiry_now();
+        for item in entries.iter()? {
+            let (key_bytes, value) = item?;
+            let info = decode_data_info(entries.info(value.value())?)?;
+            if info.deleted {
+                continue;
+            }
+
+            let key = self.decode_key(key_bytes.value())?;
+            if !self.is_expired(&key, &info.timestamp, now) {
+                results.push((key, info.timestamp));
+            }
+        }
+
+        debug!("Listed metadata of {} entries", results.len());
+        Ok(results)
+    }
+
+    /// Iterate over all key-value pairs of the storage, in key order.
+    ///
+    /// Entries are read one at a time from a snapshot of the storage taken when the
+    /// iterator is created.
+    pub fn iter_all(&self) -> Result<EntryIter<'_>> {
+        self.scan_prefix("", None)
+    }
+
+    /// Iterate over the key-value pairs matching a given prefix, in key order.
+    pub fn iter_by_prefix(&self, prefix: &str) -> Result<EntryIter<'_>> {
+        trace!("Iterating over entries by prefix: {}", prefix);
+        self.scan_prefix(prefix, None)
+    }
+
+    /// Iterate over the key-value pairs whose key intersects a key expression, in
+    /// key order.
+    ///
+    /// Patterns are handled like in [`get_by_wildcard`](Self::get_by_wildcard).
+    pub fn iter_by_wildcard<'a, P>(&self, pattern: P) -> Result<EntryIter<'_>>
+    where
+        P: TryInto<KeyExpr<'a>>,
+        P::Error: std::fmt::Display,
+    {
+        let pattern: KeyExpr<'a> = pattern
+            .try_into()
+            .map_err(|e| RedbBackendError::invalid_key(e.to_string()))?;
+
+        trace!("Iterating over entries by wildcard: {}", pattern);
+
+        let prefix = literal_prefix(pattern.as_str()).to_string();
+        self.scan_prefix(&prefix, Some(pattern.into_owned().into()))
+    }
+
+    /// Iterate over the live entries whose key starts with `prefix` and, if a
+    /// pattern is given, whose key intersects it.
+    ///
+    /// Keys are stored in byte order, so only the `[prefix, upper bound)` range of
+    /// the data_info table is visited, `prefix` being stripped like the keys.
+    fn scan_prefix(&self, prefix: &str, pattern: Option<OwnedKeyExpr>) -> Result<EntryIter<'_>> {
+        let read_txn = self.begin_read()?;
+        let entries = EntryTables::open_read(&read_txn, &self.tables, self.config.layout, false)?;
+
+        let (stored_prefix, key_prefix) = match &self.scope {
+            Some(scope) if scope.strip_prefix.is_some() => {
+                (scope.stored_prefix(prefix), Some(prefix.to_string()))
+            }
+            _ => (Some(prefix), None),
+        };
+        let range = match stored_prefix.map(str::as_bytes) {
+            Some(stored_prefix) => match prefix_upper_bound(stored_prefix).as_deref() {
+                Some(end) => Some(entries.owned_range::<&[u8]>(stored_prefix..end)?),
+                None => Some(entries.owned_range::<&[u8]>(stored_prefix..)?),
+            },
+            None => None,
+        };
+
+        Ok(EntryIter {
+            storage: self,
+            entries,
+            range,
+            key_prefix,
+            pattern,
+            now: self.expiry_now(),
+        })
+    }
+
+    /// Count the total number of key-value pairs in storage.
+    ///
+    /// This reads the usage counters, unless a TTL is configured: entries that
+    /// expired but were not swept yet are still counted there, so the entries are
+    /// scanned instead.
+    pub fn count(&self) -> Result<usize> {
+        if !self.config.has_ttl() {
+            return Ok(self.usage()?.entries as usize);
+        }
+
+        let read_txn = self.begin_read()?;
+        let entries = EntryTables::open_read(&read_txn, &self.tables, self.config.layout, false)?;
+
+        let mut count = 0;
+        let now = self.expiry_now();
+        for item in entries.iter()? {
+            let (key_bytes, value) = item?;
+            let info = decode_data_info(entries.info(value.value())?)?;
+            if !info.deleted
+                && !self.is_expired(&self.decode_key(key_bytes.value())?, &info.timestamp, now)
+            {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Remove tombstones older than `lifespan`.
+    ///
+    /// Once a tombstone is removed, a put with a timestamp older than the delete
+    /// is accepted again, so `lifespan` should exceed the longest expected delay
+    /// of out-of-order samples. The table is swept in batches, see
+    /// [`garbage_collect_batch`](Self::garbage_collect_batch). Returns the number of
+    /// tombstones removed.
+    pub fn garbage_collect(&self, lifespan: Duration) -> Result<usize> {
+        let cutoff = NTP64::from(now_since_epoch()?.saturating_sub(lifespan));
+        let mut cursor: Option<Vec<u8>> = None;
+        let mut total = 0;
+        loop {
+            let (removed, next) = self.garbage_collect_batch(cursor.as_deref(), cutoff)?;
+            total += removed;
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        debug!("Removed {} expired tombstone(s)", total);
+        Ok(total)
+    }
+
+    /// Remove the tombstones older than `cutoff` among the entries following the
+    /// stored key `after`, scanning at most `MAINTENANCE_BATCH_SIZE` entries in one
+    /// transaction.
+    ///
+    /// Returns the number of tombstones removed and the stored key to continue from,
+    /// or `None` once the table is swept. Batches removing nothing are not committed.
+    pub(crate) fn garbage_collect_batch(
+        &self,
+        after: Option<&[u8]>,
+        cutoff: NTP64,
+    ) -> Result<(usize, Option<Vec<u8>>)> {
+        if self.config.read_only {
+            return Err(RedbBackendError::other("Storage is read-only"));
+        }
+
+        trace!("Collecting tombstones older than {}", cutoff);
+
+        let write_txn = self.begin_write()?;
+        let (removed, last_key) = {
+            let mut entries =
+                EntryTables::open(&write_txn, &self.tables, self.config.layout, false)?;
+
+            // Collect expired tombstones first, the table cannot be modified while iterating
+            let mut expired = Vec::new();
+            let mut last_key = None;
+            for item in entries.range_after(after)?.take(MAINTENANCE_BATCH_SIZE) {
+                let (key_bytes, value) = item?;
+                let info = decode_data_info(entries.info(value.value())?)?;
+                if info.deleted && *info.timestamp.get_time() < cutoff {
+                    expired.push(key_bytes.value().to_vec());
+                }
+                last_key = Some(key_bytes.value().to_vec());
+            }
+
+            for key in &expired {
+                entries.remove(key.as_slice())?;
+            }
+            (expired.len(), last_key)
+        };
+        if removed == 0 {
+            write_txn.abort()?;
+        } else {
+            write_txn.commit()?;
+        }
+        Ok((removed, last_key))
+    }
+
+    /// Turn entries whose TTL has elapsed into tombstones.
+    ///
+    /// The table is swept in batches, see [`expire_batch`](Self::expire_batch), so
+    /// that writers are not blocked for long. Tombstones keep the timestamp of the
+    /// expired entry and are later removed by [`RedbStorage::garbage_collect`].
+    /// Returns the number of entries expired.
+    pub fn expire_entries(&self) -> Result<usize> {
+        let now = now_since_epoch()?;
+        let mut cursor: Option<Vec<u8>> = None;
+        let mut total = 0;
+        loop {
+            let (expired, next) = self.expire_batch(cursor.as_deref(), now)?;
+            total += expired;
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        debug!("Expired {} entries", total);
+        Ok(total)
+    }
+
+    /// Expire the entries whose TTL elapsed at `now` among the entries following
+    /// the stored key `after`, scanning at most `MAINTENANCE_BATCH_SIZE` entries in
+    /// one transaction.
+    ///
+    /// Returns the number of entries expired and the stored key to continue from,
+    /// or `None` once the table is swept. Batches expiring nothing are not committed.
+    pub(crate) fn expire_batch(
+        &self,
+        after: Option<&[u8]>,
+        now: Duration,
+    ) -> Result<(usize, Option<Vec<u8>>)> {
+        if self.config.read_only {
+            return Err(RedbBackendError::other("Storage is read-only"));
+        }
+        if !self.config.has_ttl() {
+            return Ok((0, None));
+        }
+
+        let write_txn = self.begin_write()?;
+        let (expired, last_key) = {
+            let mut entries =
+                EntryTables::open(&write_txn, &self.tables, self.config.layout, false)?;
+
+            // Collect expired entries first, the table cannot be modified while iterating
+            let mut expired = Vec::new();
+            let mut last_key = None;
+            for item in entries.range_after(after)?.take(MAINTENANCE_BATCH_SIZE) {
+                let (key_bytes, value) = item?;
+                let info = decode_data_info(entries.info(value.value())?)?;
+                let key = self.decode_key(key_bytes.value())?;
+                if !info.deleted && self.is_expired(&key, &info.timestamp, Some(now)) {
+                    expired.push((key_bytes.value().to_vec(), info.timestamp));
+                }
+                last_key = Some(key_bytes.value().to_vec());
+            }
+
+            if !expired.is_empty() {
+                let mut index = self.open_eviction_index(&write_txn)?;
+                let mut usage_table = write_txn.open_table(self.tables.usage())?;
+                let mut usage = read_usage(&usage_table)?.unwrap_or_default();
+                for (key, timestamp) in &expired {
+                    let tombstone = encode_data_info(&DataInfo::tombstone(*timestamp))?;
+                    if let Some(removed_len) = entries.insert(key.as_slice(), &tombstone, None)? {
+                        usage.remove(key, removed_len);
+                    }
+                    if let Some(index) = &mut index {
+                        index.remove(eviction_key(key, timestamp).as_slice())?;
+                    }
+                }
+                write_usage(&mut usage_table, &usage)?;
+            }
+            (expired.len(), last_key)
+        };
+        if expired == 0 {
+            write_txn.abort()?;
+        } else {
+            write_txn.commit()?;
+        }
+        Ok((expired, last_key))
+    }
+
+    /// Check the consistency of the stored entries.
+    ///
+    /// Walks the tables of the entries, and of the past versions in history mode,
+    /// reporting orphaned payloads, orphaned data_info records,
+    /// undecodable data_info records and payloads that do not match their checksum.
+    /// Entries written before checksums were recorded are not checksum-verified.
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let read_txn = self.begin_read()?;
+        let mut report = VerifyReport::default();
+        let mut issues = Vec::new();
+
+        let entries = EntryTables::open_read(&read_txn, &self.tables, self.config.layout, false)?;
+        report.checked += find_integrity_issues(&entries, &mut issues)?;
+        report
+            .issues
+            .extend(issues.drain(..).map(|(key, kind)| latest_issue(&key, kind)));
+
+        if self.config.keep_history {
+            let history =
+                EntryTables::open_read(&read_txn, &self.tables, self.config.layout, true)?;
+            report.checked += find_integrity_issues(&history, &mut issues)?;
+            report.issues.extend(
+                issues
+                    .drain(..)
+                    .map(|(key, kind)| history_issue(&key, kind)),
+            );
+        }
+
+        if !report.is_ok() {
+            warn!(
+                "Storage '{}' has {} inconsistent entries",
+                self.name,
+                report.issues.len()
+            );
+        }
+        Ok(report)
+    }
+
+    /// Check the consistency of the stored entries and fix the inconsistent ones.
+    ///
+    /// Both the payload and the data_info records of each entry reported by
+    /// [`verify`](Self::verify) are quarantined or removed, depending on `mode`, in a
+    /// single transaction, and the usage of the remaining entries is recounted.
+    /// Returns the report of the entries found before repairing.
+    pub fn repair(&self, mode: RepairMode) -> Result<VerifyReport> {
+        if self.config.read_only {
+            return Err(RedbBackendError::other("Storage is read-only"));
+        }
+
+        let write_txn = self.begin_write()?;
+        let mut report = VerifyReport::default();
+        {
+            let mut quarantine_table = match mode {
+                RepairMode::Quarantine => Some(write_txn.open_table(self.tables.quarantine())?),
+                RepairMode::Remove => None,
+            };
+            let mut issues = Vec::new();
+
+            let mut entries =
+                EntryTables::open(&write_txn, &self.tables, self.config.layout, false)?;
+            report.checked += find_integrity_issues(&entries, &mut issues)?;
+
+            for (key, kind) in issues.drain(..) {
+                entries.remove_into(&key, quarantine_table.as_mut())?;
+                report.issues.push(latest_issue(&key, kind));
+            }
+
+            // Broken entries may not have been accounted for consistently, recount
+            if !report.is_ok() {
+                let mut usage_table = write_txn.open_table(self.tables.usage())?;
+                write_usage(&mut usage_table, &count_usage(&entries)?)?;
+                drop(entries);
+                self.rebuild_eviction_index(&write_txn)?;
+            }
+
+            if self.config.keep_history {
+                let mut history =
+                    EntryTables::open(&write_txn, &self.tables, self.config.layout, true)?;
+                report.checked += find_integrity_issues(&history, &mut issues)?;
+                for (key, kind) in issues.drain(..) {
+                    history.remove_into(&key, quarantine_table.as_mut())?;
+                    report.issues.push(history_issue(&key, kind));
+                }
+            }
+        }
+        write_txn.commit()?;
+
+        if !report.is_ok() {
+            info!(
+                "Repaired {} inconsistent entries of storage '{}' ({:?})",
+                report.issues.len(),
+                self.name,
+                mode
+            );
+        }
+        Ok(report)
+    }
+
+    /// Get the schema version of the database file.
+    pub fn schema_version(&self) -> Result<u32> {
+        let read_txn = self.begin_read()?;
+        let metadata_table = read_txn.open_table(self.tables.metadata())?;
+        Ok(schema::read_schema_version(&metadata_table)?.unwrap_or(0))
+    }
+
+    /// Get the space currently used by live entries.
+    pub fn usage(&self) -> Result<StorageUsage> {
+        let read_txn = self.begin_read()?;
+        let usage_table = read_txn.open_table(self.tables.usage())?;
+        Ok(read_usage(&usage_table)?.unwrap_or_default())
+    }
+
+    /// Whether `usage` is over the configured `max_entries` or `max_bytes`.
+    fn exceeds_quota(&self, usage: &StorageUsage) -> bool {
+        self.config
+            .max_entries
+            .is_some_and(|max| usage.entries > max)
+            || self.config.max_bytes.is_some_and(|max| usage.bytes > max)
+    }
+
+    /// Bring `usage` back within quota after writing `key`.
+    ///
+    /// With `QuotaPolicy::Reject` the write is refused. With
+    /// `QuotaPolicy::EvictOldest` the live entries with the oldest timestamps,
+    /// other than `key`, are taken from the front of the eviction index and replaced
+    /// by tombstones carrying their timestamp until the quota is met, so that a late
+    /// put older than an evicted entry cannot bring it back. The write is refused
+    /// if `key` alone exceeds the quota.
+    fn enforce_quota(
+        &self,
+        entries: &mut EntryTables<Table<&'static [u8], &'static [u8]>>,
+        index: &mut Option<EvictionIndex>,
+        usage: &mut StorageUsage,
+        key: &[u8],
+    ) -> Result<()> {
+        let quota_error = |usage: &StorageUsage| {
+            RedbBackendError::quota_exceeded(format!(
+                "storage '{}' would hold {} entries and {} bytes (max_entries: {:?}, max_bytes: {:?})",
+                self.name,
+                usage.entries,
+                usage.bytes,
+                self.config.max_entries,
+                self.config.max_bytes
+            ))
+        };
+
+        let Some(index) = index else {
+            return Err(quota_error(usage));
+        };
+
+        let mut cursor: Option<Vec<u8>> = None;
+        let mut evicted = 0;
+        while self.exceeds_quota(usage) {
+            let index_key = {
+                let mut range = match &cursor {
+                    Some(cursor) => index
+                        .range::<&[u8]>((Bound::Excluded(cursor.as_slice()), Bound::Unbounded))?,
+                    None => index.range::<&[u8]>(..)?,
+                };
+                match range.next() {
+                    Some(item) => item?.0.value().to_vec(),
+                    None => break,
+                }
+            };
+            let candidate = index_key.get(EVICTION_PREFIX_LEN..).ok_or_else(|| {
+                RedbBackendError::key_encoding("Eviction key without a timestamp")
+            })?;
+            if candidate == key {
+                cursor = Some(index_key);
+                continue;
+            }
+
+            index.remove(index_key.as_slice())?;
+            match stored_timestamp(entries, candidate)? {
+                Some((timestamp, false)) if eviction_key(candidate, &timestamp) == index_key => {
+                    let tombstone = encode_data_info(&DataInfo::tombstone(timestamp))?;
+                    if let Some(removed_len) = entries.insert(candidate, &tombstone, None)? {
+                        usage.remove(candidate, removed_len);
+                    }
+                    evicted += 1;
+                }
+                _ => warn!(
+                    "Dropped stale eviction index entry of storage '{}'",
+                    self.name
+                ),
+            }
+        }
+
+        if self.exceeds_quota(usage) {
+            return Err(quota_error(usage));
+        }
+
+        debug!("Evicted {} entries to stay within quota", evicted);
+        Ok(())
+    }
+
+    /// Current time used to check TTL expiry, or `None` if no TTL is configured.
+    fn expiry_now(&self) -> Option<Duration> {
+        if self.config.has_ttl() {
+            Some(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default(),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Whether the entry stored for `key` at `timestamp` has outlived its TTL at `now`.
+    fn is_expired(&self, key: &str, timestamp: &Timestamp, now: Option<Duration>) -> bool {
+        match (now, self.config.ttl_for(key)) {
+            (Some(now), Some(ttl)) => timestamp.get_time().to_duration() + ttl <= now,
+            _ => false,
+        }
+    }
+
+    /// Clear all entries from the storage.
+    pub fn clear(&self) -> Result<()> {
+        if self.config.read_only {
+            return Err(RedbBackendError::other("Storage is read-only"));
+        }
+
+        info!("Clearing all entries from storage");
+
+        let write_txn = self.begin_write()?;
+        {
+            // Delete and recreate the tables - much more efficient than removing keys one by one
+            layout::delete_tables(&write_txn, &self.tables, self.config.layout, false)?;
+            EntryTables::open(&write_txn, &self.tables, self.config.layout, false)?;
+
+            let mut usage_table = write_txn.open_table(self.tables.usage())?;
+            write_usage(&mut usage_table, &StorageUsage::default())?;
+            self.rebuild_eviction_index(&write_txn)?;
+
+            // Past versions are dropped too, even if history was disabled since
+            layout::delete_tables(&write_txn, &self.tables, self.config.layout, true)?;
+            if self.config.keep_history {
+                EntryTables::open(&write_txn, &self.tables, self.config.layout, true)?;
+            }
+        }
+        write_txn.commit()?;
+
+        info!("Storage cleared");
+        Ok(())
+    }
+
+    /// Build the value of an entry from its metadata and stored payload bytes.
+    ///
+    /// Encrypted entries are decrypted with the key they were written with; `key` is
+    /// the encoded key of the entry, which their ciphertexts are bound to. Entries whose
+    /// payload does not match its checksum fail with [`RedbBackendError::CorruptionError`].
+    fn stored_value(
+        &self,
+        info: DataInfo,
+        key: &[u8],
+        stored_payload: &[u8],
+    ) -> Result<StoredValue> {
+        if !info.verify_checksum(stored_payload) {
+            return Err(RedbBackendError::corruption(format!(
+                "Checksum mismatch for key '{}'",
+                String::from_utf8_lossy(key)
+            )));
+        }
+
+        if info.key_id == KEY_ID_NONE {
+            let payload = compression::decompress(info.codec, stored_payload)?;
+            return Ok(StoredValue::new(
+                payload.into_owned(),
+                info.timestamp,
+                info.encoding,
+            ));
+        }
+
+        let cipher = self.cipher.as_ref().ok_or_else(|| {
+            RedbBackendError::decryption(format!(
+                "Entry is encrypted with key {} but encryption is not configured",
+                info.key_id
+            ))
+        })?;
+        let compressed = cipher.open(info.key_id, Domain::Payload, key, stored_payload)?;
+        let encoding_bytes =
+            cipher.open(info.key_id, Domain::Encoding, key, &info.sealed_encoding)?;
+        let payload = compression::decompress(info.codec, &compressed)?;
+        Ok(StoredValue::new(
+            payload.into_owned(),
+            info.timestamp,
+            decode_encoding(&encoding_bytes)?,
+        ))
+    }
+
+    /// Encode a key string into an existing buffer (zero-allocation).
+    ///
+    /// Keys outside the configured `key_expr` are rejected, and the prefix is
+    /// stripped from them when `strip_prefix` is enabled.
+    fn encode_key_into(&self, key: &str, buffer: &mut Vec<u8>) -> Result<()> {
+        let stored = match &self.scope {
+            Some(scope) => scope.strip(key)?,
+            None => key,
+        };
+        buffer.extend_from_slice(stored.as_bytes());
+        Ok(())
+    }
+
+    /// Decode key bytes back to a string, restoring the stripped prefix if any.
+    fn decode_key(&self, bytes: &[u8]) -> Result<String> {
+        let key = String::from_utf8(bytes.to_vec())
+            .map_err(|e| RedbBackendError::serialization(format!("Invalid UTF-8 in key: {}", e)))?;
+        Ok(match &self.scope {
+            Some(scope) => scope.restore(key),
+            None => key,
+        })
+    }
+
+    /// Check if a stored key matches a key expression, using Zenoh's intersection logic.
+    ///
+    /// Stored keys that are not valid key expressions never match.
+    fn matches_wildcard(key: &str, pattern: &keyexpr) -> bool {
+        match keyexpr::new(key) {
+            Ok(key) => pattern.intersects(key),
+            Err(_) => {
+                trace!("Stored key is not a valid key expression: {}", key);
+                false
+            }
+        }
+    }
+}
+
+/// Iterator over the live key-value pairs of a storage, in key order.
+///
+/// Created by [`RedbStorage::iter_all`], [`RedbStorage::iter_by_prefix`] and
+/// [`RedbStorage::iter_by_wildcard`]. The iterator holds a read transaction, so
+/// it sees the storage as it was when created, and only reads and decodes an
+/// entry when yielding it.
+pub struct EntryIter<'s> {
+    storage: &'s RedbStorage,
+    entries: EntryTables<ReadOnlyTable<&'static [u8], &'static [u8]>>,
+    /// Range of stored keys, or `None` if no stored key can match
+    range: Option<Range<'static, &'static [u8], &'static [u8]>>,
+    /// Prefix of the yielded keys, when the range alone does not ensure it
+    key_prefix: Option<String>,
+    pattern: Option<OwnedKeyExpr>,
+    now: Option<Duration>,
+}
+
+impl EntryIter<'_> {
+    /// Read an entry of the range, or `None` if it is not yielded.
+    fn read_entry(&self, key_bytes: &[u8], value: &[u8]) -> Result<Option<(String, StoredValue)>> {
+        let key = self.storage.decode_key(key_bytes)?;
+        if let Some(key_prefix) = &self.key_prefix
+            && !key.starts_with(key_prefix.as_str())
+        {
+            return Ok(None);
+        }
+        if let Some(pattern) = &self.pattern
+            && !RedbStorage::matches_wildcard(&key, pattern)
+        {
+            return Ok(None);
+        }
+
+        let info = decode_data_info(self.entries.info(value)?)?;
+        if info.deleted || self.storage.is_expired(&key, &info.timestamp, self.now) {
+            return Ok(None);
+        }
+
+        match self.entries.payload(key_bytes, value)? {
+            Some(payload) => {
+                let stored_value = self
+                    .storage
+                    .stored_value(info, key_bytes, payload.value())?;
+                Ok(Some((key, stored_value)))
+            }
+            None => {
+                warn!(
+                    "Data info exists but no payload for key: {} - skipping",
+                    key
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Iterator for EntryIter<'_> {
+    type Item = Result<(String, StoredValue)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(item) = self.range.as_mut()?.next() {
+            let entry = match item {
+                Ok((key_bytes, value)) => self.read_entry(key_bytes.value(), value.value()),
+                Err(e) => Err(e.into()),
+            };
+            if let Some(entry) = entry.transpose() {
+                return Some(entry);
+            }
+        }
+        None
+    }
+}
+
+/// The key expression the keys of a storage must be included in, and the prefix
+/// stripped from them.
+#[derive(Debug)]
+struct KeyScope {
+    key_expr: OwnedKeyExpr,
+    /// Leading chunks of `key_expr` without wildcards, when `strip_prefix` is set
+    strip_prefix: Option<String>,
+}
+
+impl KeyScope {
+    /// The scope of a storage configuration, if it has a `key_expr`.
+    fn from_config(config: &RedbStorageConfig) -> Result<Option<Self>> {
+        Self::new(config.key_expr.as_deref(), config.strip_prefix)
+    }
+
+    /// The scope recorded for the keys of a storage, if it has a `key_expr`.
+    fn from_record(record: &KeyScopeRecord) -> Result<Option<Self>> {
+        Self::new(record.key_expr.as_deref(), record.strip_prefix)
+    }
+
+    /// The scope of a `key_expr`, if any, stripping its prefix if `strip_prefix`.
+    fn new(key_expr: Option<&str>, strip_prefix: bool) -> Result<Option<Self>> {
+        let Some(key_expr) = key_expr else {
+            return Ok(None);
+        };
+        let key_expr: OwnedKeyExpr = key_expr.parse().map_err(|e| {
+            RedbBackendError::config(format!("Invalid key_expr '{}': {}", key_expr, e))
+        })?;
+
+        let strip_prefix = if strip_prefix {
+            let chunks: Vec<_> = key_expr
+                .as_str()
+                .split('/')
+                .take_while(|chunk| !chunk.contains('*'))
+                .collect();
+            Some(chunks.join("/")).filter(|prefix| !prefix.is_empty())
+        } else {
+            None
+        };
+
+        Ok(Some(Self {
+            key_expr,
+            strip_prefix,
+        }))
+    }
+
+    /// The record of how a scope, or its absence, stores keys.
+    fn record(scope: Option<&Self>) -> KeyScopeRecord {
+        KeyScopeRecord {
+            key_expr: scope.map(|scope| scope.key_expr.to_string()),
+            strip_prefix: scope.is_some_and(|scope| scope.strip_prefix.is_some()),
+        }
+    }
+
+    /// The key to store for `key`, which must be included in the key expression.
+    ///
+    /// A key equal to the stripped prefix is stored as [`NONE_KEY`], like the
+    /// storage manager of the plugin does.
+    fn strip<'k>(&self, key: &'k str) -> Result<&'k str> {
+        let included = keyexpr::new(key).is_ok_and(|key| self.key_expr.includes(key));
+        if !included {
+            return Err(RedbBackendError::invalid_key(format!(
+                "Key '{}' is not included in the storage key expression '{}'",
+                key, self.key_expr
+            )));
+        }
+
+        let Some(prefix) = &self.strip_prefix else {
+            return Ok(key);
+        };
+        // The key expression starts with the prefix, so do the keys it includes
+        match key.strip_prefix(prefix.as_str()) {
+            Some("") => Ok(NONE_KEY),
+            Some(rest) => Ok(rest.strip_prefix('/').unwrap_or(rest)),
+            None => Ok(key),
+        }
+    }
+
+    /// The key of an entry, given its stored key.
+    fn restore(&self, stored: String) -> String {
+        match &self.strip_prefix {
+            Some(prefix) if stored == NONE_KEY => prefix.clone(),
+            Some(prefix) => format!("{}/{}", prefix, stored),
+            None => stored,
+        }
+    }
+
+    /// The prefix shared by the stored keys of the keys starting with `prefix`, or
+    /// `None` if no key of the scope starts with it.
+    fn stored_prefix<'p>(&self, prefix: &'p str) -> Option<&'p str> {
+        let Some(strip_prefix) = &self.strip_prefix else {
+            return Some(prefix);
+        };
+        if strip_prefix.starts_with(prefix) {
+            return Some("");
+        }
+        prefix
+            .strip_prefix(strip_prefix.as_str())
+            .and_then(|rest| rest.strip_prefix('/'))
+    }
+}
+
+/// Longest literal prefix shared by every key a wildcard pattern can match.
+///
+/// The prefix stops before the first wildcard, without the chunk separator in
+/// front of it: `a/**` also matches `a`, so its prefix is `a` rather than `a/`.
+fn literal_prefix(pattern: &str) -> &str {
+    match pattern.find('*') {
+        Some(index) => {
+            let prefix = &pattern[..index];
+            let prefix = prefix.strip_suffix('$').unwrap_or(prefix);
+            prefix.strip_suffix('/').unwrap_or(prefix)
+        }
+        None => pattern,
+    }
+}
+
+/// Smallest byte string greater than every byte string starting with `prefix`.
+///
+/// Returns `None` when there is no such bound (empty prefix or only `0xFF` bytes),
+/// in which case the range is unbounded above.
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut upper = prefix.to_vec();
+    while let Some(last) = upper.pop() {
+        if last < u8::MAX {
+            upper.push(last + 1);
+            return Some(upper);
+        }
+    }
+    None
+}
+
+/// Current time as a duration since the UNIX epoch.
+pub(crate) fn now_since_epoch() -> Result<Duration> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| RedbBackendError::other(format!("System time error: {}", e)))
+}
+
+/// Build the key of an entry in the eviction index: the big-endian timestamp
+/// time, the bytes of the timestamp ID, then the key, so entries sort like their
+/// timestamps.
+fn eviction_key(key: &[u8], timestamp: &Timestamp) -> Vec<u8> {
+    let mut index_key = Vec::with_capacity(EVICTION_PREFIX_LEN + key.len());
+    index_key.extend_from_slice(&timestamp.get_time().as_u64().to_be_bytes());
+    index_key.extend_from_slice(&timestamp.get_id().to_le_bytes());
+    index_key.extend_from_slice(key);
+    index_key
+}
+
+/// Build the history key of a version: the key, a separator, the big-endian
+/// timestamp time, then the bytes of the timestamp ID.
+///
+/// `TimestampId` orders by its little-endian bytes, so versions sort like their
+/// timestamps, including versions with the same time.
+fn history_key(key: &[u8], timestamp: &Timestamp) -> Vec<u8> {
+    let mut version_key = history_time_bound(key, timestamp.get_time().as_u64());
+    version_key.extend_from_slice(&timestamp.get_id().to_le_bytes());
+    version_key
+}
+
+/// Describe an inconsistent entry of the latest values.
+fn latest_issue(key: &[u8], kind: IntegrityIssueKind) -> IntegrityIssue {
+    IntegrityIssue {
+        key: String::from_utf8_lossy(key).into_owned(),
+        version: None,
+        kind,
+    }
+}
+
+/// Describe an inconsistent version kept in history mode.
+fn history_issue(version_key: &[u8], kind: IntegrityIssueKind) -> IntegrityIssue {
+    if version_key.len() < HISTORY_SUFFIX_LEN {
+        return IntegrityIssue {
+            key: String::from_utf8_lossy(version_key).into_owned(),
+            version: None,
+            kind,
+        };
+    }
+    let (key, suffix) = version_key.split_at(version_key.len() - HISTORY_SUFFIX_LEN);
+    let time = u64::from_be_bytes(suffix[1..9].try_into().expect("8 bytes"));
+    IntegrityIssue {
+        key: String::from_utf8_lossy(key).into_owned(),
+        version: Some(NTP64(time)),
+        kind,
+    }
+}
+
+/// The history key prefix shared by all versions of `key` recorded at `time`.
+fn history_time_bound(key: &[u8], time: u64) -> Vec<u8> {
+    let mut bound = Vec::with_capacity(key.len() + 1 + 8 + 16);
+    bound.extend_from_slice(key);
+    bound.push(HISTORY_KEY_SEPARATOR);
+    bound.extend_from_slice(&time.to_be_bytes());
+    bound
+}
+
+/// Compute the half-open range `[start, end)` of history keys holding the versions
+/// of `key` within `time_range`.
+fn history_key_range<R>(key: &[u8], time_range: R) -> (Vec<u8>, Vec<u8>)
+where
+    R: RangeBounds<NTP64>,
+{
+    // Upper bound of every version of the key: the separator incremented by one
+    let mut key_end = key.to_vec();
+    key_end.push(HISTORY_KEY_SEPARATOR + 1);
+
+    let start = match time_range.start_bound() {
+        Bound::Included(time) => history_time_bound(key, time.as_u64()),
+        Bound::Excluded(time) => match time.as_u64().checked_add(1) {
+            Some(next) => history_time_bound(key, next),
+            None => key_end.clone(),
+        },
+        Bound::Unbounded => {
+            let mut start = key.to_vec();
+            start.push(HISTORY_KEY_SEPARATOR);
+            start
+        }
+    };
+
+    let end = match time_range.end_bound() {
+        Bound::Included(time) => match time.as_u64().checked_add(1) {
+            Some(next) => history_time_bound(key, next),
+            None => key_end,
+        },
+        Bound::Excluded(time) => history_time_bound(key, time.as_u64()),
+        Bound::Unbounded => key_end,
+    };
+
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Compression, EncryptionConfig, QuotaPolicy, StorageLayout};
+    use redb::TableHandle;
+    use tempfile::TempDir;
+    use zenoh::time::TimestampId;
+
+    fn create_test_storage() -> (RedbStorage, TempDir) {
+        create_storage_with(RedbStorageConfig::default())
+    }
+
+    fn create_storage_with(config: RedbStorageConfig) -> (RedbStorage, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.redb");
+        let storage = RedbStorage::new(db_path, config, "test".to_string()).unwrap();
+        (storage, temp_dir)
+    }
+
+    #[test]
+    fn test_put_and_get() {
+        let (storage, _temp) = create_test_storage();
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let encoding = Encoding::ZENOH_BYTES;
+        let payload = b"test data".to_vec();
+
+        let value = StoredValue::new(payload.clone(), timestamp, encoding.clone());
+        storage.put("test/key", value).unwrap();
+
+        let retrieved = storage.get("test/key").unwrap().unwrap();
+        assert_eq!(retrieved.payload, payload);
+        assert_eq!(retrieved.timestamp, timestamp);
+        assert_eq!(retrieved.encoding.id(), encoding.id());
+    }
+
+    #[test]
+    fn test_delete() {
+        let (storage, _temp) = create_test_storage();
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        storage.put("test/key", value).unwrap();
+
+        let delete_ts = Timestamp::new(NTP64(123456790), TimestampId::rand());
+        storage.delete("test/key", delete_ts).unwrap();
+        assert!(storage.get("test/key").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_get_all() {
+        let (storage, _temp) = create_test_storage();
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let value1 = StoredValue::new(b"data1".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        let value2 = StoredValue::new(b"data2".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+
+        storage.put("key1", value1).unwrap();
+        storage.put("key2", value2).unwrap();
+
+        let all = storage.get_all().unwrap();
+        assert_eq!(all.len(), 2);
+    }
+
+    #[test]
+    fn test_get_by_prefix() {
+        let (storage, _temp) = create_test_storage();
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+
+        storage.put("test/foo", value.clone()).unwrap();
+        storage.put("test/bar", value.clone()).unwrap();
+        storage.put("other/baz", value).unwrap();
+
+        let results = storage.get_by_prefix("test/").unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_wildcard_matching() {
+        let matches = |key: &str, pattern: &str| {
+            RedbStorage::matches_wildcard(key, keyexpr::new(pattern).unwrap())
+        };
+        assert!(matches("a/b/c", "a/b/c"));
+        assert!(matches("a/b/c", "a/*/c"));
+        assert!(matches("a/b/c", "a/**/c"));
+        assert!(matches("a/b/c/d", "a/**/d"));
+        assert!(!matches("a/b/c", "a/b/d"));
+    }
+
+    #[test]
+    fn test_wildcard_matching_sub_chunks() {
+        let matches = |key: &str, pattern: &str| {
+            RedbStorage::matches_wildcard(key, keyexpr::new(pattern).unwrap())
+        };
+        assert!(matches("sensor/temperature", "sensor/temp$*"));
+        assert!(matches("sensor/temp", "sensor/temp$*"));
+        assert!(matches("sensor/room1/temp", "sensor/room$*/temp"));
+        assert!(!matches("sensor/humidity", "sensor/temp$*"));
+        assert!(!matches("sensor/temp/1", "sensor/temp$*"));
+
+        // Keys that are not valid key expressions never match
+        assert!(!matches("key#with#hash", "**"));
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(literal_prefix("sensor/room1/**"), "sensor/room1");
+        assert_eq!(literal_prefix("a/*/c"), "a");
+        assert_eq!(literal_prefix("a/b/c"), "a/b/c");
+        assert_eq!(literal_prefix("**"), "");
+        assert_eq!(literal_prefix("*/temperature"), "");
+        assert_eq!(literal_prefix("sensor/temp$*"), "sensor/temp");
+    }
+
+    #[test]
+    fn test_prefix_upper_bound() {
+        assert_eq!(prefix_upper_bound(b"abc"), Some(b"abd".to_vec()));
+        assert_eq!(prefix_upper_bound(b"a/"), Some(b"a0".to_vec()));
+        assert_eq!(prefix_upper_bound(&[b'a', 0xFF]), Some(b"b".to_vec()));
+        assert_eq!(prefix_upper_bound(&[0xFF, 0xFF]), None);
+        assert_eq!(prefix_upper_bound(b""), None);
+    }
+
+    #[test]
+    fn test_get_by_wildcard_range_boundaries() {
+        let (storage, _temp) = create_test_storage();
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+
+        for key in ["a", "a/b", "a/b/c", "ab/c", "a0", "b/c", "0/a"] {
+            storage.put(key, value.clone()).unwrap();
+        }
+
+        let mut keys: Vec<String> = storage
+            .get_by_wildcard("a/**")
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "a/b", "a/b/c"]);
+
+        let results = storage.get_by_wildcard("*/c").unwrap();
+        assert_eq!(results.len(), 2);
+
+        let results = storage.get_by_prefix("a/").unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_iterators_stream_a_snapshot() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+        for key in ["a/1", "a/2", "b/1", "deleted"] {
+            storage.put(key, version(key, 100, id)).unwrap();
+        }
+        storage
+            .delete("deleted", Timestamp::new(NTP64(200), id))
+            .unwrap();
+
+        let keys =
+            |iter: EntryIter| -> Vec<String> { iter.map(|entry| entry.unwrap().0).collect() };
+
+        // Later writes are not seen by an existing iterator
+        let mut all = storage.iter_all().unwrap();
+        assert_eq!(all.next().unwrap().unwrap().1.payload, b"a/1");
+        storage.put("a/3", version("a/3", 100, id)).unwrap();
+        assert_eq!(keys(all), vec!["a/2", "b/1"]);
+
+        assert_eq!(
+            keys(storage.iter_by_prefix("a/").unwrap()),
+            vec!["a/1", "a/2", "a/3"]
+        );
+        assert_eq!(
+            keys(storage.iter_by_wildcard("*/1").unwrap()),
+            vec!["a/1", "b/1"]
+        );
+        assert!(storage.iter_by_wildcard("a/**/").is_err());
+    }
+
+    #[test]
+    fn test_count() {
+        let (storage, _temp) = create_test_storage();
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+
+        storage.put("key1", value.clone()).unwrap();
+        storage.put("key2", value.clone()).unwrap();
+        storage.put("key3", value).unwrap();
+
+        assert_eq!(storage.count().unwrap(), 3);
+
+        let delete_ts = Timestamp::new(NTP64(123456790), TimestampId::rand());
+        storage.delete("key2", delete_ts).unwrap();
+        assert_eq!(storage.count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_clear() {
+        let (storage, _temp) = create_test_storage();
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+
+        storage.put("key1", value.clone()).unwrap();
+        storage.put("key2", value).unwrap();
+
+        storage.clear().unwrap();
+        assert_eq!(storage.count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_create_db_false_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("missing.redb");
+        let config = RedbStorageConfig::default().with_create_db(false);
+
+        let result = RedbStorage::new(&db_path, config, "test".to_string());
+        assert!(matches!(result, Err(RedbBackendError::DatabaseNotFound(_))));
+        assert!(!db_path.exists());
+    }
+
+    #[test]
+    fn test_create_db_false_existing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("existing.redb");
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        {
+            let storage =
+                RedbStorage::new(&db_path, RedbStorageConfig::default(), "test".to_string())
+                    .unwrap();
+            storage.put("key1", value).unwrap();
+        }
+
+        let config = RedbStorageConfig::default().with_create_db(false);
+        let storage = RedbStorage::new(&db_path, config, "test".to_string()).unwrap();
+        assert!(storage.get("key1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_fsync_disabled_and_cache_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("nofsync.redb");
+        let config = RedbStorageConfig::default()
+            .with_fsync(false)
+            .with_cache_size(4 * 1024 * 1024);
+
+        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
+        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        {
+            let storage = RedbStorage::new(&db_path, config.clone(), "test".to_string()).unwrap();
+            storage.put("key1", value).unwrap();
+            assert!(storage.get("key1").unwrap().is_some());
+        }
+
+        // Non-durable commits are still persisted when the database is closed cleanly
+        let storage = RedbStorage::new(&db_path, config, "test".to_string()).unwrap();
+        assert!(storage.get("key1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_put_last_writer_wins() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+
+        let newer = StoredValue::new(
+            b"newer".to_vec(),
+            Timestamp::new(NTP64(200), id),
+            Encoding::ZENOH_BYTES,
+        );
+        let older = StoredValue::new(
+            b"older".to_vec(),
+            Timestamp::new(NTP64(100), id),
+            Encoding::ZENOH_BYTES,
+        );
+
+        assert_eq!(
+            storage.put("key", newer.clone()).unwrap(),
+            WriteOutcome::Inserted
+        );
+        assert_eq!(storage.put("key", older).unwrap(), WriteOutcome::Outdated);
+        assert_eq!(storage.put("key", newer).unwrap(), WriteOutcome::Outdated);
+
+        let retrieved = storage.get("key").unwrap().unwrap();
+        assert_eq!(retrieved.payload, b"newer");
+
+        let newest = StoredValue::new(
+            b"newest".to_vec(),
+            Timestamp::new(NTP64(300), id),
+            Encoding::ZENOH_BYTES,
+        );
+        assert_eq!(storage.put("key", newest).unwrap(), WriteOutcome::Replaced);
+        assert_eq!(storage.get("key").unwrap().unwrap().payload, b"newest");
+    }
+
+    #[test]
+    fn test_delete_last_writer_wins() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+
+        let value = StoredValue::new(
+            b"data".to_vec(),
+            Timestamp::new(NTP64(200), id),
+            Encoding::ZENOH_BYTES,
+        );
+        storage.put("key", value).unwrap();
+
+        let outcome = storage
+            .delete("key", Timestamp::new(NTP64(100), id))
+            .unwrap();
+        assert_eq!(outcome, WriteOutcome::Outdated);
+        assert!(storage.get("key").unwrap().is_some());
+
+        let outcome = storage
+            .delete("key", Timestamp::new(NTP64(300), id))
+            .unwrap();
+        assert_eq!(outcome, WriteOutcome::Deleted);
+        assert!(storage.get("key").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delete_writes_tombstone() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+
+        // A delete that arrives before the put it supersedes
+        let outcome = storage
+            .delete("key", Timestamp::new(NTP64(200), id))
+            .unwrap();
+        assert_eq!(outcome, WriteOutcome::Deleted);
+
+        // The late, older put must not bring the key back
+        let value = StoredValue::new(
+            b"late".to_vec(),
+            Timestamp::new(NTP64(100), id),
+            Encoding::ZENOH_BYTES,
+        );
+        assert_eq!(storage.put("key", value).unwrap(), WriteOutcome::Outdated);
+        assert!(storage.get("key").unwrap().is_none());
+        assert_eq!(storage.count().unwrap(), 0);
+
+        // A newer put is accepted and reported as an insertion
+        let value = StoredValue::new(
+            b"new".to_vec(),
+            Timestamp::new(NTP64(300), id),
+            Encoding::ZENOH_BYTES,
+        );
+        assert_eq!(storage.put("key", value).unwrap(), WriteOutcome::Inserted);
+        assert_eq!(storage.get("key").unwrap().unwrap().payload, b"new");
+    }
+
+    #[test]
+    fn test_garbage_collect_tombstones() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let recent = Timestamp::new(NTP64::from(now), id);
+        let old = Timestamp::new(NTP64::from(now - Duration::from_secs(7200)), id);
+
+        let value = StoredValue::new(b"data".to_vec(), recent, Encoding::ZENOH_BYTES);
+        storage.put("alive", value).unwrap();
+        storage.delete("old", old).unwrap();
+        storage.delete("recent", recent).unwrap();
+
+        // Only the tombstone older than the lifespan is collected
+        let removed = storage.garbage_collect(Duration::from_secs(3600)).unwrap();
+        assert_eq!(removed, 1);
+        assert!(storage.get("alive").unwrap().is_some());
+
+        // The collected key accepts older puts again, the recent tombstone still rejects them
+        let older = Timestamp::new(NTP64::from(now - Duration::from_secs(10800)), id);
+        let value = StoredValue::new(b"data".to_vec(), older, Encoding::ZENOH_BYTES);
+        assert_eq!(
+            storage.put("old", value.clone()).unwrap(),
+            WriteOutcome::Inserted
+        );
+        assert_eq!(
+            storage.put("recent", value).unwrap(),
+            WriteOutcome::Outdated
+        );
+    }
+
+    #[test]
+    fn test_garbage_collect_in_batches() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let old = Timestamp::new(NTP64::from(now - Duration::from_secs(7200)), id);
+
+        // More tombstones than a single collection transaction handles
+        let tombstones = MAINTENANCE_BATCH_SIZE + 10;
+        for i in 0..tombstones {
+            storage.delete(&format!("old/{}", i), old).unwrap();
+        }
+
+        assert_eq!(
+            storage.garbage_collect(Duration::from_secs(3600)).unwrap(),
+            tombstones
+        );
+        assert_eq!(
+            storage.garbage_collect(Duration::from_secs(3600)).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_write_batch() {
+        let (storage, _temp_dir) = create_test_storage();
+        let id = TimestampId::rand();
+        storage.put("a", version("old", 100, id)).unwrap();
+
+        let outcomes = storage
+            .write_batch([
+                WriteOp::Put {
+                    key: "a".to_string(),
+                    value: version("new", 200, id),
+                },
+                WriteOp::Put {
+                    key: "b".to_string(),
+                    value: version("b", 100, id),
+                },
+                // Older than the put of the same batch
+                WriteOp::Put {
+                    key: "a".to_string(),
+                    value: version("late", 150, id),
+                },
+                WriteOp::Delete {
+                    key: "b".to_string(),
+                    timestamp: Timestamp::new(NTP64(200), id),
+                },
+                WriteOp::Put {
+                    key: "c".to_string(),
+                    value: version("c", 100, id),
+                },
+            ])
+            .unwrap();
+        assert_eq!(
+            outcomes,
+            vec![
+                WriteOutcome::Replaced,
+                WriteOutcome::Inserted,
+                WriteOutcome::Outdated,
+                WriteOutcome::Deleted,
+                WriteOutcome::Inserted,
+            ]
+        );
+
+        assert_eq!(storage.get("a").unwrap().unwrap().payload, b"new");
+        assert!(storage.get("b").unwrap().is_none());
+        assert_eq!(storage.get("c").unwrap().unwrap().payload, b"c");
+        assert_eq!(
+            storage.usage().unwrap(),
+            StorageUsage {
+                entries: 2,
+                bytes: 1 + 3 + 1 + 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_write_batch_is_atomic() {
+        let (storage, _temp_dir) =
+            create_storage_with(RedbStorageConfig::new().with_max_entries(2));
+        let id = TimestampId::rand();
+
+        let ops = ["a", "b", "c"].map(|key| WriteOp::Put {
+            key: key.to_string(),
+            value: version(key, 100, id),
+        });
+        let result = storage.write_batch(ops);
+        assert!(matches!(result, Err(RedbBackendError::QuotaExceeded(_))));
+
+        // None of the batch was applied
+        assert_eq!(storage.count().unwrap(), 0);
+        assert_eq!(storage.usage().unwrap(), StorageUsage::default());
+    }
+
+    #[test]
+    fn test_write_batch_records_history() {
+        let (storage, _temp_dir) =
+            create_storage_with(RedbStorageConfig::new().with_keep_history(true));
+        let id = TimestampId::rand();
+
+        let ops = [(200, "v2"), (100, "v1")].map(|(time, payload)| WriteOp::Put {
+            key: "test/key".to_string(),
+            value: version(payload, time, id),
+        });
+        let outcomes = storage.write_batch(ops).unwrap();
+        assert_eq!(outcomes, vec![WriteOutcome::Inserted; 2]);
+
+        let versions = storage.get_history("test/key", ..).unwrap();
+        let payloads: Vec<_> = versions.iter().map(|v| v.payload.as_slice()).collect();
+        assert_eq!(payloads, vec![&b"v1"[..], b"v2"]);
+        assert_eq!(storage.get("test/key").unwrap().unwrap().payload, b"v2");
+    }
+
+    fn version(payload: &str, time: u64, id: TimestampId) -> StoredValue {
+        StoredValue::new(
+            payload.as_bytes().to_vec(),
+            Timestamp::new(NTP64(time), id),
+            Encoding::ZENOH_STRING,
+        )
+    }
+
+    #[test]
+    fn test_history_keeps_every_version() {
+        let (storage, _temp_dir) =
+            create_storage_with(RedbStorageConfig::new().with_keep_history(true));
+        let id = TimestampId::rand();
+
+        storage.put("test/key", version("v1", 100, id)).unwrap();
+        storage.put("test/key", version("v3", 300, id)).unwrap();
+        // An older sample is still recorded as a version
+        let outcome = storage.put("test/key", version("v2", 200, id)).unwrap();
+        assert_eq!(outcome, WriteOutcome::Inserted);
+        // The same version twice is outdated
+        let outcome = storage.put("test/key", version("v2", 200, id)).unwrap();
+        assert_eq!(outcome, WriteOutcome::Outdated);
+        storage.put("test/other", version("x", 150, id)).unwrap();
+
+        // The latest value is unaffected by older samples
+        let latest = storage.get("test/key").unwrap().unwrap();
+        assert_eq!(latest.payload, b"v3");
+
+        let payloads = |versions: Vec<StoredValue>| -> Vec<Vec<u8>> {
+            versions.into_iter().map(|v| v.payload).collect()
+        };
+        let all = storage.get_history("test/key", ..).unwrap();
+        assert_eq!(
+            payloads(all),
+            vec![b"v1".to_vec(), b"v2".to_vec(), b"v3".to_vec()]
+        );
+
+        let range = storage
+            .get_history("test/key", NTP64(200)..NTP64(300))
+            .unwrap();
+        assert_eq!(payloads(range), vec![b"v2".to_vec()]);
+
+        let range = storage.get_history("test/key", NTP64(200)..).unwrap();
+        assert_eq!(payloads(range), vec![b"v2".to_vec(), b"v3".to_vec()]);
+
+        let range = storage.get_history("test/key", ..=NTP64(100)).unwrap();
+        assert_eq!(payloads(range), vec![b"v1".to_vec()]);
+
+        let range = storage
+            .get_history("test/key", NTP64(301)..NTP64(400))
+            .unwrap();
+        assert!(range.is_empty());
+    }
+
+    #[test]
+    fn test_history_orders_versions_like_timestamps() {
+        let (storage, _temp_dir) =
+            create_storage_with(RedbStorageConfig::new().with_keep_history(true));
+        // Their byte order and their numeric order differ
+        let ids = [
+            TimestampId::try_from([2u8, 1]).unwrap(),
+            TimestampId::try_from([1u8, 2]).unwrap(),
+            TimestampId::try_from([1u8]).unwrap(),
+        ];
+        for (i, id) in ids.into_iter().enumerate() {
+            let payload = i.to_string();
+            storage.put("test/key", version(&payload, 100, id)).unwrap();
+        }
+
+        let timestamps: Vec<_> = storage
+            .get_history("test/key", ..)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.timestamp)
+            .collect();
+        let mut sorted = timestamps.clone();
+        sorted.sort();
+        assert_eq!(timestamps.len(), 3);
+        assert_eq!(timestamps, sorted);
+    }
+
+    #[test]
+    fn test_history_max_versions() {
+        let (storage, _temp_dir) = create_storage_with(
+            RedbStorageConfig::new()
+                .with_keep_history(true)
+                .with_max_versions(2),
+        );
+        let id = TimestampId::rand();
+
+        for time in 1..=5 {
+            storage
+                .put("test/key", version(&format!("v{}", time), time * 100, id))
+                .unwrap();
+        }
+
+        let versions = storage.get_history("test/key", ..).unwrap();
+        let times: Vec<u64> = versions
+            .iter()
+            .map(|v| v.timestamp.get_time().as_u64())
+            .collect();
+        assert_eq!(times, vec![400, 500]);
+    }
+
+    #[test]
+    fn test_history_disabled_returns_latest() {
+        let (storage, _temp_dir) = create_test_storage();
+        let id = TimestampId::rand();
+
+        storage.put("test/key", version("v1", 100, id)).unwrap();
+        storage.put("test/key", version("v2", 200, id)).unwrap();
+
+        let versions = storage.get_history("test/key", ..).unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].payload, b"v2");
+        assert!(
+            storage
+                .get_history("test/key", ..NTP64(200))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_clear_drops_history() {
+        let (storage, _temp_dir) =
+            create_storage_with(RedbStorageConfig::new().with_keep_history(true));
+        let id = TimestampId::rand();
+
+        storage.put("test/key", version("v1", 100, id)).unwrap();
+        storage.clear().unwrap();
+
+        assert!(storage.get_history("test/key", ..).unwrap().is_empty());
+    }
+
+    fn age(secs: u64) -> Timestamp {
+        let then = SystemTime::now() - Duration::from_secs(secs);
+        Timestamp::new(
+            NTP64::from(then.duration_since(UNIX_EPOCH).unwrap()),
+            TimestampId::rand(),
+        )
+    }
+
+    #[test]
+    fn test_ttl_hides_expired_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = RedbStorageConfig::new()
+            .with_ttl_secs(3600)
+            .with_ttl_override("presence/".to_string(), 60);
+        let storage =
+            RedbStorage::new(temp_dir.path().join("ttl.redb"), config, "ttl".to_string()).unwrap();
+
+        let value =
+            |timestamp| StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        storage.put("presence/alice", value(age(120))).unwrap();
+        storage.put("presence/bob", value(age(10))).unwrap();
+        storage.put("sensor/temp", value(age(120))).unwrap();
+
+        assert!(storage.get("presence/alice").unwrap().is_none());
+        assert!(storage.get("presence/bob").unwrap().is_some());
+        assert!(storage.get("sensor/temp").unwrap().is_some());
+        assert_eq!(storage.get_all().unwrap().len(), 2);
+        assert_eq!(storage.get_by_wildcard("presence/*").unwrap().len(), 1);
+        assert_eq!(storage.count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_expire_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = RedbStorageConfig::new().with_ttl_secs(60);
+        let storage =
+            RedbStorage::new(temp_dir.path().join("ttl.redb"), config, "ttl".to_string()).unwrap();
+
+        // More entries than a single sweep transaction handles
+        let expired_count = MAINTENANCE_BATCH_SIZE + 10;
+        for i in 0..expired_count {
+            let value = StoredValue::new(b"data".to_vec(), age(120), Encoding::ZENOH_BYTES);
+            storage.put(&format!("old/{}", i), value).unwrap();
+        }
+        let fresh = StoredValue::new(b"data".to_vec(), age(0), Encoding::ZENOH_BYTES);
+        storage.put("fresh", fresh).unwrap();
+
+        assert_eq!(storage.expire_entries().unwrap(), expired_count);
+        assert_eq!(storage.expire_entries().unwrap(), 0);
+        assert_eq!(storage.count().unwrap(), 1);
+
+        // Expired entries become tombstones, which still reject older puts
+        let stale = StoredValue::new(b"stale".to_vec(), age(300), Encoding::ZENOH_BYTES);
+        assert_eq!(storage.put("old/0", stale).unwrap(), WriteOutcome::Outdated);
+    }
+
+    #[test]
+    fn test_expire_entries_without_ttl() {
+        let (storage, _temp_dir) = create_test_storage();
+        let value = StoredValue::new(b"data".to_vec(), age(3600), Encoding::ZENOH_BYTES);
+        storage.put("test/key", value).unwrap();
+
+        assert_eq!(storage.expire_entries().unwrap(), 0);
+        assert!(storage.get("test/key").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_usage_tracking() {
+        let (storage, _temp_dir) = create_test_storage();
+        let id = TimestampId::rand();
+
+        storage.put("a", version("1234", 100, id)).unwrap();
+        storage.put("bb", version("12", 100, id)).unwrap();
+        assert_eq!(
+            storage.usage().unwrap(),
+            StorageUsage {
+                entries: 2,
+                bytes: 1 + 4 + 2 + 2
+            }
+        );
+
+        // Replacing accounts for the previous payload
+        storage.put("a", version("1", 200, id)).unwrap();
+        assert_eq!(storage.usage().unwrap().bytes, 1 + 1 + 2 + 2);
+
+        storage
+            .delete("bb", Timestamp::new(NTP64(300), id))
+            .unwrap();
+        assert_eq!(
+            storage.usage().unwrap(),
+            StorageUsage {
+                entries: 1,
+                bytes: 2
+            }
+        );
+
+        storage.clear().unwrap();
+        assert_eq!(storage.usage().unwrap(), StorageUsage::default());
+    }
+
+    #[test]
+    fn test_usage_counted_on_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("usage.redb");
+        let id = TimestampId::rand();
+
+        let storage =
+            RedbStorage::new(&db_path, RedbStorageConfig::new(), "usage".to_string()).unwrap();
+        storage.put("key", version("value", 100, id)).unwrap();
+        drop(storage);
+
+        // Simulate a database created before usage tracking and schema versioning
+        {
+            let db = Database::open(&db_path).unwrap();
+            let tables = TableNames::new(DEFAULT_TABLE_NAME).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            write_txn.delete_table(tables.usage()).unwrap();
+            write_txn.delete_table(tables.metadata()).unwrap();
+            write_txn.commit().unwrap();
+        }
+
+        let storage =
+            RedbStorage::new(&db_path, RedbStorageConfig::new(), "usage".to_string()).unwrap();
+        assert_eq!(storage.schema_version().unwrap(), schema::SCHEMA_VERSION);
+        assert_eq!(
+            storage.usage().unwrap(),
+            StorageUsage {
+                entries: 1,
+                bytes: 8
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_only_open_does_not_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("read_only.redb");
+        let id = TimestampId::rand();
+        let read_only = RedbStorageConfig::new()
+            .with_create_db(false)
+            .with_read_only(true);
+
+        let storage =
+            RedbStorage::new(&db_path, RedbStorageConfig::new(), "ro".to_string()).unwrap();
+        storage.put("key", version("value", 100, id)).unwrap();
+
+        // An up-to-date storage opens while a writer holds the database
+        let db = storage.database().clone();
+        let write_txn = db.begin_write().unwrap();
+        let reader =
+            RedbStorage::from_database(db.clone(), read_only.clone(), "ro".to_string()).unwrap();
+        assert_eq!(reader.get("key").unwrap().unwrap().payload, b"value");
+        write_txn.abort().unwrap();
+        drop((reader, storage, db));
+
+        // A storage needing a migration is left as it is
+        {
+            let db = Database::open(&db_path).unwrap();
+            let tables = TableNames::new(DEFAULT_TABLE_NAME).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            write_txn.delete_table(tables.metadata()).unwrap();
+            write_txn.commit().unwrap();
+        }
+        match RedbStorage::new(&db_path, read_only, "ro".to_string()) {
+            Err(RedbBackendError::ConfigError(msg)) => {
+                assert!(msg.contains("migrate its schema from version 0"));
+            }
+            _ => panic!("expected a configuration error"),
+        }
+        let db = Database::open(&db_path).unwrap();
+        let read_txn = db.begin_read().unwrap();
+        let tables = TableNames::new(DEFAULT_TABLE_NAME).unwrap();
+        assert!(matches!(
+            read_txn.open_table(tables.metadata()),
+            Err(TableError::TableDoesNotExist(_))
+        ));
+    }
+
+    #[test]
+    fn test_newer_schema_version_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("newer.redb");
+
+        let storage =
+            RedbStorage::new(&db_path, RedbStorageConfig::new(), "newer".to_string()).unwrap();
+        assert_eq!(storage.schema_version().unwrap(), schema::SCHEMA_VERSION);
+        drop(storage);
+
+        {
+            let db = Database::open(&db_path).unwrap();
+            let tables = TableNames::new(DEFAULT_TABLE_NAME).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            {
+                let mut metadata_table = write_txn.open_table(tables.metadata()).unwrap();
+                let newer = (schema::SCHEMA_VERSION + 1).to_le_bytes();
+                metadata_table
+                    .insert("schema_version", newer.as_slice())
+                    .unwrap();
+            }
+            write_txn.commit().unwrap();
+        }
+
+        let result = RedbStorage::new(&db_path, RedbStorageConfig::new(), "newer".to_string());
+        match result {
+            Err(RedbBackendError::SchemaError(msg)) => {
+                let newer = format!("schema version {}", schema::SCHEMA_VERSION + 1);
+                assert!(msg.contains(&newer));
+            }
+            _ => panic!("expected a schema error"),
+        }
+    }
+
+    #[test]
+    fn test_quota_reject() {
+        let (storage, _temp_dir) =
+            create_storage_with(RedbStorageConfig::new().with_max_entries(2));
+        let id = TimestampId::rand();
+
+        storage.put("a", version("1", 100, id)).unwrap();
+        storage.put("b", version("2", 100, id)).unwrap();
+        let result = storage.put("c", version("3", 100, id));
+        assert!(matches!(result, Err(RedbBackendError::QuotaExceeded(_))));
+        assert!(storage.get("c").unwrap().is_none());
+        assert_eq!(storage.usage().unwrap().entries, 2);
+
+        // Updating an existing key does not grow the entry count
+        storage.put("a", version("9", 200, id)).unwrap();
+        assert_eq!(storage.get("a").unwrap().unwrap().payload, b"9");
+    }
+
+    #[test]
+    fn test_quota_evict_oldest() {
+        let (storage, _temp_dir) = create_storage_with(
+            RedbStorageConfig::new()
+                .with_max_bytes(20)
+                .with_quota_policy(QuotaPolicy::EvictOldest),
+        );
+        let id = TimestampId::rand();
+
+        // Each entry takes 1 + 4 bytes
+        storage.put("a", version("1111", 300, id)).unwrap();
+        storage.put("b", version("2222", 100, id)).unwrap();
+        storage.put("c", version("3333", 200, id)).unwrap();
+        storage.put("d", version("4444", 400, id)).unwrap();
+
+        // The entry with the oldest timestamp makes room for the new one
+        storage.put("e", version("5555", 500, id)).unwrap();
+        assert!(storage.get("b").unwrap().is_none());
+        assert_eq!(storage.count().unwrap(), 4);
+        assert_eq!(
+            storage.usage().unwrap(),
+            StorageUsage {
+                entries: 4,
+                bytes: 20
+            }
+        );
+
+        // An entry larger than the quota on its own is rejected
+        let result = storage.put("f", version(&"x".repeat(32), 600, id));
+        assert!(matches!(result, Err(RedbBackendError::QuotaExceeded(_))));
+        assert_eq!(storage.count().unwrap(), 4);
     }
 
-    /// Retrieve all key-value pairs matching a wildcard pattern.
-    pub fn get_by_wildcard(&self, pattern: &str) -> Result<Vec<(String, StoredValue)>> {
-        trace!("Getting entries by wildcard: {}", pattern);
+    #[test]
+    fn test_quota_eviction_leaves_tombstones() {
+        let (storage, _temp_dir) = create_storage_with(
+            RedbStorageConfig::new()
+                .with_max_entries(2)
+                .with_quota_policy(QuotaPolicy::EvictOldest),
+        );
+        let id = TimestampId::rand();
 
-        let read_txn = self.db.begin_read()?;
-        let payloads_table = read_txn.open_table(PAYLOADS_TABLE)?;
-        let data_info_table = read_txn.open_table(DATA_INFO_TABLE)?;
+        storage.put("a", version("1", 100, id)).unwrap();
+        storage.put("b", version("2", 200, id)).unwrap();
+        storage.put("c", version("3", 300, id)).unwrap();
+        assert!(storage.get("a").unwrap().is_none());
 
-        let mut results = Vec::new();
+        // A late put older than the evicted entry does not bring it back
+        assert_eq!(
+            storage.put("a", version("0", 50, id)).unwrap(),
+            WriteOutcome::Outdated
+        );
+        assert!(storage.get("a").unwrap().is_none());
 
-        for item in data_info_table.iter()? {
-            let (key_bytes, info_bytes) = item?;
-            let key = self.decode_key(key_bytes.value())?;
+        // A newer put does, evicting the next oldest entry
+        storage.put("a", version("4", 400, id)).unwrap();
+        assert!(storage.get("b").unwrap().is_none());
+        assert_eq!(storage.count().unwrap(), 2);
+        assert_eq!(storage.garbage_collect(Duration::ZERO).unwrap(), 1);
+    }
 
-            if Self::matches_wildcard(&key, pattern) {
-                let (encoding, timestamp, deleted) = decode_data_info(info_bytes.value())?;
+    #[test]
+    fn test_quota_eviction_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("index.redb");
+        let id = TimestampId::rand();
+        let evicting = RedbStorageConfig::new()
+            .with_max_entries(3)
+            .with_quota_policy(QuotaPolicy::EvictOldest);
 
-                if !deleted && let Some(payload_guard) = payloads_table.get(key_bytes.value())? {
-                    let payload_bytes = payload_guard.value();
-                    let stored_value =
-                        StoredValue::new(payload_bytes.to_vec(), timestamp, encoding);
-                    results.push((key, stored_value));
-                }
-            }
+        // Entries written while nothing is evicted are indexed on reopen
+        {
+            let storage =
+                RedbStorage::new(&db_path, RedbStorageConfig::new(), "index".to_string()).unwrap();
+            storage.put("a", version("1", 300, id)).unwrap();
+            storage.put("b", version("2", 100, id)).unwrap();
+            storage.put("c", version("3", 200, id)).unwrap();
         }
+        let storage = RedbStorage::new(&db_path, evicting.clone(), "index".to_string()).unwrap();
 
-        debug!(
-            "Retrieved {} entries matching wildcard '{}'",
-            results.len(),
-            pattern
+        // Updates and deletes move entries in the index
+        storage.put("b", version("2", 500, id)).unwrap();
+        storage.delete("c", Timestamp::new(NTP64(600), id)).unwrap();
+        storage.put("d", version("4", 700, id)).unwrap();
+        storage.put("e", version("5", 800, id)).unwrap();
+        let keys: Vec<_> = storage
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec!["b", "d", "e"]);
+
+        let read_txn = storage.db.begin_read().unwrap();
+        let index = read_txn
+            .open_table(storage.tables.eviction_index())
+            .unwrap();
+        let indexed: Vec<_> = index
+            .iter()
+            .unwrap()
+            .map(|item| item.unwrap().0.value()[EVICTION_PREFIX_LEN..].to_vec())
+            .collect();
+        assert_eq!(indexed, vec![b"b".to_vec(), b"d".to_vec(), b"e".to_vec()]);
+        drop(index);
+        drop(read_txn);
+        drop(storage);
+
+        // The index is dropped once nothing is evicted
+        let storage =
+            RedbStorage::new(&db_path, RedbStorageConfig::new(), "index".to_string()).unwrap();
+        let read_txn = storage.db.begin_read().unwrap();
+        assert!(
+            !read_txn
+                .list_tables()
+                .unwrap()
+                .any(|table| table.name() == storage.tables.eviction_index().name())
         );
-        Ok(results)
     }
 
-    /// Count the total number of key-value pairs in storage.
-    pub fn count(&self) -> Result<usize> {
-        let read_txn = self.db.begin_read()?;
-        let data_info_table = read_txn.open_table(DATA_INFO_TABLE)?;
+    #[test]
+    fn test_legacy_data_info_decodes_without_codec() {
+        let timestamp = Timestamp::new(NTP64(42), TimestampId::rand());
+        let info = DataInfo::new(Encoding::APPLICATION_JSON, timestamp, CODEC_NONE);
+        let legacy = zenoh_ext::z_serialize(&info.as_tuple())
+            .to_bytes()
+            .into_owned();
 
-        let mut count = 0;
-        for item in data_info_table.iter()? {
-            let (_, info_bytes) = item?;
-            let (_, _, deleted) = decode_data_info(info_bytes.value())?;
-            if !deleted {
-                count += 1;
-            }
-        }
+        let decoded = decode_data_info(&legacy).unwrap();
+        assert_eq!(decoded.timestamp, timestamp);
+        assert_eq!(decoded.codec, CODEC_NONE);
+        assert!(!decoded.deleted);
 
-        Ok(count)
+        let info = DataInfo::new(Encoding::APPLICATION_JSON, timestamp, 2);
+        let decoded = decode_data_info(&encode_data_info(&info).unwrap()).unwrap();
+        assert_eq!(decoded.codec, 2);
     }
 
-    /// Clear all entries from the storage.
-    pub fn clear(&self) -> Result<()> {
-        if self.config.read_only {
-            return Err(RedbBackendError::other("Storage is read-only"));
+    #[test]
+    fn test_compression_settings_can_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("compressed.redb");
+        let id = TimestampId::rand();
+        let json = r#"{"sensor":"temperature","value":23.5}"#.repeat(50);
+
+        let open = |compression| {
+            let config = RedbStorageConfig::new().with_compression(compression);
+            RedbStorage::new(&db_path, config, "compressed".to_string()).unwrap()
+        };
+
+        let storage = open(Compression::Lz4);
+        storage.put("lz4", version(&json, 100, id)).unwrap();
+        storage.put("small", version("tiny", 100, id)).unwrap();
+        let compressed_usage = storage.usage().unwrap();
+        assert!(compressed_usage.bytes < json.len() as u64);
+        drop(storage);
+
+        // Entries keep the codec they were written with
+        let storage = open(Compression::None);
+        storage.put("raw", version(&json, 100, id)).unwrap();
+        let stored: Vec<_> = storage
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|(key, value)| (key, value.payload))
+            .collect();
+        assert_eq!(
+            stored,
+            vec![
+                ("lz4".to_string(), json.as_bytes().to_vec()),
+                ("raw".to_string(), json.as_bytes().to_vec()),
+                ("small".to_string(), b"tiny".to_vec()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_compressed_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = RedbStorageConfig::new()
+            .with_keep_history(true)
+            .with_compression(Compression::Zstd { level: 5 })
+            .with_compression_min_size(0);
+        let storage = RedbStorage::new(
+            temp_dir.path().join("zstd.redb"),
+            config,
+            "zstd".to_string(),
+        )
+        .unwrap();
+        let id = TimestampId::rand();
+        let payload = "abcd".repeat(100);
+
+        storage.put("key", version(&payload, 100, id)).unwrap();
+        storage.put("key", version(&payload, 200, id)).unwrap();
+
+        assert_eq!(
+            storage.get("key").unwrap().unwrap().payload,
+            payload.as_bytes()
+        );
+        let versions = storage.get_history("key", ..).unwrap();
+        assert_eq!(versions.len(), 2);
+        assert!(versions.iter().all(|v| v.payload == payload.as_bytes()));
+    }
+
+    fn encryption_key(dir: &TempDir, name: &str, byte: u8) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, [byte; 32]).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_encrypted_storage_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("encrypted.redb");
+        let key1 = encryption_key(&temp_dir, "key1", 1);
+        let config = RedbStorageConfig::new()
+            .with_keep_history(true)
+            .with_compression(Compression::Lz4)
+            .with_encryption(EncryptionConfig::new(1).with_key_file(1, key1));
+        let storage = RedbStorage::new(&db_path, config, "encrypted".to_string()).unwrap();
+        let id = TimestampId::rand();
+        let secret = "top-secret-reading ".repeat(10);
+        let encoding = Encoding::TEXT_PLAIN.with_schema("utf-8");
+
+        let value = StoredValue::new(
+            secret.as_bytes().to_vec(),
+            Timestamp::new(NTP64(100), id),
+            encoding.clone(),
+        );
+        storage.put("sensor/secret", value).unwrap();
+
+        let stored = storage.get("sensor/secret").unwrap().unwrap();
+        assert_eq!(stored.payload, secret.as_bytes());
+        assert_eq!(stored.encoding, encoding);
+        assert_eq!(storage.get_all().unwrap().len(), 1);
+        assert_eq!(storage.get_history("sensor/secret", ..).unwrap().len(), 1);
+        drop(storage);
+
+        // Neither the payload nor the encoding schema is written in clear
+        let file = std::fs::read(&db_path).unwrap();
+        for needle in [&b"top-secret"[..], b"utf-8"] {
+            assert!(!file.windows(needle.len()).any(|w| w == needle));
         }
+    }
 
-        info!("Clearing all entries from storage");
+    #[test]
+    fn test_encryption_key_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("rotation.redb");
+        let key1 = encryption_key(&temp_dir, "key1", 1);
+        let key2 = encryption_key(&temp_dir, "key2", 2);
+        let id = TimestampId::rand();
 
-        let write_txn = self.db.begin_write()?;
-        {
-            // Delete and recreate both tables - much more efficient than removing keys one by one
-            write_txn.delete_table(PAYLOADS_TABLE)?;
-            write_txn.delete_table(DATA_INFO_TABLE)?;
+        let open = |encryption: Option<EncryptionConfig>| {
+            let mut config = RedbStorageConfig::new();
+            if let Some(encryption) = encryption {
+                config = config.with_encryption(encryption);
+            }
+            RedbStorage::new(&db_path, config, "rotation".to_string())
+        };
+
+        // Plaintext entries stay readable once encryption is enabled
+        let storage = open(None).unwrap();
+        storage.put("plain", version("p", 100, id)).unwrap();
+        drop(storage);
+
+        let storage = open(Some(
+            EncryptionConfig::new(1).with_key_file(1, key1.clone()),
+        ))
+        .unwrap();
+        storage.put("old", version("o", 100, id)).unwrap();
+        drop(storage);
+
+        let rotated = EncryptionConfig::new(2)
+            .with_key_file(1, key1.clone())
+            .with_key_file(2, key2.clone());
+        let storage = open(Some(rotated)).unwrap();
+        storage.put("new", version("n", 100, id)).unwrap();
+        let payloads: Vec<_> = storage
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|(key, value)| (key, value.payload))
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![
+                ("new".to_string(), b"n".to_vec()),
+                ("old".to_string(), b"o".to_vec()),
+                ("plain".to_string(), b"p".to_vec()),
+            ]
+        );
+        drop(storage);
+
+        // Entries of a retired key can no longer be decrypted
+        let storage = open(Some(EncryptionConfig::new(2).with_key_file(2, key2))).unwrap();
+        assert_eq!(storage.get("new").unwrap().unwrap().payload, b"n");
+        assert!(matches!(
+            storage.get("old"),
+            Err(RedbBackendError::DecryptionError(_))
+        ));
+        drop(storage);
+
+        // A different key under the same id fails authentication
+        let wrong = encryption_key(&temp_dir, "wrong", 9);
+        let storage = open(Some(EncryptionConfig::new(1).with_key_file(1, wrong))).unwrap();
+        assert!(matches!(
+            storage.get("old"),
+            Err(RedbBackendError::DecryptionError(_))
+        ));
+        drop(storage);
+
+        let storage = open(None).unwrap();
+        assert_eq!(storage.get("plain").unwrap().unwrap().payload, b"p");
+        assert!(matches!(
+            storage.get("old"),
+            Err(RedbBackendError::DecryptionError(_))
+        ));
+    }
 
-            // Recreate the tables
-            write_txn.open_table(PAYLOADS_TABLE)?;
-            write_txn.open_table(DATA_INFO_TABLE)?;
+    /// Overwrite raw records, bypassing the storage API.
+    fn write_raw(
+        storage: &RedbStorage,
+        table: TableDefinition<&[u8], &[u8]>,
+        key: &[u8],
+        value: Option<&[u8]>,
+    ) {
+        let write_txn = storage.db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(table).unwrap();
+            match value {
+                Some(value) => table.insert(key, value).unwrap(),
+                None => table.remove(key).unwrap(),
+            };
         }
-        write_txn.commit()?;
+        write_txn.commit().unwrap();
+    }
 
-        info!("Storage cleared");
-        Ok(())
+    #[test]
+    fn test_checksum_mismatch_detected_on_read() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+        storage.put("key", version("payload", 100, id)).unwrap();
+
+        write_raw(
+            &storage,
+            storage.tables.payloads(),
+            b"key",
+            Some(b"pAyload"),
+        );
+
+        assert!(matches!(
+            storage.get("key"),
+            Err(RedbBackendError::CorruptionError(_))
+        ));
+        assert!(matches!(
+            storage.get_all(),
+            Err(RedbBackendError::CorruptionError(_))
+        ));
     }
 
-    /// Encode a key string into an existing buffer (zero-allocation).
-    fn encode_key_into(&self, key: &str, buffer: &mut Vec<u8>) -> Result<()> {
-        buffer.extend_from_slice(key.as_bytes());
-        Ok(())
+    #[test]
+    fn test_list_entries_metadata() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+        storage.put("a", version("a", 100, id)).unwrap();
+        storage.put("b", version("b", 200, id)).unwrap();
+        storage.put("c", version("c", 300, id)).unwrap();
+        storage.delete("c", Timestamp::new(NTP64(400), id)).unwrap();
+
+        // Payloads are not read, so a corrupted one does not fail the listing
+        write_raw(
+            &storage,
+            storage.tables.payloads(),
+            b"a",
+            Some(b"corrupted"),
+        );
+        assert!(storage.get_all().is_err());
+
+        assert_eq!(
+            storage.list_entries_metadata().unwrap(),
+            vec![
+                ("a".to_string(), Timestamp::new(NTP64(100), id)),
+                ("b".to_string(), Timestamp::new(NTP64(200), id)),
+            ]
+        );
     }
 
-    /// Decode key bytes back to a string.
-    fn decode_key(&self, bytes: &[u8]) -> Result<String> {
-        String::from_utf8(bytes.to_vec())
-            .map_err(|e| RedbBackendError::serialization(format!("Invalid UTF-8 in key: {}", e)))
+    #[test]
+    fn test_get_view_shares_payload() {
+        for layout in [StorageLayout::TwoTables, StorageLayout::SingleTable] {
+            let (storage, _temp) =
+                create_storage_with(RedbStorageConfig::new().with_layout(layout));
+            let id = TimestampId::rand();
+            storage.put("key", version("payload", 100, id)).unwrap();
+
+            let view = storage.get_view("key").unwrap().unwrap();
+            assert!(view.is_zero_copy());
+            assert_eq!(view.payload(), b"payload");
+            assert_eq!(view.timestamp(), &Timestamp::new(NTP64(100), id));
+            assert_eq!(view.encoding(), &Encoding::ZENOH_STRING);
+
+            // The view keeps its snapshot while the storage is updated
+            storage.put("key", version("updated", 200, id)).unwrap();
+            assert_eq!(view.into_zbytes().to_bytes().as_ref(), b"payload");
+
+            storage
+                .delete("key", Timestamp::new(NTP64(300), id))
+                .unwrap();
+            assert!(storage.get_view("key").unwrap().is_none());
+            assert!(storage.get_view("missing").unwrap().is_none());
+        }
     }
 
-    /// Check if a key matches a wildcard pattern.
-    /// Supports '*' and '**' wildcards.
-    fn matches_wildcard(key: &str, pattern: &str) -> bool {
-        let key_parts: Vec<&str> = key.split('/').collect();
-        let pattern_parts: Vec<&str> = pattern.split('/').collect();
-        matches_parts(&key_parts, &pattern_parts)
+    #[test]
+    fn test_get_view_decodes_compressed_payload() {
+        let (storage, _temp) = create_storage_with(
+            RedbStorageConfig::new()
+                .with_compression(Compression::Lz4)
+                .with_compression_min_size(0),
+        );
+        let id = TimestampId::rand();
+        let payload = "compressible ".repeat(100);
+        storage.put("key", version(&payload, 100, id)).unwrap();
+
+        let view = storage.get_view("key").unwrap().unwrap();
+        assert!(!view.is_zero_copy());
+        assert_eq!(view.payload(), payload.as_bytes());
+    }
+
+    #[test]
+    fn test_get_view_verifies_checksum() {
+        let (storage, _temp) = create_test_storage();
+        let id = TimestampId::rand();
+        storage.put("key", version("payload", 100, id)).unwrap();
+
+        write_raw(
+            &storage,
+            storage.tables.payloads(),
+            b"key",
+            Some(b"pAyload"),
+        );
+        assert!(matches!(
+            storage.get_view("key"),
+            Err(RedbBackendError::CorruptionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_legacy_data_info_without_checksum_is_not_verified() {
+        let timestamp = Timestamp::new(NTP64(42), TimestampId::rand());
+        let info = DataInfo::new(Encoding::APPLICATION_JSON, timestamp, CODEC_NONE);
+        let decoded = decode_data_info(&encode_data_info(&info).unwrap()).unwrap();
+        assert_eq!(decoded.checksum, None);
+        assert!(decoded.verify_checksum(b"anything"));
+
+        let info = info.with_checksum(b"payload");
+        let decoded = decode_data_info(&encode_data_info(&info).unwrap()).unwrap();
+        assert!(decoded.verify_checksum(b"payload"));
+        assert!(!decoded.verify_checksum(b"pAyload"));
     }
-}
 
-/// Recursive helper function for wildcard matching.
-fn matches_parts(key_parts: &[&str], pattern_parts: &[&str]) -> bool {
-    match (key_parts.first(), pattern_parts.first()) {
-        (None, None) => true,
-        (Some(_), None) => false,
-        (None, Some(&"**")) => matches_parts(key_parts, &pattern_parts[1..]),
-        (None, Some(_)) => false,
-        (Some(_), Some(&"**")) => {
-            matches_parts(key_parts, &pattern_parts[1..])
-                || matches_parts(&key_parts[1..], pattern_parts)
+    /// Store `ok`, `deleted`, and one entry per kind of inconsistency.
+    fn store_inconsistent_entries(storage: &RedbStorage) {
+        let id = TimestampId::rand();
+
+        for key in ["ok", "deleted", "orphaned_info", "undecodable", "mismatch"] {
+            storage.put(key, version("payload", 100, id)).unwrap();
         }
-        (Some(_), Some(&"*")) => matches_parts(&key_parts[1..], &pattern_parts[1..]),
-        (Some(&key_part), Some(&pattern_part)) => {
-            key_part == pattern_part && matches_parts(&key_parts[1..], &pattern_parts[1..])
+        storage
+            .delete("deleted", Timestamp::new(NTP64(200), id))
+            .unwrap();
+        write_raw(
+            storage,
+            storage.tables.payloads(),
+            b"orphaned_payload",
+            Some(b"payload"),
+        );
+        write_raw(storage, storage.tables.payloads(), b"orphaned_info", None);
+        write_raw(
+            storage,
+            storage.tables.data_info(),
+            b"undecodable",
+            Some(b"\xff"),
+        );
+        write_raw(
+            storage,
+            storage.tables.payloads(),
+            b"mismatch",
+            Some(b"pAyload"),
+        );
+    }
+
+    fn issue(key: &str, version: Option<u64>, kind: IntegrityIssueKind) -> IntegrityIssue {
+        IntegrityIssue {
+            key: key.to_string(),
+            version: version.map(NTP64),
+            kind,
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
-    use zenoh::time::TimestampId;
+    fn expected_issues() -> Vec<IntegrityIssue> {
+        vec![
+            issue("mismatch", None, IntegrityIssueKind::ChecksumMismatch),
+            issue("orphaned_info", None, IntegrityIssueKind::OrphanedDataInfo),
+            issue("undecodable", None, IntegrityIssueKind::UndecodableDataInfo),
+            issue(
+                "orphaned_payload",
+                None,
+                IntegrityIssueKind::OrphanedPayload,
+            ),
+        ]
+    }
 
-    fn create_test_storage() -> (RedbStorage, TempDir) {
-        let temp_dir = TempDir::new().unwrap();
-        let db_path = temp_dir.path().join("test.redb");
-        let config = RedbStorageConfig::default();
-        let storage = RedbStorage::new(db_path, config, "test".to_string()).unwrap();
-        (storage, temp_dir)
+    #[test]
+    fn test_verify_reports_inconsistent_entries() {
+        let (storage, _temp) =
+            create_storage_with(RedbStorageConfig::new().with_keep_history(false));
+        store_inconsistent_entries(&storage);
+
+        let report = storage.verify().unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.checked, 6);
+        assert_eq!(report.issues, expected_issues());
     }
 
     #[test]
-    fn test_put_and_get() {
-        let (storage, _temp) = create_test_storage();
+    fn test_verify_reports_history_versions() {
+        let (storage, _temp) =
+            create_storage_with(RedbStorageConfig::new().with_keep_history(true));
+        store_inconsistent_entries(&storage);
+        let id = TimestampId::rand();
+        let version_key = history_key(b"ok", &Timestamp::new(NTP64(100), id));
+        write_raw(
+            &storage,
+            storage.tables.history_payloads(),
+            &version_key,
+            Some(b"pAyload"),
+        );
 
-        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
-        let encoding = Encoding::ZENOH_BYTES;
-        let payload = b"test data".to_vec();
+        let report = storage.verify().unwrap();
+        let mut expected = expected_issues();
+        expected.push(issue("ok", Some(100), IntegrityIssueKind::OrphanedPayload));
+        assert_eq!(report.issues, expected);
+    }
 
-        let value = StoredValue::new(payload.clone(), timestamp, encoding.clone());
-        storage.put("test/key", value).unwrap();
+    #[test]
+    fn test_repair_removes_inconsistent_entries() {
+        let (storage, _temp) =
+            create_storage_with(RedbStorageConfig::new().with_keep_history(false));
+        store_inconsistent_entries(&storage);
 
-        let retrieved = storage.get("test/key").unwrap().unwrap();
-        assert_eq!(retrieved.payload, payload);
-        assert_eq!(retrieved.timestamp, timestamp);
-        assert_eq!(retrieved.encoding.id(), encoding.id());
+        let report = storage.repair(RepairMode::Remove).unwrap();
+        assert_eq!(report.issues, expected_issues());
+        assert!(storage.verify().unwrap().is_ok());
+
+        // Only the remaining entry is counted
+        assert_eq!(
+            storage.usage().unwrap(),
+            StorageUsage {
+                entries: 1,
+                bytes: ("ok".len() + "payload".len()) as u64,
+            }
+        );
+        let keys: Vec<_> = storage
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec!["ok".to_string()]);
+
+        let read_txn = storage.db.begin_read().unwrap();
+        assert!(read_txn.open_table(storage.tables.quarantine()).is_err());
     }
 
     #[test]
-    fn test_delete() {
-        let (storage, _temp) = create_test_storage();
+    fn test_repair_quarantines_inconsistent_entries() {
+        let (storage, _temp) =
+            create_storage_with(RedbStorageConfig::new().with_keep_history(false));
+        store_inconsistent_entries(&storage);
 
-        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
-        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
-        storage.put("test/key", value).unwrap();
+        let report = storage.repair(RepairMode::Quarantine).unwrap();
+        assert_eq!(report.issues.len(), 4);
+        assert!(storage.verify().unwrap().is_ok());
+        assert!(storage.repair(RepairMode::Quarantine).unwrap().is_ok());
 
-        storage.delete("test/key").unwrap();
-        assert!(storage.get("test/key").unwrap().is_none());
+        let read_txn = storage.db.begin_read().unwrap();
+        let quarantine = read_txn.open_table(storage.tables.quarantine()).unwrap();
+        let records: Vec<(String, Vec<u8>)> = quarantine
+            .iter()
+            .unwrap()
+            .map(|item| {
+                let (key, _) = item.unwrap();
+                let (table, key) = key.value();
+                (table.to_string(), key.to_vec())
+            })
+            .collect();
+        let record = |table: &str, key: &[u8]| (table.to_string(), key.to_vec());
+        assert_eq!(
+            records,
+            vec![
+                record("data_info", b"mismatch"),
+                record("data_info", b"orphaned_info"),
+                record("data_info", b"undecodable"),
+                record("payloads", b"mismatch"),
+                record("payloads", b"orphaned_payload"),
+                record("payloads", b"undecodable"),
+            ]
+        );
     }
 
     #[test]
-    fn test_get_all() {
-        let (storage, _temp) = create_test_storage();
+    fn test_single_table_layout() {
+        let config = RedbStorageConfig::new()
+            .with_keep_history(true)
+            .with_ttl_override("ttl/".to_string(), 60)
+            .with_max_entries(3)
+            .with_quota_policy(QuotaPolicy::EvictOldest);
+        let (storage, _temp) = create_storage_with(config.with_layout(StorageLayout::SingleTable));
+        let id = TimestampId::rand();
 
-        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
-        let value1 = StoredValue::new(b"data1".to_vec(), timestamp, Encoding::ZENOH_BYTES);
-        let value2 = StoredValue::new(b"data2".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        assert_eq!(
+            storage.put("a/1", version("one", 100, id)).unwrap(),
+            WriteOutcome::Inserted
+        );
+        assert_eq!(
+            storage.put("a/1", version("uno", 200, id)).unwrap(),
+            WriteOutcome::Replaced
+        );
+        assert_eq!(
+            storage.put("a/1", version("old", 150, id)).unwrap(),
+            WriteOutcome::Inserted
+        );
+        storage.put("a/2", version("two", 100, id)).unwrap();
+        storage.put("b/1", version("", 100, id)).unwrap();
 
-        storage.put("key1", value1).unwrap();
-        storage.put("key2", value2).unwrap();
+        assert_eq!(storage.get("a/1").unwrap().unwrap().payload, b"uno");
+        assert_eq!(storage.get("b/1").unwrap().unwrap().payload, b"");
+        assert_eq!(storage.get_by_prefix("a/").unwrap().len(), 2);
+        assert_eq!(storage.get_history("a/1", ..).unwrap().len(), 3);
+        assert_eq!(
+            storage.usage().unwrap(),
+            StorageUsage {
+                entries: 3,
+                bytes: 3 * 3 + 3 + 3
+            }
+        );
 
-        let all = storage.get_all().unwrap();
-        assert_eq!(all.len(), 2);
+        // Deletes keep a tombstone without payload
+        assert_eq!(
+            storage
+                .delete("a/2", Timestamp::new(NTP64(300), id))
+                .unwrap(),
+            WriteOutcome::Deleted
+        );
+        assert!(storage.get("a/2").unwrap().is_none());
+        assert_eq!(storage.count().unwrap(), 2);
+        storage.put("a/2", version("two", 250, id)).unwrap();
+        assert!(storage.get("a/2").unwrap().is_none());
+
+        // Quotas evict the oldest entries
+        storage.put("c/1", version("c", 400, id)).unwrap();
+        storage.put("c/2", version("c", 500, id)).unwrap();
+        let keys: Vec<_> = storage
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec!["a/1", "c/1", "c/2"]);
+
+        // Expiry and garbage collection
+        let value = StoredValue::new(b"data".to_vec(), age(120), Encoding::ZENOH_BYTES);
+        storage.put("ttl/1", value).unwrap();
+        assert!(storage.get("ttl/1").unwrap().is_none());
+        assert_eq!(storage.expire_entries().unwrap(), 1);
+        // Tombstones of the delete, the two evicted entries and the expired one
+        assert_eq!(storage.garbage_collect(Duration::from_secs(60)).unwrap(), 4);
+        assert!(storage.verify().unwrap().is_ok());
+
+        storage.clear().unwrap();
+        assert_eq!(storage.count().unwrap(), 0);
+        assert!(storage.get_history("a/1", ..).unwrap().is_empty());
     }
 
     #[test]
-    fn test_get_by_prefix() {
-        let (storage, _temp) = create_test_storage();
+    fn test_single_table_verify_and_repair() {
+        let (storage, _temp) =
+            create_storage_with(RedbStorageConfig::new().with_layout(StorageLayout::SingleTable));
+        let id = TimestampId::rand();
+        for key in ["ok", "undecodable", "mismatch"] {
+            storage.put(key, version("payload", 100, id)).unwrap();
+        }
 
-        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
-        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        let corrupt = |key: &[u8], f: &dyn Fn(&mut Vec<u8>)| {
+            let write_txn = storage.db.begin_write().unwrap();
+            {
+                let mut records = write_txn.open_table(storage.tables.records()).unwrap();
+                let mut record = records.get(key).unwrap().unwrap().value().to_vec();
+                f(&mut record);
+                records.insert(key, record.as_slice()).unwrap();
+            }
+            write_txn.commit().unwrap();
+        };
+        corrupt(b"undecodable", &|record| record.truncate(3));
+        corrupt(b"mismatch", &|record| *record.last_mut().unwrap() ^= 0xff);
 
-        storage.put("test/foo", value.clone()).unwrap();
-        storage.put("test/bar", value.clone()).unwrap();
-        storage.put("other/baz", value).unwrap();
+        assert!(matches!(
+            storage.get("mismatch"),
+            Err(RedbBackendError::CorruptionError(_))
+        ));
+        let expected = vec![
+            issue("mismatch", None, IntegrityIssueKind::ChecksumMismatch),
+            issue("undecodable", None, IntegrityIssueKind::UndecodableDataInfo),
+        ];
+        assert_eq!(storage.verify().unwrap().issues, expected);
 
-        let results = storage.get_by_prefix("test/").unwrap();
-        assert_eq!(results.len(), 2);
+        assert_eq!(
+            storage.repair(RepairMode::Quarantine).unwrap().issues,
+            expected
+        );
+        assert!(storage.verify().unwrap().is_ok());
+        assert_eq!(storage.usage().unwrap().entries, 1);
     }
 
     #[test]
-    fn test_wildcard_matching() {
-        assert!(RedbStorage::matches_wildcard("a/b/c", "a/b/c"));
-        assert!(RedbStorage::matches_wildcard("a/b/c", "a/*/c"));
-        assert!(RedbStorage::matches_wildcard("a/b/c", "a/**/c"));
-        assert!(RedbStorage::matches_wildcard("a/b/c/d", "a/**/d"));
-        assert!(!RedbStorage::matches_wildcard("a/b/c", "a/b/d"));
+    fn test_layout_conversion() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("convert.redb");
+        let id = TimestampId::rand();
+        let open = |layout| {
+            let config = RedbStorageConfig::new()
+                .with_keep_history(true)
+                .with_layout(layout);
+            RedbStorage::new(&db_path, config, "convert".to_string()).unwrap()
+        };
+        let contents = |storage: &RedbStorage| {
+            let values: Vec<_> = storage
+                .get_all()
+                .unwrap()
+                .into_iter()
+                .map(|(key, value)| (key, value.payload, value.timestamp))
+                .collect();
+            let history: Vec<_> = storage
+                .get_history("a", ..)
+                .unwrap()
+                .into_iter()
+                .map(|value| value.payload)
+                .collect();
+            (values, history, storage.usage().unwrap())
+        };
+        let table_names = |storage: &RedbStorage| {
+            let read_txn = storage.db.begin_read().unwrap();
+            let mut names: Vec<_> = read_txn
+                .list_tables()
+                .unwrap()
+                .map(|table| table.name().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+
+        let storage = open(StorageLayout::TwoTables);
+        storage.put("a", version("first", 100, id)).unwrap();
+        storage.put("a", version("second", 200, id)).unwrap();
+        storage.put("b", version("bee", 100, id)).unwrap();
+        storage.delete("b", Timestamp::new(NTP64(300), id)).unwrap();
+        storage.put("c", version("sea", 100, id)).unwrap();
+        let expected = contents(&storage);
+        drop(storage);
+
+        let storage = open(StorageLayout::SingleTable);
+        assert_eq!(contents(&storage), expected);
+        assert_eq!(
+            table_names(&storage),
+            vec!["history_records", "metadata", "records", "usage"]
+        );
+        // The tombstone survived the conversion
+        storage.put("b", version("late", 250, id)).unwrap();
+        assert!(storage.get("b").unwrap().is_none());
+        drop(storage);
+
+        let storage = open(StorageLayout::TwoTables);
+        assert_eq!(contents(&storage), expected);
+        assert_eq!(
+            table_names(&storage),
+            vec![
+                "data_info",
+                "history_data_info",
+                "history_payloads",
+                "metadata",
+                "payloads",
+                "usage"
+            ]
+        );
+        assert!(storage.verify().unwrap().is_ok());
     }
 
     #[test]
-    fn test_matches_parts_function() {
-        let key_parts = vec!["a", "b", "c"];
-        let pattern_parts = vec!["a", "*", "c"];
-        assert!(matches_parts(&key_parts, &pattern_parts));
+    fn test_strip_prefix() {
+        let config = RedbStorageConfig::new()
+            .with_key_expr("demo/app/**".to_string())
+            .with_strip_prefix(true);
+        let (storage, _temp) = create_storage_with(config);
+        let id = TimestampId::rand();
+        storage.put("demo/app", version("root", 100, id)).unwrap();
+        storage.put("demo/app/a/1", version("a1", 100, id)).unwrap();
+        storage.put("demo/app/b/1", version("b1", 100, id)).unwrap();
+
+        let read_txn = storage.db.begin_read().unwrap();
+        let data_info = read_txn.open_table(storage.tables.data_info()).unwrap();
+        let stored: Vec<_> = data_info
+            .iter()
+            .unwrap()
+            .map(|item| item.unwrap().0.value().to_vec())
+            .collect();
+        assert_eq!(
+            stored,
+            vec![
+                NONE_KEY.as_bytes().to_vec(),
+                b"a/1".to_vec(),
+                b"b/1".to_vec()
+            ]
+        );
+
+        // Keys are restored on reads
+        let keys = |entries: Vec<(String, StoredValue)>| -> Vec<String> {
+            entries.into_iter().map(|(key, _)| key).collect()
+        };
+        assert_eq!(storage.get("demo/app").unwrap().unwrap().payload, b"root");
+        assert_eq!(
+            keys(storage.get_all().unwrap()),
+            vec!["demo/app", "demo/app/a/1", "demo/app/b/1"]
+        );
+        assert_eq!(
+            keys(storage.get_by_prefix("demo/app/a").unwrap()),
+            vec!["demo/app/a/1"]
+        );
+        assert_eq!(
+            keys(storage.get_by_prefix("demo/app/").unwrap()),
+            vec!["demo/app/a/1", "demo/app/b/1"]
+        );
+        assert_eq!(
+            keys(storage.get_by_prefix("demo/apple").unwrap()),
+            Vec::<String>::new()
+        );
+        assert_eq!(keys(storage.get_by_prefix("demo").unwrap()).len(), 3);
+        assert_eq!(
+            keys(storage.get_by_wildcard("demo/app/*/1").unwrap()),
+            vec!["demo/app/a/1", "demo/app/b/1"]
+        );
+        assert_eq!(keys(storage.get_by_wildcard("demo/**").unwrap()).len(), 3);
+        assert_eq!(
+            storage.list_entries_metadata().unwrap()[0].0,
+            "demo/app".to_string()
+        );
     }
 
     #[test]
-    fn test_count() {
-        let (storage, _temp) = create_test_storage();
+    fn test_key_expr_rejects_outside_keys() {
+        let config = RedbStorageConfig::new().with_key_expr("demo/*/temp".to_string());
+        let (storage, _temp) = create_storage_with(config);
+        let id = TimestampId::rand();
+        storage.put("demo/a/temp", version("a", 100, id)).unwrap();
 
-        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
-        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        for key in ["demo/a/humidity", "other/a/temp", "demo/a/temp/"] {
+            assert!(matches!(
+                storage.put(key, version("x", 100, id)),
+                Err(RedbBackendError::InvalidKeyExpression(_))
+            ));
+        }
+        assert!(storage.get("other/a/temp").is_err());
+        // Keys are stored in full when the prefix is not stripped
+        assert_eq!(storage.get_all().unwrap()[0].0, "demo/a/temp");
 
-        storage.put("key1", value.clone()).unwrap();
-        storage.put("key2", value.clone()).unwrap();
-        storage.put("key3", value).unwrap();
+        let invalid = RedbStorageConfig::new().with_key_expr("demo/app/".to_string());
+        let temp_dir = TempDir::new().unwrap();
+        assert!(matches!(
+            RedbStorage::new(
+                temp_dir.path().join("invalid.redb"),
+                invalid,
+                "x".to_string()
+            ),
+            Err(RedbBackendError::ConfigError(_))
+        ));
+    }
 
-        assert_eq!(storage.count().unwrap(), 3);
+    #[test]
+    fn test_key_scope_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("scope.redb");
+        let open =
+            |config: RedbStorageConfig| RedbStorage::new(&db_path, config, "scope".to_string());
+        let stripped = RedbStorageConfig::new()
+            .with_key_expr("demo/app/**".to_string())
+            .with_strip_prefix(true);
+        let id = TimestampId::rand();
 
-        storage.delete("key2").unwrap();
-        assert_eq!(storage.count().unwrap(), 2);
+        let storage = open(stripped.clone()).unwrap();
+        storage.put("demo/app", version("root", 100, id)).unwrap();
+        storage.put("demo/app/a", version("a", 100, id)).unwrap();
+        storage
+            .delete("demo/app/b", Timestamp::new(NTP64(100), id))
+            .unwrap();
+        let usage = storage.usage().unwrap();
+        drop(storage);
+
+        for config in [
+            stripped.clone().with_strip_prefix(false),
+            stripped.clone().with_key_expr("demo/**".to_string()),
+            RedbStorageConfig::new(),
+            stripped.clone().with_table_name("other".to_string()),
+        ] {
+            assert!(matches!(
+                open(config),
+                Err(RedbBackendError::ConfigMismatch(_))
+            ));
+        }
+
+        // Migrating the keys stores them in full
+        let full = RedbStorageConfig::new()
+            .with_key_expr("demo/**".to_string())
+            .with_migrate_keys(true);
+        let storage = open(full).unwrap();
+        let keys: Vec<_> = storage
+            .list_entries_metadata()
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec!["demo/app", "demo/app/a"]);
+        assert_eq!(storage.get("demo/app").unwrap().unwrap().payload, b"root");
+        // The tombstone was kept
+        storage.put("demo/app/b", version("late", 50, id)).unwrap();
+        assert!(storage.get("demo/app/b").unwrap().is_none());
+        assert_eq!(storage.usage().unwrap().entries, usage.entries);
+        assert!(storage.usage().unwrap().bytes > usage.bytes);
+        drop(storage);
+
+        // Keys outside the new key expression fail the migration, leaving the file
+        let narrow = RedbStorageConfig::new()
+            .with_key_expr("demo/app/a".to_string())
+            .with_migrate_keys(true);
+        assert!(matches!(
+            open(narrow),
+            Err(RedbBackendError::InvalidKeyExpression(_))
+        ));
+        let storage = open(RedbStorageConfig::new().with_key_expr("demo/**".to_string())).unwrap();
+        assert_eq!(storage.get_all().unwrap().len(), 2);
     }
 
     #[test]
-    fn test_clear() {
-        let (storage, _temp) = create_test_storage();
+    fn test_migrate_encrypted_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("encrypted.redb");
+        let key1 = encryption_key(&temp_dir, "key1", 1);
+        let config = RedbStorageConfig::new()
+            .with_keep_history(true)
+            .with_encryption(EncryptionConfig::new(1).with_key_file(1, key1))
+            .with_key_expr("sensor/**".to_string());
+        let id = TimestampId::rand();
 
-        let timestamp = Timestamp::new(NTP64(123456789), TimestampId::rand());
-        let value = StoredValue::new(b"data".to_vec(), timestamp, Encoding::ZENOH_BYTES);
+        let storage = RedbStorage::new(&db_path, config.clone(), "enc".to_string()).unwrap();
+        storage
+            .put("sensor/temp", version("first", 100, id))
+            .unwrap();
+        storage
+            .put("sensor/temp", version("second", 200, id))
+            .unwrap();
+        drop(storage);
 
-        storage.put("key1", value.clone()).unwrap();
-        storage.put("key2", value).unwrap();
+        // Ciphertexts are bound to the stored keys, so they are sealed again
+        let config = config.with_strip_prefix(true).with_migrate_keys(true);
+        let storage = RedbStorage::new(&db_path, config, "enc".to_string()).unwrap();
+        assert_eq!(
+            storage.get("sensor/temp").unwrap().unwrap().payload,
+            b"second"
+        );
+        let versions = storage.get_history("sensor/temp", ..).unwrap();
+        let payloads: Vec<_> = versions.iter().map(|v| v.payload.as_slice()).collect();
+        assert_eq!(payloads, vec![&b"first"[..], b"second"]);
+        assert!(storage.verify().unwrap().is_ok());
 
-        storage.clear().unwrap();
-        assert_eq!(storage.count().unwrap(), 0);
+        // The staging tables replaced the tables
+        let read_txn = storage.db.begin_read().unwrap();
+        assert!(
+            !read_txn
+                .list_tables()
+                .unwrap()
+                .any(|table| table.name().ends_with("~staging"))
+        );
+    }
+
+    #[test]
+    fn test_table_names() {
+        let tables = TableNames::new(DEFAULT_TABLE_NAME).unwrap();
+        assert_eq!(tables.payloads().name(), "payloads");
+        assert_eq!(tables.metadata().name(), "metadata");
+
+        let tables = TableNames::new("sensors").unwrap();
+        assert_eq!(tables.payloads().name(), "sensors:payloads");
+        assert_eq!(tables.history_records().name(), "sensors:history_records");
+        assert!(tables.contains("sensors:usage"));
+        assert!(!tables.contains("usage"));
+        assert_eq!(TableNames::table_name_of("sensors:usage"), "sensors");
+        assert_eq!(TableNames::table_name_of("usage"), DEFAULT_TABLE_NAME);
+
+        for invalid in ["", "a:b"] {
+            assert!(matches!(
+                TableNames::new(invalid),
+                Err(RedbBackendError::ConfigError(_))
+            ));
+        }
     }
 }
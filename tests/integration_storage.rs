@@ -1,10 +1,15 @@
 //! Integration tests for storage operations and data persistence.
 
+use proptest::prelude::*;
 use std::collections::HashSet;
 use tempfile::TempDir;
 use zenoh::bytes::Encoding;
+use zenoh::key_expr::{KeyExpr, OwnedKeyExpr, keyexpr};
 use zenoh::time::{NTP64, Timestamp, TimestampId};
-use zenoh_backend_redb::{RedbBackend, RedbBackendConfig, RedbStorageConfig, StoredValue};
+use zenoh_backend_redb::{
+    RedbBackend, RedbBackendConfig, RedbBackendError, RedbStorageConfig, StorageLayout,
+    StoredValue, WriteOp, WriteOutcome,
+};
 
 /// Helper function to create a test backend and storage.
 fn create_test_storage() -> (RedbBackend, TempDir) {
@@ -45,8 +50,8 @@ fn test_basic_put_get_delete() {
     assert_eq!(retrieved.encoding.id(), value.encoding.id());
 
     // Delete the value
-    storage.delete("test_key").unwrap();
-    // deleted
+    let delete_ts = Timestamp::new(NTP64(12346), TimestampId::rand());
+    storage.delete("test_key", delete_ts).unwrap();
 
     // Verify it's gone
     let retrieved = storage.get("test_key").unwrap();
@@ -72,14 +77,73 @@ fn test_put_overwrite() {
     assert_eq!(retrieved.timestamp.get_time().as_u64(), 200);
 }
 
+#[test]
+fn test_put_out_of_order_delivery() {
+    let (backend, _temp) = create_test_storage();
+    let storage = backend.get_storage("test_storage").unwrap();
+
+    // Samples arrive out of order, e.g. during replication or after a reconnection
+    for time in [300, 100, 500, 200, 400] {
+        let value = test_value(format!("value_{}", time).into_bytes(), time);
+        storage.put("sensor/temp", value).unwrap();
+    }
+
+    // The newest sample wins regardless of arrival order
+    let retrieved = storage.get("sensor/temp").unwrap().unwrap();
+    assert_eq!(retrieved.payload, b"value_500");
+    assert_eq!(retrieved.timestamp.get_time().as_u64(), 500);
+}
+
+#[test]
+fn test_put_outdated_is_reported() {
+    let (backend, _temp) = create_test_storage();
+    let storage = backend.get_storage("test_storage").unwrap();
+
+    let outcome = storage.put("key", test_value(b"v2".to_vec(), 200)).unwrap();
+    assert_eq!(outcome, WriteOutcome::Inserted);
+
+    let outcome = storage.put("key", test_value(b"v1".to_vec(), 100)).unwrap();
+    assert_eq!(outcome, WriteOutcome::Outdated);
+
+    let outcome = storage.put("key", test_value(b"v3".to_vec(), 300)).unwrap();
+    assert_eq!(outcome, WriteOutcome::Replaced);
+}
+
+#[test]
+fn test_delete_out_of_order_delivery() {
+    let (backend, _temp) = create_test_storage();
+    let storage = backend.get_storage("test_storage").unwrap();
+
+    storage
+        .put("key", test_value(b"value".to_vec(), 200))
+        .unwrap();
+
+    // A delete older than the stored value is skipped
+    let old_delete = Timestamp::new(NTP64(100), TimestampId::rand());
+    assert_eq!(
+        storage.delete("key", old_delete).unwrap(),
+        WriteOutcome::Outdated
+    );
+    assert!(storage.get("key").unwrap().is_some());
+
+    // A newer delete removes the value
+    let new_delete = Timestamp::new(NTP64(300), TimestampId::rand());
+    assert_eq!(
+        storage.delete("key", new_delete).unwrap(),
+        WriteOutcome::Deleted
+    );
+    assert!(storage.get("key").unwrap().is_none());
+}
+
 #[test]
 fn test_delete_nonexistent() {
     let (backend, _temp) = create_test_storage();
     let storage = backend.get_storage("test_storage").unwrap();
 
-    // Delete non-existent key should return false
-    storage.delete("nonexistent").unwrap(); // returns ()
-    // Delete was called (no error)
+    // Delete non-existent key should still succeed
+    let delete_ts = Timestamp::new(NTP64(1), TimestampId::rand());
+    let outcome = storage.delete("nonexistent", delete_ts).unwrap();
+    assert_eq!(outcome, WriteOutcome::Deleted);
 }
 
 #[test]
@@ -276,6 +340,113 @@ fn test_wildcard_complex_patterns() {
     assert_eq!(all.len(), 5);
 }
 
+#[test]
+fn test_wildcard_sub_chunk() {
+    let (backend, _temp) = create_test_storage();
+    let storage = backend.get_storage("test_storage").unwrap();
+
+    let keys = [
+        "sensor/temp",
+        "sensor/temperature",
+        "sensor/temp/1",
+        "sensor/humidity",
+        "sensor/room1/temp",
+        "sensor/room2/temp",
+        "sensor/lobby/temp",
+    ];
+    for (i, key) in keys.iter().enumerate() {
+        storage
+            .put(key, test_value(b"value".to_vec(), i as u64))
+            .unwrap();
+    }
+
+    let results = storage.get_by_wildcard("sensor/temp$*").unwrap();
+    let found: HashSet<String> = results.into_iter().map(|(k, _)| k).collect();
+    assert_eq!(
+        found,
+        HashSet::from(["sensor/temp".to_string(), "sensor/temperature".to_string()])
+    );
+
+    let results = storage.get_by_wildcard("sensor/room$*/temp").unwrap();
+    assert_eq!(results.len(), 2);
+}
+
+#[test]
+fn test_wildcard_accepts_key_expr() {
+    let (backend, _temp) = create_test_storage();
+    let storage = backend.get_storage("test_storage").unwrap();
+
+    storage.put("a/b/c", test_value(b"1".to_vec(), 1)).unwrap();
+    storage.put("a/x/c", test_value(b"2".to_vec(), 2)).unwrap();
+
+    let key_expr = KeyExpr::try_from("a/*/c").unwrap();
+    assert_eq!(storage.get_by_wildcard(&key_expr).unwrap().len(), 2);
+
+    let owned = OwnedKeyExpr::new("a/b/**").unwrap();
+    assert_eq!(storage.get_by_wildcard(&owned).unwrap().len(), 1);
+
+    // Invalid key expressions are rejected
+    assert!(storage.get_by_wildcard("a/**/**").is_err());
+}
+
+/// Strategy for a literal key chunk drawn from a small alphabet, to get frequent overlaps.
+fn literal_chunk() -> impl Strategy<Value = String> {
+    prop::sample::select(vec!["a", "b", "ab", "ba", "abc"]).prop_map(String::from)
+}
+
+/// Strategy for a key expression chunk, including `*`, `**` and `$*` wildcards.
+fn pattern_chunk() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => literal_chunk(),
+        1 => Just("*".to_string()),
+        1 => Just("**".to_string()),
+        1 => prop::sample::select(vec!["a$*", "$*b", "a$*b", "$*c"]).prop_map(String::from),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn prop_wildcard_matches_key_expr_intersection(
+        keys in prop::collection::hash_set(
+            prop::collection::vec(literal_chunk(), 1..4).prop_map(|c| c.join("/")),
+            1..24,
+        ),
+        pattern in prop::collection::vec(pattern_chunk(), 1..4).prop_map(|c| c.join("/")),
+    ) {
+        let pattern = OwnedKeyExpr::autocanonize(pattern).unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let config = RedbBackendConfig::new().with_base_dir(temp_dir.path().to_path_buf());
+        let backend = RedbBackend::new(config).unwrap();
+        let storage = backend
+            .create_storage(
+                "prop_storage".to_string(),
+                Some(RedbStorageConfig::new().with_fsync(false)),
+            )
+            .unwrap();
+
+        for (i, key) in keys.iter().enumerate() {
+            storage.put(key, test_value(b"value".to_vec(), i as u64)).unwrap();
+        }
+
+        let found: HashSet<String> = storage
+            .get_by_wildcard(&pattern)
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        let expected: HashSet<String> = keys
+            .iter()
+            .filter(|k| pattern.intersects(keyexpr::new(k.as_str()).unwrap()))
+            .cloned()
+            .collect();
+
+        prop_assert_eq!(found, expected, "pattern: {}", pattern);
+    }
+}
+
 #[test]
 fn test_clear_storage() {
     let (backend, _temp) = create_test_storage();
@@ -561,7 +732,7 @@ fn test_storage_with_prefix_stripping() {
     let backend = RedbBackend::new(config).unwrap();
 
     let storage_config = RedbStorageConfig::new()
-        .with_key_expr("demo/app/".to_string())
+        .with_key_expr("demo/app/**".to_string())
         .with_strip_prefix(true);
 
     let storage = backend
@@ -575,6 +746,24 @@ fn test_storage_with_prefix_stripping() {
     // Should be able to retrieve with full key
     let retrieved = storage.get("demo/app/sensor/temp").unwrap();
     assert!(retrieved.is_some());
+
+    // The key matching the prefix exactly is stored too
+    let value = test_value(b"root".to_vec(), 2);
+    storage.put("demo/app", value).unwrap();
+    let keys: Vec<_> = storage
+        .get_all()
+        .unwrap()
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(keys, vec!["demo/app", "demo/app/sensor/temp"]);
+
+    // Keys outside the key expression are rejected
+    let value = test_value(b"other".to_vec(), 3);
+    assert!(matches!(
+        storage.put("other/app/sensor", value),
+        Err(RedbBackendError::InvalidKeyExpression(_))
+    ));
 }
 
 #[test]
@@ -601,3 +790,91 @@ fn test_batch_operations() {
     storage.clear().unwrap();
     assert_eq!(storage.count().unwrap(), 0);
 }
+
+#[test]
+fn test_streaming_queries() {
+    for layout in [StorageLayout::TwoTables, StorageLayout::SingleTable] {
+        let (backend, _temp) = create_test_storage();
+        let config = RedbStorageConfig::new().with_layout(layout);
+        let storage = backend
+            .create_storage("streamed".to_string(), Some(config))
+            .unwrap();
+
+        let puts = (0..500).map(|i| WriteOp::Put {
+            key: format!("sensor/{:03}/temp", i),
+            value: test_value(vec![i as u8; 1024], 100),
+        });
+        storage.write_batch(puts).unwrap();
+
+        // Entries come in key order and only as many as requested are read
+        let first: Vec<_> = storage
+            .iter_all()
+            .unwrap()
+            .take(3)
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(
+            first,
+            vec!["sensor/000/temp", "sensor/001/temp", "sensor/002/temp"]
+        );
+
+        let matched = storage.iter_by_wildcard("sensor/1$*/temp").unwrap();
+        let mut count = 0;
+        for entry in matched {
+            let (key, value) = entry.unwrap();
+            assert!(key.starts_with("sensor/1"));
+            assert_eq!(value.payload.len(), 1024);
+            count += 1;
+        }
+        assert_eq!(count, 100);
+
+        assert_eq!(storage.iter_by_prefix("sensor/4").unwrap().count(), 100);
+    }
+}
+
+#[test]
+fn test_write_batch() {
+    let (backend, _temp) = create_test_storage();
+    let storage = backend.get_storage("test_storage").unwrap();
+
+    // Batch insert in a single transaction
+    let batch_size = 1000;
+    let puts = (0..batch_size).map(|i| WriteOp::Put {
+        key: format!("batch_key_{}", i),
+        value: test_value(format!("batch_value_{}", i).into_bytes(), 100),
+    });
+    let outcomes = storage.write_batch(puts).unwrap();
+    assert_eq!(outcomes.len(), batch_size);
+    assert!(outcomes.iter().all(|o| *o == WriteOutcome::Inserted));
+    assert_eq!(storage.count().unwrap(), batch_size);
+
+    // Batch delete of the even keys, mixed with outdated puts of the odd keys
+    let ops = (0..batch_size).map(|i| {
+        let key = format!("batch_key_{}", i);
+        if i % 2 == 0 {
+            WriteOp::Delete {
+                key,
+                timestamp: Timestamp::new(NTP64(200), TimestampId::rand()),
+            }
+        } else {
+            WriteOp::Put {
+                key,
+                value: test_value(b"outdated".to_vec(), 50),
+            }
+        }
+    });
+    let outcomes = storage.write_batch(ops).unwrap();
+    for (i, outcome) in outcomes.iter().enumerate() {
+        let expected = if i % 2 == 0 {
+            WriteOutcome::Deleted
+        } else {
+            WriteOutcome::Outdated
+        };
+        assert_eq!(*outcome, expected);
+    }
+
+    assert_eq!(storage.count().unwrap(), batch_size / 2);
+    let value = storage.get("batch_key_1").unwrap().unwrap();
+    assert_eq!(value.payload, b"batch_value_1");
+    assert!(storage.get("batch_key_0").unwrap().is_none());
+}